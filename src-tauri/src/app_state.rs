@@ -1,6 +1,9 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::protocol::addressing::{AddressingMode, EcuAddress};
+use crate::topology::{build_topology, TopologyHints};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
@@ -19,6 +22,8 @@ pub enum AppPhase {
 pub enum TransportMode {
   Simulation,
   J2534,
+  Direct,
+  SocketCan,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -29,6 +34,27 @@ pub enum ModuleStatus {
   Error,
 }
 
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum DiagnosticSession {
+  #[default]
+  Default,
+  Programming,
+  Extended,
+  SafetySystem,
+}
+
+impl DiagnosticSession {
+  pub fn sub_function(&self) -> u8 {
+    match self {
+      DiagnosticSession::Default => 0x01,
+      DiagnosticSession::Programming => 0x02,
+      DiagnosticSession::Extended => 0x03,
+      DiagnosticSession::SafetySystem => 0x04,
+    }
+  }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ModuleInfo {
@@ -38,16 +64,107 @@ pub struct ModuleInfo {
   pub category: String,
   pub tx_id: u32,
   pub rx_id: u32,
+  #[serde(default)]
+  pub addressing: AddressingMode,
   pub status: ModuleStatus,
   pub dtc_count: usize,
+  #[serde(default)]
+  pub identification: Vec<DidValue>,
+  #[serde(default)]
+  pub session: DiagnosticSession,
+  #[serde(default)]
+  pub security_level: Option<u8>,
+  #[serde(default)]
+  pub vin_checks: Vec<VinCheck>,
+}
+
+impl ModuleInfo {
+  pub fn address(&self) -> EcuAddress {
+    EcuAddress {
+      tx_id: self.tx_id,
+      rx_id: self.rx_id,
+      mode: self.addressing,
+    }
+  }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DtcInfo {
   pub code: String,
+  #[serde(default)]
   pub description: String,
   pub status: String,
+  #[serde(default)]
+  pub status_byte: u8,
+  #[serde(default)]
+  pub status_flags: DtcStatus,
+  #[serde(default)]
+  pub permanent: bool,
+  #[serde(default)]
+  pub snapshots: Vec<DtcSnapshot>,
+  #[serde(default)]
+  pub extended_data: Vec<DtcExtendedData>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DtcSnapshot {
+  pub record_number: u8,
+  pub values: Vec<DidValue>,
+  #[serde(default)]
+  pub undecoded: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DtcExtendedData {
+  pub record_number: u8,
+  pub name: String,
+  pub value: String,
+  pub raw: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DidValue {
+  pub did: u16,
+  pub name: String,
+  pub value: String,
+  pub raw: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum VinCheckStatus {
+  Match,
+  Mismatch,
+  // Answered with all zeros, 0xFF or spaces: a replacement module that was never programmed.
+  Blank,
+  NotSupported,
+  NoResponse,
+}
+
+// One VIN-holding DID of a module compared against the vehicle VIN.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VinCheck {
+  pub did: u16,
+  pub vin: Option<String>,
+  pub status: VinCheckStatus,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct DtcStatus {
+  pub test_failed: bool,
+  pub test_failed_this_operation_cycle: bool,
+  pub pending: bool,
+  pub confirmed: bool,
+  pub test_not_completed_since_last_clear: bool,
+  pub test_failed_since_last_clear: bool,
+  pub test_not_completed_this_operation_cycle: bool,
+  pub warning_indicator_requested: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -55,12 +172,122 @@ pub struct DtcInfo {
 pub struct BusInfo {
   pub name: String,
   pub modules: Vec<String>,
+  #[serde(default)]
+  pub baud_rate: Option<u32>,
+  #[serde(default)]
+  pub addressing: Vec<BusAddressing>,
+  // The bus the tester is wired to through the DLC.
+  #[serde(default)]
+  pub tester: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+pub enum BusAddressing {
+  Normal11Bit,
+  Normal29Bit,
+  NormalFixed,
+  Extended,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TopologyNode {
+  pub id: String,
+  pub name: String,
+  pub bus: String,
+  pub category: String,
+  pub status: ModuleStatus,
+  pub dtc_count: usize,
+  pub gateway: bool,
+  // U-codes the module reports, to show lost-communication faults next to the bus they concern.
+  pub network_dtcs: Vec<String>,
 }
 
+// The gateway module routes diagnostic traffic from its own bus onto `to`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
+pub struct GatewayLink {
+  pub gateway: String,
+  pub from: String,
+  pub to: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct TopologyGraph {
   pub buses: Vec<BusInfo>,
+  #[serde(default)]
+  pub nodes: Vec<TopologyNode>,
+  #[serde(default)]
+  pub links: Vec<GatewayLink>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BaselineSource {
+  #[default]
+  Saved,
+  Profile,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BaselineStatus {
+  Match,
+  Missing,
+  Extra,
+  UnexpectedAddress,
+  UnexpectedBus,
+  IdentificationChanged,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentificationMismatch {
+  pub did: u16,
+  pub expected: String,
+  pub found: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BaselineEntry {
+  pub name: String,
+  // The module in `AppSnapshot.modules` this entry refers to.
+  pub module_id: Option<String>,
+  pub status: BaselineStatus,
+  pub expected_bus: Option<String>,
+  pub expected_address: Option<String>,
+  pub found_bus: Option<String>,
+  pub found_address: Option<String>,
+  #[serde(default)]
+  pub identification: Vec<IdentificationMismatch>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BaselineReport {
+  pub baseline_id: String,
+  pub name: String,
+  pub source: BaselineSource,
+  pub entries: Vec<BaselineEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VehicleInfo {
+  pub make: String,
+  pub model: String,
+  pub year: String,
+  #[serde(default)]
+  pub trim: String,
+  // Engine, body, drive, plant and the like; decoded VINs fill what their patterns cover.
+  #[serde(default)]
+  pub details: BTreeMap<String, String>,
+  // None where position 9 is not a check digit.
+  #[serde(default)]
+  pub check_digit_valid: Option<bool>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -94,7 +321,10 @@ pub struct AppSnapshot {
   pub phase: AppPhase,
   pub transport: TransportMode,
   pub adapter_connected: bool,
+  pub battery_voltage: Option<f32>,
   pub vin: Option<String>,
+  pub vehicle: Option<VehicleInfo>,
+  pub baseline: Option<BaselineReport>,
   pub modules: Vec<ModuleInfo>,
   pub dtcs: HashMap<String, Vec<DtcInfo>>,
   pub topology: TopologyGraph,
@@ -110,7 +340,10 @@ pub struct AppState {
   pub phase: AppPhase,
   pub transport: TransportMode,
   pub adapter_connected: bool,
+  pub battery_voltage: Option<f32>,
   pub vin: Option<String>,
+  pub vehicle: Option<VehicleInfo>,
+  pub baseline: Option<BaselineReport>,
   pub modules: Vec<ModuleInfo>,
   pub dtcs: HashMap<String, Vec<DtcInfo>>,
   pub topology: TopologyGraph,
@@ -119,6 +352,8 @@ pub struct AppState {
   pub session_id: Option<String>,
   pub logs_path: Option<String>,
   pub last_session: Option<SessionSummary>,
+  // Bus and gateway knowledge from the discovery profile, kept for topology rebuilds.
+  pub topology_hints: TopologyHints,
 }
 
 impl Default for AppState {
@@ -127,26 +362,37 @@ impl Default for AppState {
       phase: AppPhase::Disconnected,
       transport: TransportMode::Simulation,
       adapter_connected: false,
+      battery_voltage: None,
       vin: None,
+      vehicle: None,
+      baseline: None,
       modules: Vec::new(),
       dtcs: HashMap::new(),
-      topology: TopologyGraph { buses: Vec::new() },
+      topology: TopologyGraph::default(),
       progress: None,
       last_error: None,
       session_id: None,
       logs_path: None,
       last_session: None,
+      topology_hints: TopologyHints::default(),
     }
   }
 }
 
 impl AppState {
+  pub fn refresh_topology(&mut self) {
+    self.topology = build_topology(&self.modules, &self.dtcs, &self.topology_hints);
+  }
+
   pub fn snapshot(&self) -> AppSnapshot {
     AppSnapshot {
       phase: self.phase.clone(),
       transport: self.transport.clone(),
       adapter_connected: self.adapter_connected,
+      battery_voltage: self.battery_voltage,
       vin: self.vin.clone(),
+      vehicle: self.vehicle.clone(),
+      baseline: self.baseline.clone(),
       modules: self.modules.clone(),
       dtcs: self.dtcs.clone(),
       topology: self.topology.clone(),
@@ -0,0 +1,172 @@
+use crate::app_state::{
+  BaselineEntry, BaselineReport, BaselineStatus, IdentificationMismatch, ModuleInfo, ModuleStatus,
+};
+use crate::discovery::merge_inventory;
+
+use super::{Baseline, BaselineModule, BASELINE_DIDS};
+
+// Diffs the scan against the baseline; expected modules that never answered are added as NoResponse.
+pub fn compare(baseline: &Baseline, modules: &mut Vec<ModuleInfo>) -> BaselineReport {
+  let mut matched = vec![false; modules.len()];
+  let mut entries = Vec::new();
+  let mut missing = Vec::new();
+
+  for expected in &baseline.modules {
+    let index = find_module(modules, &matched, expected);
+    let found = index.map(|index| &modules[index]);
+    if let Some(index) = index {
+      matched[index] = true;
+    }
+
+    let Some(module) = found.filter(|module| module.status != ModuleStatus::NoResponse) else {
+      if found.is_none() {
+        missing.push(expected.candidate());
+      }
+      entries.push(BaselineEntry {
+        name: expected.name.clone(),
+        module_id: Some(
+          found.map_or_else(|| expected.address().label(), |module| module.id.clone()),
+        ),
+        status: BaselineStatus::Missing,
+        expected_bus: Some(expected.bus.clone()),
+        expected_address: Some(expected.address().label()),
+        found_bus: None,
+        found_address: None,
+        identification: Vec::new(),
+      });
+      continue;
+    };
+
+    let identification = expected
+      .identification
+      .iter()
+      .filter_map(|(did, value)| {
+        let did = u16::from_str_radix(did.trim_start_matches("0x"), 16)
+          .ok()
+          .filter(|did| BASELINE_DIDS.contains(did))?;
+        let current = module
+          .identification
+          .iter()
+          .find(|entry| entry.did == did)
+          .map(|entry| entry.value.clone());
+        (current.as_deref().map(str::trim) != Some(value.trim())).then(|| IdentificationMismatch {
+          did,
+          expected: value.clone(),
+          found: current,
+        })
+      })
+      .collect::<Vec<_>>();
+    let status = if module.address() != expected.address() {
+      BaselineStatus::UnexpectedAddress
+    } else if module.bus != expected.bus {
+      BaselineStatus::UnexpectedBus
+    } else if !identification.is_empty() {
+      BaselineStatus::IdentificationChanged
+    } else {
+      BaselineStatus::Match
+    };
+    entries.push(BaselineEntry {
+      name: expected.name.clone(),
+      module_id: Some(module.id.clone()),
+      status,
+      expected_bus: Some(expected.bus.clone()),
+      expected_address: Some(expected.address().label()),
+      found_bus: Some(module.bus.clone()),
+      found_address: Some(module.address().label()),
+      identification,
+    });
+  }
+
+  for (module, _) in modules
+    .iter()
+    .zip(&matched)
+    .filter(|(module, matched)| !**matched && module.status != ModuleStatus::NoResponse)
+  {
+    entries.push(BaselineEntry {
+      name: module.name.clone(),
+      module_id: Some(module.id.clone()),
+      status: BaselineStatus::Extra,
+      expected_bus: None,
+      expected_address: None,
+      found_bus: Some(module.bus.clone()),
+      found_address: Some(module.address().label()),
+      identification: Vec::new(),
+    });
+  }
+
+  merge_inventory(modules, &missing);
+  BaselineReport {
+    baseline_id: baseline.id.clone(),
+    name: baseline.name.clone(),
+    source: baseline.source,
+    entries,
+  }
+}
+
+// Same address first, then the same name at another address.
+fn find_module(
+  modules: &[ModuleInfo],
+  matched: &[bool],
+  expected: &BaselineModule,
+) -> Option<usize> {
+  let address = expected.address();
+  let free = |index: &usize| !matched[*index];
+  (0..modules.len())
+    .filter(free)
+    .find(|index| modules[*index].address() == address)
+    .or_else(|| {
+      (0..modules.len())
+        .filter(free)
+        .find(|index| modules[*index].name.eq_ignore_ascii_case(&expected.name))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::app_state::{BaselineStatus, DiagnosticSession, DidValue};
+  use crate::protocol::addressing::AddressingMode;
+
+  fn scan(serial: &str, software: &str) -> Vec<ModuleInfo> {
+    let did = |did: u16, value: &str| DidValue {
+      did,
+      name: String::new(),
+      value: value.to_string(),
+      raw: String::new(),
+    };
+    vec![ModuleInfo {
+      id: "pcm".to_string(),
+      name: "PCM".to_string(),
+      bus: "HS-CAN".to_string(),
+      category: "powertrain".to_string(),
+      tx_id: 0x7E0,
+      rx_id: 0x7E8,
+      addressing: AddressingMode::default(),
+      status: ModuleStatus::Ok,
+      dtc_count: 0,
+      identification: vec![
+        did(0xF188, software),
+        did(0xF18B, "2019-03-14"),
+        did(0xF18C, serial),
+      ],
+      session: DiagnosticSession::Default,
+      security_level: None,
+      vin_checks: Vec::new(),
+    }]
+  }
+
+  #[test]
+  fn serial_number_is_not_compared() {
+    let baseline = Baseline::from_scan("F-250", None, &scan("SN0001", "KB3A-14C204-AB"));
+    assert!(!baseline.modules[0].identification.contains_key("F18C"));
+
+    let report = compare(&baseline, &mut scan("SN0002", "KB3A-14C204-AB"));
+    assert_eq!(report.entries[0].status, BaselineStatus::Match);
+
+    let report = compare(&baseline, &mut scan("SN0002", "KB3A-14C204-AC"));
+    assert_eq!(
+      report.entries[0].status,
+      BaselineStatus::IdentificationChanged
+    );
+  }
+}
@@ -0,0 +1,257 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Runtime};
+
+use crate::app_state::{BaselineSource, ModuleInfo, ModuleStatus};
+use crate::discovery::ModuleCandidate;
+use crate::profiles::DiscoveryProfile;
+use crate::protocol::addressing::{AddressingMode, EcuAddress};
+
+mod compare;
+
+pub use compare::compare;
+
+pub const BASELINE_DIR: &str = "baselines";
+
+// Part, software and hardware numbers; serial number and manufacturing date differ between identical builds.
+pub const BASELINE_DIDS: &[u16] = &[0xF187, 0xF188, 0xF189, 0xF191, 0xF192, 0xF110, 0xF111, 0xF113];
+
+// The modules a known-good vehicle is built with.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Baseline {
+  pub id: String,
+  pub name: String,
+  // Compared position by position from the start of the VIN; '?' matches any character.
+  #[serde(default)]
+  pub vin_pattern: Option<String>,
+  #[serde(default)]
+  pub created: Option<DateTime<Utc>>,
+  pub modules: Vec<BaselineModule>,
+  #[serde(default, skip_deserializing)]
+  pub source: BaselineSource,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BaselineModule {
+  pub name: String,
+  pub bus: String,
+  pub category: String,
+  pub tx_id: u32,
+  pub rx_id: u32,
+  #[serde(default)]
+  pub addressing: AddressingMode,
+  // Identification values keyed by hex DID, as read from the known-good vehicle.
+  #[serde(default)]
+  pub identification: BTreeMap<String, String>,
+}
+
+impl BaselineModule {
+  pub fn address(&self) -> EcuAddress {
+    EcuAddress {
+      tx_id: self.tx_id,
+      rx_id: self.rx_id,
+      mode: self.addressing,
+    }
+  }
+
+  pub fn candidate(&self) -> ModuleCandidate {
+    ModuleCandidate {
+      tx_id: self.tx_id,
+      rx_id: self.rx_id,
+      addressing: self.addressing,
+      name: self.name.clone(),
+      bus: self.bus.clone(),
+      category: self.category.clone(),
+    }
+  }
+}
+
+impl Baseline {
+  // Everything that answered, with its identification, as the expected build for this VIN pattern.
+  pub fn from_scan(name: &str, vin_pattern: Option<String>, modules: &[ModuleInfo]) -> Self {
+    Self {
+      id: uuid::Uuid::new_v4().to_string(),
+      name: name.to_string(),
+      vin_pattern,
+      created: Some(Utc::now()),
+      modules: modules
+        .iter()
+        .filter(|module| module.status != ModuleStatus::NoResponse)
+        .map(|module| BaselineModule {
+          name: module.name.clone(),
+          bus: module.bus.clone(),
+          category: module.category.clone(),
+          tx_id: module.tx_id,
+          rx_id: module.rx_id,
+          addressing: module.addressing,
+          identification: module
+            .identification
+            .iter()
+            .filter(|value| BASELINE_DIDS.contains(&value.did))
+            .map(|value| (format!("{:04X}", value.did), value.value.clone()))
+            .collect(),
+        })
+        .collect(),
+      source: BaselineSource::Saved,
+    }
+  }
+
+  // Only profiles that mark their module list as the expected build yield a baseline.
+  pub fn from_profile(profile: &DiscoveryProfile) -> Option<Self> {
+    if !profile.baseline {
+      return None;
+    }
+    Some(Self {
+      id: format!("profile:{}", profile.id),
+      name: profile.name.clone(),
+      vin_pattern: None,
+      created: None,
+      modules: profile
+        .modules
+        .iter()
+        .map(|module| BaselineModule {
+          name: module.name.clone(),
+          bus: module.bus.clone(),
+          category: module.category.clone(),
+          tx_id: module.tx_id,
+          rx_id: module.rx_id,
+          addressing: module.addressing,
+          identification: BTreeMap::new(),
+        })
+        .collect(),
+      source: BaselineSource::Profile,
+    })
+  }
+
+  // Positions the pattern pins down, or None when it does not match.
+  fn match_score(&self, vin: &str) -> Option<usize> {
+    let pattern = self.vin_pattern.as_deref()?;
+    if pattern.len() > vin.len() {
+      return None;
+    }
+    let mut score = 0;
+    for (expected, actual) in pattern.chars().zip(vin.chars()) {
+      if expected == '?' {
+        continue;
+      }
+      if !expected.eq_ignore_ascii_case(&actual) {
+        return None;
+      }
+      score += 1;
+    }
+    Some(score)
+  }
+
+  fn validate(&self) -> Result<(), String> {
+    if self.id.is_empty()
+      || !self
+        .id
+        .chars()
+        .all(|ch| ch.is_ascii_alphanumeric() || ch == '-')
+    {
+      return Err(format!("Invalid baseline id '{}'", self.id));
+    }
+    if self.name.trim().is_empty() {
+      return Err(format!("Baseline {} has no name", self.id));
+    }
+    Ok(())
+  }
+}
+
+// WMI and VDS plus model year and plant, with the check digit left open; the serial is dropped.
+pub fn default_vin_pattern(vin: &str) -> Option<String> {
+  if vin.len() != 17 || !vin.is_ascii() {
+    return None;
+  }
+  Some(format!("{}?{}", &vin[0..8], &vin[9..11]))
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BaselineStore {
+  baselines: Vec<Baseline>,
+}
+
+impl BaselineStore {
+  pub fn load<R: Runtime>(app: &AppHandle<R>) -> (Self, Vec<String>) {
+    let mut store = Self::default();
+    let errors = match baseline_dir(app) {
+      Some(dir) if dir.is_dir() => store.load_dir(&dir),
+      _ => Vec::new(),
+    };
+    (store, errors)
+  }
+
+  pub fn load_dir(&mut self, dir: &Path) -> Vec<String> {
+    let mut paths = match fs::read_dir(dir) {
+      Ok(entries) => entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect::<Vec<_>>(),
+      Err(err) => {
+        return vec![format!(
+          "Failed to read baseline dir {}: {err}",
+          dir.display()
+        )]
+      }
+    };
+    paths.sort();
+
+    let mut errors = Vec::new();
+    for path in paths {
+      let baseline = fs::read_to_string(&path)
+        .map_err(|err| err.to_string())
+        .and_then(|contents| {
+          serde_json::from_str::<Baseline>(&contents).map_err(|err| err.to_string())
+        })
+        .and_then(|baseline| baseline.validate().map(|_| baseline));
+      match baseline {
+        Ok(baseline) => self.baselines.push(baseline),
+        Err(err) => errors.push(format!("Invalid baseline {}: {err}", path.display())),
+      }
+    }
+    errors
+  }
+
+  pub fn baselines(&self) -> &[Baseline] {
+    &self.baselines
+  }
+
+  // The saved baseline whose VIN pattern pins down the most positions; ties go to the newest.
+  pub fn best_match(&self, vin: &str) -> Option<&Baseline> {
+    self
+      .baselines
+      .iter()
+      .filter_map(|baseline| Some(((baseline.match_score(vin)?, baseline.created), baseline)))
+      .max_by_key(|(rank, _)| *rank)
+      .map(|(_, baseline)| baseline)
+  }
+}
+
+pub fn baseline_dir<R: Runtime>(app: &AppHandle<R>) -> Option<PathBuf> {
+  let base = tauri::api::path::app_data_dir(&app.config())?;
+  Some(base.join(BASELINE_DIR))
+}
+
+pub fn save_user_baseline(app: &AppHandle, baseline: &Baseline) -> Result<(), String> {
+  baseline.validate()?;
+  let dir = baseline_dir(app).ok_or_else(|| "Missing app data dir".to_string())?;
+  fs::create_dir_all(&dir).map_err(|err| format!("Failed to create baseline dir: {err}"))?;
+  let contents = serde_json::to_string_pretty(baseline).map_err(|err| err.to_string())?;
+  fs::write(dir.join(format!("{}.json", baseline.id)), contents)
+    .map_err(|err| format!("Failed to save baseline {}: {err}", baseline.name))
+}
+
+pub fn delete_user_baseline(app: &AppHandle, baseline_id: &str) -> Result<(), String> {
+  let dir = baseline_dir(app).ok_or_else(|| "Missing app data dir".to_string())?;
+  let path = dir.join(format!("{baseline_id}.json"));
+  if baseline_id.contains(['/', '\\', '.']) || !path.exists() {
+    return Err(format!("No baseline {baseline_id}"));
+  }
+  fs::remove_file(&path).map_err(|err| format!("Failed to delete baseline {baseline_id}: {err}"))
+}
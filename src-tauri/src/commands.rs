@@ -1,17 +1,32 @@
 use std::fs;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 use chrono::Utc;
 use serde::Serialize;
 use serde_json::json;
 use tauri::{AppHandle, State};
 
-use crate::app_state::{AppSnapshot, ErrorInfo, ProgressInfo, TransportMode};
+use crate::app_state::{
+  AppSnapshot, DiagnosticSession, ErrorInfo, ModuleInfo, ModuleStatus, ProgressInfo,
+  TransportMode,
+};
+use crate::baseline::{
+  default_vin_pattern, delete_user_baseline, save_user_baseline, Baseline, BaselineStore,
+};
+use crate::discovery::DiscoveryConfig;
 use crate::logger::{LogEvent, LogKind};
-use crate::protocol::uds::UdsClient;
-use crate::runtime::AppRuntime;
-use crate::scanner::run_scan;
-use crate::transport::VLinkerFsJ2534Transport;
+use crate::profiles::{
+  delete_user_profile, load_selection, save_selection, save_user_profile, DiscoveryProfile, ProfileStore,
+};
+use crate::protocol::uds::{unlock, Nrc, UdsError};
+use crate::runtime::{load_device_selection, save_device_selection, AppRuntime};
+use crate::scanner::{run_scan, uds_client, AdapterOptions};
+use crate::topology::{self, TopologyFormat};
+use crate::transport::{
+  AdapterVersions, J2534Device, TimingConfig, Transport, VLinkerFsJ2534Transport,
+};
 
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -19,6 +34,33 @@ pub struct AdapterStatus {
   pub available: bool,
   pub message: String,
   pub dll_path: Option<String>,
+  pub battery_voltage: Option<f32>,
+  pub versions: Option<AdapterVersions>,
+  // Why the device could not be queried, worded by the driver when it says.
+  pub device_error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BaselineList {
+  pub baselines: Vec<Baseline>,
+  pub errors: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct J2534DeviceList {
+  pub devices: Vec<J2534Device>,
+  pub selected: Option<String>,
+  pub errors: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileList {
+  pub profiles: Vec<DiscoveryProfile>,
+  pub selected: Option<String>,
+  pub errors: Vec<String>,
 }
 
 #[tauri::command]
@@ -27,17 +69,41 @@ pub fn get_snapshot(state: State<Arc<AppRuntime>>) -> AppSnapshot {
 }
 
 #[tauri::command]
-pub fn get_adapter_status() -> AdapterStatus {
-  match VLinkerFsJ2534Transport::probe() {
-    Ok(path) => AdapterStatus {
-      available: true,
-      message: "Adapter driver detected".to_string(),
-      dll_path: Some(path.to_string_lossy().to_string()),
-    },
+pub fn get_adapter_status(app: AppHandle, state: State<Arc<AppRuntime>>) -> AdapterStatus {
+  let runtime = state.inner().clone();
+  let driver = selected_j2534_library(&app).map_or_else(VLinkerFsJ2534Transport::probe, Ok);
+  match driver {
+    Ok(path) => {
+      // A J2534 session's transport owns the device; otherwise the driver opens it for each reading.
+      let live = runtime.state.lock().transport == TransportMode::J2534;
+      let mut session = runtime.transport.lock();
+      let mut standalone = VLinkerFsJ2534Transport::new(Some(path.clone()));
+      let transport: &mut dyn Transport = match session.as_mut() {
+        Some(transport) if live => transport.as_mut(),
+        _ => &mut standalone,
+      };
+      let versions = transport.adapter_versions();
+      let battery_voltage = transport.battery_voltage().ok();
+      drop(session);
+      if battery_voltage.is_some() {
+        runtime.update_state(&app, |state| state.battery_voltage = battery_voltage);
+      }
+      AdapterStatus {
+        available: true,
+        message: "Adapter driver detected".to_string(),
+        dll_path: Some(path.to_string_lossy().to_string()),
+        battery_voltage,
+        device_error: versions.as_ref().err().cloned(),
+        versions: versions.ok(),
+      }
+    }
     Err(err) => AdapterStatus {
       available: false,
       message: err,
       dll_path: None,
+      battery_voltage: None,
+      versions: None,
+      device_error: None,
     },
   }
 }
@@ -48,10 +114,21 @@ pub fn start_scan(
   state: State<Arc<AppRuntime>>,
   mode: TransportMode,
   simulation_path: Option<String>,
+  device: Option<String>,
+  native_isotp: Option<bool>,
+  discovery: Option<DiscoveryConfig>,
 ) -> Result<(), String> {
   let runtime = state.inner().clone();
+  let discovery = discovery.unwrap_or_default();
   tauri::async_runtime::spawn(async move {
-    if let Err(err) = run_scan(app.clone(), runtime.clone(), mode, simulation_path, vec![]).await {
+    let adapter = AdapterOptions {
+      device,
+      dll_path: selected_j2534_library(&app),
+      native_isotp: native_isotp.unwrap_or(false),
+    };
+    let result =
+      run_scan(app.clone(), runtime.clone(), mode, simulation_path, adapter, vec![], discovery).await;
+    if let Err(err) = result {
       runtime.update_state(&app, |state| {
         state.phase = crate::app_state::AppPhase::Error;
         state.last_error = Some(ErrorInfo {
@@ -75,11 +152,10 @@ pub fn start_scan(
 #[tauri::command]
 pub async fn clear_dtcs(
   app: AppHandle,
-  state: State<Arc<AppRuntime>>,
+  state: State<'_, Arc<AppRuntime>>,
   module_id: Option<String>,
 ) -> Result<(), String> {
   let runtime = state.inner().clone();
-  let snapshot = runtime.snapshot();
 
   runtime.update_state(&app, |state| {
     state.progress = Some(ProgressInfo {
@@ -89,51 +165,40 @@ pub async fn clear_dtcs(
     });
   });
 
-  if snapshot.transport == TransportMode::Simulation {
-    runtime.update_state(&app, |state| {
-      if let Some(ref module_id) = module_id {
-        state.dtcs.insert(module_id.clone(), Vec::new());
-      } else {
-        for dtcs in state.dtcs.values_mut() {
-          dtcs.clear();
-        }
-      }
-      for info in state.modules.iter_mut() {
-        if let Some(dtcs) = state.dtcs.get(&info.id) {
-          info.dtc_count = dtcs.len();
-        }
-      }
-      state.progress = None;
-    });
-
-    runtime.log_event(LogEvent {
-      timestamp: Utc::now(),
-      level: "info".to_string(),
-      kind: LogKind::Protocol,
-      message: "Cleared DTCs (simulation)".to_string(),
-      payload: json!({ "module": module_id }),
-    });
-
-    return Ok(());
-  }
-
   let modules_to_clear = {
     let state = runtime.state.lock();
     state
       .modules
       .iter()
       .filter(|module| module_id.as_ref().map(|id| id == &module.id).unwrap_or(true))
+      .filter(|module| module.status != ModuleStatus::NoResponse)
       .cloned()
       .collect::<Vec<_>>()
   };
 
+  let mode = runtime.state.lock().transport.clone();
   let mut transport_guard = runtime.transport.lock();
   let transport = transport_guard.take().ok_or_else(|| "No active transport".to_string())?;
-  let mut uds = UdsClient::new(transport, 500, 1);
+  let mut uds = uds_client(&mode, transport);
 
   for (index, module) in modules_to_clear.iter().enumerate() {
-    let result = uds.clear_dtcs(module.tx_id, module.rx_id);
-    let error = result.as_ref().err().cloned();
+    let mut result = uds
+      .use_timing(runtime.module_timing(&module.id))
+      .and_then(|_| uds.clear_dtcs(module.address()));
+    let mut session = module.session;
+    // Some modules only accept a clear from the extended session.
+    if needs_extended_session(&result) && session == DiagnosticSession::Default {
+      match uds.start_session(module.address(), DiagnosticSession::Extended) {
+        Ok(timing) => {
+          session = DiagnosticSession::Extended;
+          log_session_change(&runtime, module, session, Some(&timing), None);
+          runtime.set_module_timing(&module.id, session, timing);
+          result = uds.clear_dtcs(module.address());
+        }
+        Err(err) => log_session_change(&runtime, module, DiagnosticSession::Extended, None, Some(&err)),
+      }
+    }
+    let error = result.as_ref().err().map(|err| err.to_string());
     runtime.log_event(LogEvent {
       timestamp: Utc::now(),
       level: if result.is_ok() { "info" } else { "warn" }.to_string(),
@@ -152,11 +217,15 @@ pub async fn clear_dtcs(
       if result.is_ok() {
         state.dtcs.insert(module.id.clone(), Vec::new());
       }
+      if let Some(info) = state.modules.iter_mut().find(|info| info.id == module.id) {
+        info.session = session;
+      }
       for info in state.modules.iter_mut() {
         if let Some(dtcs) = state.dtcs.get(&info.id) {
           info.dtc_count = dtcs.len();
         }
       }
+      state.refresh_topology();
     });
   }
 
@@ -165,27 +234,295 @@ pub async fn clear_dtcs(
   });
 
   *transport_guard = Some(Box::new(uds.into_transport()));
+  drop(transport_guard);
+  runtime.ensure_keep_alive();
 
   Ok(())
 }
 
 #[tauri::command]
-pub fn export_logs(state: State<Arc<AppRuntime>>, destination: String) -> Result<(), String> {
-  let logger = state
-    .inner()
-    .logger
+pub async fn set_diagnostic_session(
+  app: AppHandle,
+  state: State<'_, Arc<AppRuntime>>,
+  module_id: String,
+  session: DiagnosticSession,
+) -> Result<(), String> {
+  let runtime = state.inner().clone();
+  let module = runtime
+    .state
+    .lock()
+    .modules
+    .iter()
+    .find(|module| module.id == module_id)
+    .cloned()
+    .ok_or_else(|| format!("Unknown module {module_id}"))?;
+
+  let mode = runtime.state.lock().transport.clone();
+  let mut transport_guard = runtime.transport.lock();
+  let transport = transport_guard.take().ok_or_else(|| "No active transport".to_string())?;
+  let mut uds = uds_client(&mode, transport);
+  let result = uds
+    .use_timing(runtime.module_timing(&module.id))
+    .and_then(|_| uds.start_session(module.address(), session));
+  *transport_guard = Some(Box::new(uds.into_transport()));
+  drop(transport_guard);
+
+  match result {
+    Ok(timing) => {
+      log_session_change(&runtime, &module, session, Some(&timing), None);
+      runtime.set_module_timing(&module.id, session, timing);
+      runtime.update_state(&app, |state| {
+        if let Some(info) = state.modules.iter_mut().find(|info| info.id == module.id) {
+          info.session = session;
+          info.security_level = None;
+        }
+      });
+      runtime.ensure_keep_alive();
+      Ok(())
+    }
+    Err(err) => {
+      log_session_change(&runtime, &module, session, None, Some(&err));
+      Err(err.to_string())
+    }
+  }
+}
+
+#[tauri::command]
+pub async fn security_access(
+  app: AppHandle,
+  state: State<'_, Arc<AppRuntime>>,
+  module_id: String,
+  level: u8,
+) -> Result<(), String> {
+  let runtime = state.inner().clone();
+  let (module, vin) = {
+    let state = runtime.state.lock();
+    let module = state
+      .modules
+      .iter()
+      .find(|module| module.id == module_id)
+      .cloned()
+      .ok_or_else(|| format!("Unknown module {module_id}"))?;
+    (module, state.vin.clone().unwrap_or_default())
+  };
+  let make = runtime.vehicle_make(&vin);
+  let algorithm = runtime
+    .security
     .lock()
+    .find(make.as_deref(), &[&module.id, &module.name], level)
+    .ok_or_else(|| format!("No seed-key algorithm configured for {} level 0x{level:02X}", module.name))?;
+
+  if runtime.transport.lock().is_none() {
+    return Err("No active transport".to_string());
+  }
+  let mode = runtime.state.lock().transport.clone();
+  let address = module.address();
+  // Lockout delays can run past a minute; the transport is only taken per attempt, so the keep-alive
+  // thread holds every module's session meanwhile.
+  runtime.ensure_keep_alive();
+  let (result, attempts) = {
+    let runtime = runtime.clone();
+    let module_id = module.id.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+      unlock(
+        level,
+        algorithm.as_ref(),
+        || {
+          let mut transport_guard = runtime.transport.lock();
+          let Some(transport) = transport_guard.take() else {
+            return ("seed", Err(UdsError::Transport("No active transport".to_string())));
+          };
+          let mut uds = uds_client(&mode, transport);
+          let result = match uds.use_timing(runtime.module_timing(&module_id)) {
+            Ok(()) => uds.security_attempt(address, level, algorithm.as_ref()),
+            Err(err) => ("seed", Err(err)),
+          };
+          *transport_guard = Some(Box::new(uds.into_transport()));
+          result
+        },
+        |delay_ms| std::thread::sleep(Duration::from_millis(delay_ms)),
+      )
+    })
+    .await
+    .map_err(|err| err.to_string())?
+  };
+
+  for attempt in &attempts {
+    runtime.log_event(LogEvent {
+      timestamp: Utc::now(),
+      level: match attempt.outcome.as_str() {
+        "unlocked" | "alreadyUnlocked" => "info",
+        _ => "warn",
+      }
+      .to_string(),
+      kind: LogKind::Protocol,
+      message: "Security access".to_string(),
+      payload: json!({
+        "module": module.id,
+        "attempt": attempt,
+      }),
+    });
+  }
+
+  result.map_err(|err| err.to_string())?;
+  runtime.update_state(&app, |state| {
+    if let Some(info) = state.modules.iter_mut().find(|info| info.id == module.id) {
+      info.security_level = Some(level);
+    }
+  });
+  Ok(())
+}
+
+#[tauri::command]
+pub fn list_profiles(app: AppHandle) -> ProfileList {
+  let (store, errors) = ProfileStore::load(&app);
+  ProfileList {
+    profiles: store.profiles().to_vec(),
+    selected: load_selection(&app),
+    errors,
+  }
+}
+
+#[tauri::command]
+pub fn list_j2534_devices(app: AppHandle) -> J2534DeviceList {
+  let (devices, errors) = crate::transport::list_j2534_devices();
+  J2534DeviceList {
+    devices,
+    selected: load_device_selection(&app),
+    errors,
+  }
+}
+
+// None goes back to the automatic driver lookup.
+#[tauri::command]
+pub fn select_j2534_device(app: AppHandle, library: Option<String>) -> Result<(), String> {
+  if let Some(library) = &library {
+    let (devices, _) = crate::transport::list_j2534_devices();
+    if !devices.iter().any(|device| &device.library == library) {
+      return Err(format!("Unknown J2534 driver {library}"));
+    }
+  }
+  save_device_selection(&app, library)
+}
+
+// A remembered driver that has since been uninstalled falls back to the automatic lookup.
+fn selected_j2534_library(app: &AppHandle) -> Option<PathBuf> {
+  load_device_selection(app)
+    .map(PathBuf::from)
+    .filter(|path| path.exists())
+}
+
+// None goes back to picking the profile from the VIN.
+#[tauri::command]
+pub fn select_profile(app: AppHandle, profile_id: Option<String>) -> Result<(), String> {
+  if let Some(id) = &profile_id {
+    let (store, _) = ProfileStore::load(&app);
+    if store.get(id).is_none() {
+      return Err(format!("Unknown profile {id}"));
+    }
+  }
+  save_selection(&app, profile_id)
+}
+
+#[tauri::command]
+pub fn save_profile(app: AppHandle, profile: DiscoveryProfile) -> Result<(), String> {
+  save_user_profile(&app, &profile)
+}
+
+#[tauri::command]
+pub fn delete_profile(app: AppHandle, profile_id: String) -> Result<(), String> {
+  delete_user_profile(&app, &profile_id)?;
+  let (store, _) = ProfileStore::load(&app);
+  if load_selection(&app).is_some_and(|selected| store.get(&selected).is_none()) {
+    save_selection(&app, None)?;
+  }
+  Ok(())
+}
+
+#[tauri::command]
+pub fn list_baselines(app: AppHandle) -> BaselineList {
+  let (store, errors) = BaselineStore::load(&app);
+  BaselineList {
+    baselines: store.baselines().to_vec(),
+    errors,
+  }
+}
+
+// Saves the current scan as the expected build; the VIN pattern defaults to the VIN minus its serial.
+#[tauri::command]
+pub fn save_baseline(
+  app: AppHandle,
+  state: State<Arc<AppRuntime>>,
+  name: String,
+  vin_pattern: Option<String>,
+) -> Result<Baseline, String> {
+  let snapshot = state.inner().snapshot();
+  if snapshot.modules.is_empty() {
+    return Err("No scan to save as a baseline".to_string());
+  }
+  let vin_pattern = vin_pattern.or_else(|| snapshot.vin.as_deref().and_then(default_vin_pattern));
+  let baseline = Baseline::from_scan(&name, vin_pattern, &snapshot.modules);
+  save_user_baseline(&app, &baseline)?;
+  Ok(baseline)
+}
+
+#[tauri::command]
+pub fn delete_baseline(app: AppHandle, baseline_id: String) -> Result<(), String> {
+  delete_user_baseline(&app, &baseline_id)
+}
+
+fn needs_extended_session(result: &Result<(), UdsError>) -> bool {
+  matches!(
+    result.as_ref().err().and_then(UdsError::nrc),
+    Some(Nrc::ConditionsNotCorrect | Nrc::ServiceNotSupportedInActiveSession)
+  )
+}
+
+fn log_session_change(
+  runtime: &AppRuntime,
+  module: &ModuleInfo,
+  session: DiagnosticSession,
+  timing: Option<&TimingConfig>,
+  error: Option<&UdsError>,
+) {
+  runtime.log_event(LogEvent {
+    timestamp: Utc::now(),
+    level: if error.is_none() { "info" } else { "warn" }.to_string(),
+    kind: LogKind::Protocol,
+    message: "Diagnostic session".to_string(),
+    payload: json!({
+      "module": module.id,
+      "session": session,
+      "p2Ms": timing.map(|timing| timing.p2_ms),
+      "p2StarMs": timing.map(|timing| timing.p2_star_ms),
+      "error": error.map(|err| err.to_string()),
+    }),
+  });
+}
+
+#[tauri::command]
+pub fn export_logs(state: State<Arc<AppRuntime>>, destination: String) -> Result<(), String> {
+  let guard = state.inner().logger.lock();
+  let logger = guard
     .as_ref()
     .ok_or_else(|| "No active log session".to_string())?;
   logger.copy_to(destination)
 }
 
+#[tauri::command]
+pub fn export_topology(
+  state: State<Arc<AppRuntime>>,
+  destination: String,
+  format: TopologyFormat,
+) -> Result<(), String> {
+  let contents = topology::export_topology(&state.inner().snapshot().topology, format)?;
+  fs::write(&destination, contents).map_err(|err| format!("Failed to export topology: {err}"))
+}
+
 #[tauri::command]
 pub fn read_log_tail(state: State<Arc<AppRuntime>>, lines: usize) -> Result<String, String> {
-  let logger = state
-    .inner()
-    .logger
-    .lock()
+  let guard = state.inner().logger.lock();
+  let logger = guard
     .as_ref()
     .ok_or_else(|| "No active log session".to_string())?;
   let path = logger.path_str();
@@ -0,0 +1,283 @@
+use serde::{Deserialize, Serialize};
+
+use crate::app_state::{ModuleInfo, ModuleStatus};
+use crate::protocol::addressing::{AddressingMode, EcuAddress};
+use crate::protocol::uds::{UdsClient, UdsError};
+use crate::transport::Transport;
+
+use super::sweep::module_info;
+use super::ModuleCandidate;
+
+// Where a profile's gateway keeps its list of fitted modules and how that list is encoded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GatewaySpec {
+  pub tx_id: u32,
+  pub rx_id: u32,
+  #[serde(default)]
+  pub addressing: AddressingMode,
+  pub source: InventorySource,
+  pub format: InventoryFormat,
+}
+
+impl GatewaySpec {
+  pub fn address(&self) -> EcuAddress {
+    EcuAddress {
+      tx_id: self.tx_id,
+      rx_id: self.rx_id,
+      mode: self.addressing,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum InventorySource {
+  Did {
+    did: u16,
+  },
+  // RoutineControl start; the status record after the routine ID carries the list.
+  #[serde(rename_all = "camelCase")]
+  Routine {
+    routine_id: u16,
+    #[serde(default)]
+    option: Vec<u8>,
+  },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum InventoryFormat {
+  // Big-endian request IDs of `width` bytes; zero entries are padding.
+  #[serde(rename_all = "camelCase")]
+  IdList {
+    width: usize,
+    rx_offset: i64,
+  },
+  // Bit n, most significant bit of the first byte first, marks modules[n] as fitted.
+  Bitmap {
+    modules: Vec<ModuleCandidate>,
+  },
+}
+
+// Modules the gateway reports as fitted; ID-list entries take their names from `catalog`.
+pub fn gateway_inventory<T: Transport>(
+  uds: &mut UdsClient<T>,
+  spec: &GatewaySpec,
+  catalog: &[ModuleCandidate],
+) -> Result<Vec<ModuleCandidate>, UdsError> {
+  let address = spec.address();
+  let data = match &spec.source {
+    InventorySource::Did { did } => uds.read_did(address, *did)?,
+    InventorySource::Routine { routine_id, option } => {
+      uds.start_routine(address, *routine_id, option)?
+    }
+  };
+
+  match &spec.format {
+    InventoryFormat::IdList { width, rx_offset } => {
+      if !(1..=4).contains(width) || data.len() % width != 0 {
+        return Err(UdsError::UnexpectedResponse(format!(
+          "Gateway module list of {} bytes does not split into {width}-byte IDs",
+          data.len()
+        )));
+      }
+      Ok(
+        data
+          .chunks_exact(*width)
+          .map(|entry| {
+            entry
+              .iter()
+              .fold(0u32, |acc, byte| (acc << 8) | *byte as u32)
+          })
+          .filter(|tx_id| *tx_id != 0)
+          .filter_map(|tx_id| {
+            if let Some(known) = catalog.iter().find(|known| known.tx_id == tx_id) {
+              return Some(known.clone());
+            }
+            let address = EcuAddress::normal(tx_id, u32::try_from(tx_id as i64 + rx_offset).ok()?);
+            Some(ModuleCandidate {
+              tx_id,
+              rx_id: address.rx_id,
+              addressing: AddressingMode::Normal,
+              name: format!("ECU {}", address.label()),
+              bus: "Unknown".to_string(),
+              category: "Unknown".to_string(),
+            })
+          })
+          .collect(),
+      )
+    }
+    InventoryFormat::Bitmap { modules } => Ok(
+      modules
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| {
+          data
+            .get(index / 8)
+            .is_some_and(|byte| byte & (0x80 >> (index % 8)) != 0)
+        })
+        .map(|(_, module)| module.clone())
+        .collect(),
+    ),
+  }
+}
+
+// Fitted modules that never answered the probes are listed as NoResponse.
+pub fn merge_inventory(modules: &mut Vec<ModuleInfo>, fitted: &[ModuleCandidate]) {
+  for candidate in fitted {
+    let address = candidate.address();
+    if modules.iter().any(|module| module.address() == address) {
+      continue;
+    }
+    let mut info = module_info(
+      address,
+      &candidate.name,
+      &candidate.bus,
+      &candidate.category,
+    );
+    info.status = ModuleStatus::NoResponse;
+    modules.push(info);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+
+  use super::*;
+  use crate::simulation::SimulationSession;
+  use crate::transport::SimTransport;
+
+  // A gateway at 0x716/0x71E answering the given raw DIDs and routine status records.
+  fn gateway(raw_dids: serde_json::Value, routines: serde_json::Value) -> UdsClient<SimTransport> {
+    let session: SimulationSession = serde_json::from_value(json!({
+      "vin": "1FT7W2BT6KED12345",
+      "vehicle": { "make": "Ford", "model": "F-250", "year": "2019" },
+      "modules": [{
+        "id": "GWM",
+        "name": "Gateway Module",
+        "bus": "HS-CAN",
+        "category": "Network",
+        "txId": 0x716,
+        "rxId": 0x71E,
+        "dtcs": [],
+        "rawDids": raw_dids,
+        "routines": routines
+      }]
+    }))
+    .unwrap();
+    let mut uds = UdsClient::new(SimTransport::from_session(&session), 20, 0);
+    uds.open().unwrap();
+    uds
+  }
+
+  fn spec(source: InventorySource, format: InventoryFormat) -> GatewaySpec {
+    GatewaySpec {
+      tx_id: 0x716,
+      rx_id: 0x71E,
+      addressing: AddressingMode::Normal,
+      source,
+      format,
+    }
+  }
+
+  fn id_list(width: usize) -> GatewaySpec {
+    spec(
+      InventorySource::Did { did: 0xF1A0 },
+      InventoryFormat::IdList {
+        width,
+        rx_offset: 8,
+      },
+    )
+  }
+
+  fn candidate(tx_id: u32, name: &str) -> ModuleCandidate {
+    ModuleCandidate {
+      tx_id,
+      rx_id: tx_id + 8,
+      addressing: AddressingMode::Normal,
+      name: name.to_string(),
+      bus: "HS-CAN".to_string(),
+      category: "Body".to_string(),
+    }
+  }
+
+  fn names(modules: &[ModuleCandidate]) -> Vec<&str> {
+    modules.iter().map(|module| module.name.as_str()).collect()
+  }
+
+  #[test]
+  fn id_list_skips_padding_and_names_from_catalog() {
+    let mut uds = gateway(json!({ "F1A0": "0726 0000 07E0 0733 0000" }), json!({}));
+    let catalog = [candidate(0x7E0, "PCM"), candidate(0x726, "BCM")];
+    let fitted = gateway_inventory(&mut uds, &id_list(2), &catalog).unwrap();
+    assert_eq!(names(&fitted), vec!["BCM", "PCM", "ECU 0x733"]);
+    let unknown = &fitted[2];
+    assert_eq!((unknown.tx_id, unknown.rx_id), (0x733, 0x73B));
+    assert_eq!(unknown.bus, "Unknown");
+  }
+
+  #[test]
+  fn id_list_rejects_widths_that_do_not_split_the_data() {
+    for width in [0, 3, 5] {
+      let mut uds = gateway(json!({ "F1A0": "0726 07E0" }), json!({}));
+      let result = gateway_inventory(&mut uds, &id_list(width), &[]);
+      assert!(
+        matches!(result, Err(UdsError::UnexpectedResponse(_))),
+        "width {width}: {result:?}"
+      );
+    }
+    let mut uds = gateway(json!({ "F1A0": "0000 0726 0000 07E0" }), json!({}));
+    let fitted = gateway_inventory(&mut uds, &id_list(4), &[]).unwrap();
+    assert_eq!(names(&fitted), vec!["ECU 0x726", "ECU 0x7E0"]);
+  }
+
+  #[test]
+  fn bitmap_reads_most_significant_bit_first() {
+    let modules = (0..10)
+      .map(|index| candidate(0x700 + index, &format!("M{index}")))
+      .collect::<Vec<_>>();
+    let bitmap = |option: Vec<u8>| {
+      spec(
+        InventorySource::Routine {
+          routine_id: 0x0203,
+          option,
+        },
+        InventoryFormat::Bitmap {
+          modules: modules.clone(),
+        },
+      )
+    };
+
+    let mut uds = gateway(json!({}), json!({ "0203": "A0 40 FF" }));
+    let fitted = gateway_inventory(&mut uds, &bitmap(vec![0x01]), &[]).unwrap();
+    assert_eq!(names(&fitted), vec!["M0", "M2", "M9"]);
+
+    // Modules past the end of a short bitmap are not fitted.
+    let mut uds = gateway(json!({}), json!({ "0203": "A0" }));
+    let fitted = gateway_inventory(&mut uds, &bitmap(Vec::new()), &[]).unwrap();
+    assert_eq!(names(&fitted), vec!["M0", "M2"]);
+  }
+
+  #[test]
+  fn merge_adds_silent_fitted_modules_as_no_response() {
+    let mut modules = vec![module_info(
+      EcuAddress::normal(0x726, 0x72E),
+      "Body Control Module",
+      "HS-CAN",
+      "Body",
+    )];
+    merge_inventory(
+      &mut modules,
+      &[candidate(0x726, "BCM"), candidate(0x733, "HVAC")],
+    );
+    assert_eq!(modules.len(), 2);
+    assert_eq!(modules[0].name, "Body Control Module");
+    assert_eq!(modules[0].status, ModuleStatus::Ok);
+    assert_eq!(modules[1].id, "0x733");
+    assert_eq!(modules[1].name, "HVAC");
+    assert_eq!(modules[1].rx_id, 0x73B);
+    assert_eq!(modules[1].status, ModuleStatus::NoResponse);
+  }
+}
@@ -0,0 +1,499 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::app_state::{DiagnosticSession, ModuleInfo, ModuleStatus};
+use crate::protocol::addressing::{
+  normal_fixed_targets, AddressingMode, EcuAddress, NORMAL_FIXED_FUNCTIONAL_ID, OBD_FUNCTIONAL_ID,
+};
+use crate::protocol::uds::UdsClient;
+use crate::transport::Transport;
+
+use super::ModuleCandidate;
+
+const MAX_STANDARD_ID: u32 = 0x7FF;
+// Slowest responder latency times this factor becomes the probe window.
+const LATENCY_FACTOR: u32 = 3;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProbeRange {
+  pub start: u32,
+  pub end: u32,
+  pub rx_offset: i64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum NormalFixedSweep {
+  // Only when a candidate or the functional probe shows 29-bit normal-fixed traffic.
+  #[default]
+  Auto,
+  Always,
+  Never,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct DiscoveryConfig {
+  pub ranges: Vec<ProbeRange>,
+  pub normal_fixed: NormalFixedSweep,
+  pub functional_sweep: bool,
+  pub batch_size: usize,
+  pub min_timeout_ms: u64,
+  pub max_timeout_ms: u64,
+  // Known candidates that stay silent get this many more probes at the full timeout.
+  pub candidate_retries: u8,
+}
+
+impl Default for DiscoveryConfig {
+  fn default() -> Self {
+    Self {
+      ranges: vec![ProbeRange {
+        start: 0x700,
+        end: 0x7E7,
+        rx_offset: 8,
+      }],
+      normal_fixed: NormalFixedSweep::Auto,
+      functional_sweep: true,
+      batch_size: 16,
+      min_timeout_ms: 60,
+      max_timeout_ms: 500,
+      candidate_retries: 1,
+    }
+  }
+}
+
+impl DiscoveryConfig {
+  fn range_addresses(&self) -> Vec<EcuAddress> {
+    self
+      .ranges
+      .iter()
+      .flat_map(|range| {
+        (range.start..=range.end).filter_map(move |tx_id| {
+          let rx_id = u32::try_from(tx_id as i64 + range.rx_offset).ok()?;
+          let limit = if tx_id > MAX_STANDARD_ID { 0x1FFF_FFFF } else { MAX_STANDARD_ID };
+          (rx_id <= limit && rx_id != tx_id).then(|| EcuAddress::normal(tx_id, rx_id))
+        })
+      })
+      .collect()
+  }
+}
+
+// Starts at the full window and narrows to a multiple of the slowest reply seen so far.
+struct AdaptiveTimeout {
+  min_ms: u64,
+  max_ms: u64,
+  slowest: Option<Duration>,
+}
+
+impl AdaptiveTimeout {
+  fn current_ms(&self) -> u64 {
+    match self.slowest {
+      Some(latency) => ((latency * LATENCY_FACTOR).as_millis() as u64).clamp(self.min_ms, self.max_ms),
+      None => self.max_ms,
+    }
+  }
+
+  fn observe(&mut self, latency: Duration) {
+    self.slowest = Some(self.slowest.map_or(latency, |slowest| slowest.max(latency)));
+  }
+}
+
+struct Sweep<'a, T: Transport> {
+  uds: &'a mut UdsClient<T>,
+  config: &'a DiscoveryConfig,
+  timeout: AdaptiveTimeout,
+  found: Vec<EcuAddress>,
+  seen: HashSet<EcuAddress>,
+}
+
+impl<T: Transport> Sweep<'_, T> {
+  fn probe(&mut self, addresses: &[EcuAddress], fixed_timeout_ms: Option<u64>) {
+    let pending = addresses
+      .iter()
+      .filter(|address| !self.seen.contains(address))
+      .copied()
+      .collect::<Vec<_>>();
+    for batch in pending.chunks(self.config.batch_size.max(1)) {
+      let timeout_ms = fixed_timeout_ms.unwrap_or_else(|| self.timeout.current_ms());
+      if let Ok(replies) = self.uds.probe_tester_present(batch, timeout_ms) {
+        self.record_replies(replies);
+      }
+    }
+  }
+
+  fn functional(&mut self, request_id: u32, responders: &[EcuAddress]) -> bool {
+    let timeout_ms = self.timeout.current_ms();
+    let Ok(replies) = self.uds.probe_tester_present_functional(request_id, responders, timeout_ms) else {
+      return false;
+    };
+    let answered = !replies.is_empty();
+    self.record_replies(replies);
+    answered
+  }
+
+  fn record_replies(&mut self, replies: Vec<(EcuAddress, Duration)>) {
+    for (address, latency) in replies {
+      self.timeout.observe(latency);
+      if self.seen.insert(address) {
+        self.found.push(address);
+      }
+    }
+  }
+}
+
+// `names` are never probed; they only label responders that are not candidates.
+pub fn discover_modules<T: Transport>(
+  uds: &mut UdsClient<T>,
+  candidates: &[ModuleCandidate],
+  names: &[ModuleCandidate],
+  config: &DiscoveryConfig,
+) -> Vec<ModuleInfo> {
+  let candidate_addresses = candidates.iter().map(ModuleCandidate::address).collect::<Vec<_>>();
+  let range_addresses = config.range_addresses();
+
+  let mut sweep = Sweep {
+    uds,
+    config,
+    timeout: AdaptiveTimeout {
+      min_ms: config.min_timeout_ms,
+      max_ms: config.max_timeout_ms.max(config.min_timeout_ms),
+      slowest: None,
+    },
+    found: Vec::new(),
+    seen: HashSet::new(),
+  };
+
+  let mut normal_fixed_seen = false;
+  if config.functional_sweep {
+    let standard = candidate_addresses
+      .iter()
+      .chain(range_addresses.iter())
+      .filter(|address| address.mode == AddressingMode::Normal && !address.is_29bit())
+      .copied()
+      .collect::<Vec<_>>();
+    sweep.functional(OBD_FUNCTIONAL_ID, &standard);
+    if config.normal_fixed != NormalFixedSweep::Never {
+      normal_fixed_seen = sweep.functional(NORMAL_FIXED_FUNCTIONAL_ID, &normal_fixed_targets());
+    }
+  }
+
+  // Known modules go first so the window adapts to real latencies before the blind sweep.
+  sweep.probe(&candidate_addresses, None);
+  sweep.probe(&range_addresses, None);
+
+  let sweep_normal_fixed = match config.normal_fixed {
+    NormalFixedSweep::Always => true,
+    NormalFixedSweep::Never => false,
+    NormalFixedSweep::Auto => {
+      normal_fixed_seen
+        || candidates
+          .iter()
+          .any(|candidate| candidate.addressing == AddressingMode::NormalFixed)
+    }
+  };
+  if sweep_normal_fixed {
+    sweep.probe(&normal_fixed_targets(), None);
+  }
+
+  for _ in 0..config.candidate_retries {
+    sweep.probe(&candidate_addresses, Some(config.max_timeout_ms));
+  }
+
+  // Candidates keep their listed order and names; everything else follows in the order it was found.
+  let found = sweep.found;
+  let mut modules: Vec<ModuleInfo> = Vec::new();
+  for candidate in candidates {
+    let address = candidate.address();
+    if found.contains(&address) && !modules.iter().any(|module| module.address() == address) {
+      modules.push(module_info(address, &candidate.name, &candidate.bus, &candidate.category));
+    }
+  }
+  for address in found {
+    if candidate_addresses.contains(&address) {
+      continue;
+    }
+    modules.push(match names.iter().find(|known| known.address() == address) {
+      Some(known) => module_info(address, &known.name, &known.bus, &known.category),
+      None => unknown_module(address),
+    });
+  }
+  modules
+}
+
+fn unknown_module(address: EcuAddress) -> ModuleInfo {
+  let label = address.label();
+  module_info(address, &format!("ECU {label}"), "Unknown", "Unknown")
+}
+
+pub(super) fn module_info(address: EcuAddress, name: &str, bus: &str, category: &str) -> ModuleInfo {
+  ModuleInfo {
+    id: address.label(),
+    name: name.to_string(),
+    bus: bus.to_string(),
+    category: category.to_string(),
+    tx_id: address.tx_id,
+    rx_id: address.rx_id,
+    addressing: address.mode,
+    status: ModuleStatus::Ok,
+    dtc_count: 0,
+    identification: Vec::new(),
+    session: DiagnosticSession::Default,
+    security_level: None,
+    vin_checks: Vec::new(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+
+  use super::*;
+  use crate::simulation::SimulationSession;
+  use crate::transport::{BusType, Filter, Frame, SimTransport, TimingConfig};
+
+  #[derive(Debug, Clone, Copy, PartialEq)]
+  enum Event {
+    Send(u32),
+    Listen(u64),
+  }
+
+  // Virtual ECUs behind a log of what the sweep sent and how long it listened.
+  struct Bus {
+    ecus: SimTransport,
+    events: Vec<Event>,
+  }
+
+  impl Transport for Bus {
+    fn open(&mut self) -> Result<(), String> {
+      self.ecus.open()
+    }
+    fn close(&mut self) {}
+    fn send(&mut self, frame: &Frame) -> Result<(), String> {
+      self.events.push(Event::Send(frame.id));
+      self.ecus.send(frame)
+    }
+    fn recv(&mut self, timeout_ms: u64) -> Result<Option<Frame>, String> {
+      self.events.push(Event::Listen(timeout_ms));
+      self.ecus.recv(timeout_ms)
+    }
+    fn set_filters(&mut self, _filters: Vec<Filter>) -> Result<(), String> {
+      Ok(())
+    }
+    fn set_baud(&mut self, _baud: u32) -> Result<(), String> {
+      Ok(())
+    }
+    fn set_bus(&mut self, _bus: BusType) -> Result<(), String> {
+      Ok(())
+    }
+    fn set_timing(&mut self, _timing: TimingConfig) -> Result<(), String> {
+      Ok(())
+    }
+  }
+
+  fn vehicle(modules: &[(u32, u32, &str)]) -> UdsClient<Bus> {
+    let modules = modules
+      .iter()
+      .map(|(tx_id, rx_id, mode)| {
+        json!({
+          "id": format!("{tx_id:X}"),
+          "name": "ECU",
+          "bus": "HS-CAN",
+          "category": "Body",
+          "txId": tx_id,
+          "rxId": rx_id,
+          "addressing": { "mode": mode },
+          "dtcs": []
+        })
+      })
+      .collect::<Vec<_>>();
+    let session: SimulationSession = serde_json::from_value(json!({
+      "vin": "1FT7W2BT6KED12345",
+      "vehicle": { "make": "Ford", "model": "F-250", "year": "2019" },
+      "modules": modules
+    }))
+    .unwrap();
+    let mut uds = UdsClient::new(
+      Bus {
+        ecus: SimTransport::from_session(&session),
+        events: Vec::new(),
+      },
+      20,
+      0,
+    );
+    uds.open().unwrap();
+    uds
+  }
+
+  fn candidate(tx_id: u32, rx_id: u32, name: &str) -> ModuleCandidate {
+    ModuleCandidate {
+      tx_id,
+      rx_id,
+      addressing: AddressingMode::Normal,
+      name: name.to_string(),
+      bus: "HS-CAN".to_string(),
+      category: "Powertrain".to_string(),
+    }
+  }
+
+  fn config(start: u32, end: u32) -> DiscoveryConfig {
+    DiscoveryConfig {
+      ranges: vec![ProbeRange {
+        start,
+        end,
+        rx_offset: 8,
+      }],
+      normal_fixed: NormalFixedSweep::Never,
+      functional_sweep: false,
+      batch_size: 4,
+      min_timeout_ms: 5,
+      max_timeout_ms: 40,
+      candidate_retries: 0,
+    }
+  }
+
+  fn physical_sends(events: &[Event]) -> Vec<u32> {
+    events
+      .iter()
+      .filter_map(|event| match event {
+        Event::Send(id) => Some(*id),
+        Event::Listen(_) => None,
+      })
+      .collect()
+  }
+
+  #[test]
+  fn range_addresses_stay_within_can_ids() {
+    let addresses = |start, end, rx_offset| {
+      DiscoveryConfig {
+        ranges: vec![ProbeRange {
+          start,
+          end,
+          rx_offset,
+        }],
+        ..DiscoveryConfig::default()
+      }
+      .range_addresses()
+      .iter()
+      .map(|address| (address.tx_id, address.rx_id))
+      .collect::<Vec<_>>()
+    };
+    assert_eq!(
+      addresses(0x7F6, 0x7F9, 8),
+      vec![(0x7F6, 0x7FE), (0x7F7, 0x7FF)]
+    );
+    assert_eq!(
+      addresses(0x006, 0x009, -8),
+      vec![(0x008, 0x000), (0x009, 0x001)]
+    );
+    assert_eq!(addresses(0x7E0, 0x7E1, 0), vec![]);
+    assert_eq!(
+      addresses(0x1FFF_FFFE, 0x1FFF_FFFF, 1),
+      vec![(0x1FFF_FFFE, 0x1FFF_FFFF)]
+    );
+    assert_eq!(
+      addresses(0x18DA_10F1, 0x18DA_10F1, -0x100),
+      vec![(0x18DA_10F1, 0x18DA_0FF1)]
+    );
+  }
+
+  #[test]
+  fn lists_candidates_first_and_names_the_rest() {
+    let mut uds = vehicle(&[
+      (0x7E0, 0x7E8, "normal"),
+      (0x726, 0x72E, "normal"),
+      (0x740, 0x748, "normal"),
+      (0x760, 0x768, "normal"),
+    ]);
+    let candidates = [
+      candidate(0x760, 0x768, "ABS"),
+      candidate(0x7E0, 0x7E8, "PCM"),
+      candidate(0x7A0, 0x7A8, "Silent"),
+    ];
+    let names = [candidate(0x726, 0x72E, "BCM")];
+    let modules = discover_modules(&mut uds, &candidates, &names, &config(0x700, 0x7E7));
+    let found = modules
+      .iter()
+      .map(|module| (module.name.as_str(), module.bus.as_str()))
+      .collect::<Vec<_>>();
+    assert_eq!(
+      found,
+      vec![
+        ("ABS", "HS-CAN"),
+        ("PCM", "HS-CAN"),
+        ("BCM", "HS-CAN"),
+        ("ECU 0x740", "Unknown"),
+      ]
+    );
+  }
+
+  #[test]
+  fn probes_in_batches_and_narrows_the_window() {
+    let mut uds = vehicle(&[(0x701, 0x709, "normal")]);
+    discover_modules(&mut uds, &[], &[], &config(0x700, 0x709));
+    let events = uds.into_transport().events;
+
+    // Ten addresses in batches of four; each batch is sent before listening for any of it.
+    let batches = events
+      .split(|event| matches!(event, Event::Listen(_)))
+      .filter(|sends| !sends.is_empty())
+      .map(<[Event]>::len)
+      .collect::<Vec<_>>();
+    assert_eq!(batches, vec![4, 4, 2]);
+
+    // The first batch listens for the full window; the reply from 0x709 narrows the rest to the minimum.
+    let windows = events
+      .windows(2)
+      .filter_map(|pair| match pair {
+        [Event::Send(_), Event::Listen(timeout)] => Some(*timeout),
+        _ => None,
+      })
+      .collect::<Vec<_>>();
+    assert!(windows[0] > 5, "{windows:?}");
+    assert!(
+      windows[1..].iter().all(|timeout| *timeout <= 5),
+      "{windows:?}"
+    );
+  }
+
+  #[test]
+  fn sweeps_normal_fixed_ids_only_when_seen() {
+    let normal_fixed_probes = |uds: UdsClient<Bus>| {
+      physical_sends(&uds.into_transport().events)
+        .into_iter()
+        .filter(|id| id >> 16 == 0x18DA)
+        .count()
+    };
+    let auto = DiscoveryConfig {
+      normal_fixed: NormalFixedSweep::Auto,
+      functional_sweep: true,
+      ..config(0x7E0, 0x7E0)
+    };
+
+    let mut uds = vehicle(&[(0x7E0, 0x7E8, "normal")]);
+    assert_eq!(discover_modules(&mut uds, &[], &[], &auto).len(), 1);
+    assert_eq!(normal_fixed_probes(uds), 0);
+
+    let mut uds = vehicle(&[
+      (0x7E0, 0x7E8, "normal"),
+      (0x18DA_10F1, 0x18DA_F110, "normalFixed"),
+    ]);
+    let modules = discover_modules(&mut uds, &[], &[], &auto);
+    assert!(modules.iter().any(
+      |module| module.addressing == AddressingMode::NormalFixed && module.tx_id == 0x18DA_10F1
+    ));
+    // Every target but the one that already answered the functional probe.
+    assert_eq!(normal_fixed_probes(uds), normal_fixed_targets().len() - 1);
+
+    // A normal-fixed candidate turns the sweep on even when nothing answers.
+    let mut uds = vehicle(&[(0x7E0, 0x7E8, "normal")]);
+    let candidates = [ModuleCandidate {
+      addressing: AddressingMode::NormalFixed,
+      ..candidate(0x18DA_10F1, 0x18DA_F110, "TCM")
+    }];
+    assert_eq!(discover_modules(&mut uds, &candidates, &[], &auto).len(), 1);
+    assert_eq!(normal_fixed_probes(uds), 1 + normal_fixed_targets().len());
+  }
+}
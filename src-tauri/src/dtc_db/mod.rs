@@ -0,0 +1,237 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use tauri::AppHandle;
+
+use crate::app_state::DtcInfo;
+
+// Generic SAE descriptions first, then OEM files.
+const BUNDLED_DBS: &[(&str, &str)] = &[
+  ("generic.json", include_str!("../../data/dtc/generic.json")),
+  ("ford.json", include_str!("../../data/dtc/ford.json")),
+];
+
+pub const USER_DB_FILE: &str = "dtc_database.json";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DtcDatabaseFile {
+  #[serde(default)]
+  pub makes: Vec<String>,
+  #[serde(default)]
+  pub entries: Vec<DtcEntry>,
+  #[serde(default)]
+  pub failure_types: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DtcEntry {
+  pub code: String,
+  pub description: String,
+  #[serde(default)]
+  pub make: Option<String>,
+  #[serde(default)]
+  pub module: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct EntryKey {
+  make: String,
+  module: String,
+  code: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DtcDatabase {
+  entries: HashMap<EntryKey, String>,
+  failure_types: HashMap<u8, String>,
+}
+
+impl DtcDatabase {
+  pub fn builtin() -> Self {
+    Self::bundled().0
+  }
+
+  fn bundled() -> (Self, Vec<String>) {
+    let mut db = Self::default();
+    let mut errors = Vec::new();
+    for (name, source) in BUNDLED_DBS {
+      match serde_json::from_str::<DtcDatabaseFile>(source) {
+        Ok(file) => errors.extend(
+          db.merge(file)
+            .into_iter()
+            .map(|err| format!("Bundled DTC database {name}: {err}")),
+        ),
+        Err(err) => errors.push(format!("Invalid bundled DTC database {name}: {err}")),
+      }
+    }
+    (db, errors)
+  }
+
+  // Bundled data first, then the user's file so their entries win on conflicts.
+  // Returns the number of user entries and everything that was skipped.
+  pub fn load(app: &AppHandle) -> (Self, usize, Vec<String>) {
+    let (mut db, mut errors) = Self::bundled();
+    let mut count = 0;
+    if let Some(path) = user_db_path(app).filter(|path| path.exists()) {
+      match db.merge_file(&path, &mut errors) {
+        Ok(entries) => count = entries,
+        Err(err) => errors.push(err),
+      }
+    }
+    (db, count, errors)
+  }
+
+  pub fn merge_file(&mut self, path: &Path, errors: &mut Vec<String>) -> Result<usize, String> {
+    let contents = fs::read_to_string(path)
+      .map_err(|err| format!("Failed to read DTC database {}: {err}", path.display()))?;
+    let file: DtcDatabaseFile = serde_json::from_str(&contents)
+      .map_err(|err| format!("Invalid DTC database {}: {err}", path.display()))?;
+    let count = file.entries.len();
+    errors.extend(
+      self
+        .merge(file)
+        .into_iter()
+        .map(|err| format!("DTC database {}: {err}", path.display())),
+    );
+    Ok(count)
+  }
+
+  // Returns the failure type keys that are not a hex byte; everything else is merged.
+  pub fn merge(&mut self, file: DtcDatabaseFile) -> Vec<String> {
+    let mut errors = Vec::new();
+    let file_makes = if file.makes.is_empty() {
+      vec![String::new()]
+    } else {
+      file.makes.iter().map(|make| normalize(make)).collect()
+    };
+    for entry in file.entries {
+      let makes = match &entry.make {
+        Some(make) => vec![normalize(make)],
+        None => file_makes.clone(),
+      };
+      let module = entry.module.as_deref().map(normalize).unwrap_or_default();
+      let code = entry.code.trim().to_ascii_uppercase();
+      for make in makes {
+        self.entries.insert(
+          EntryKey {
+            make,
+            module: module.clone(),
+            code: code.clone(),
+          },
+          entry.description.clone(),
+        );
+      }
+    }
+    for (key, description) in file.failure_types {
+      match u8::from_str_radix(key.trim(), 16) {
+        Ok(byte) => {
+          self.failure_types.insert(byte, description);
+        }
+        Err(_) => errors.push(format!("invalid failure type '{key}'")),
+      }
+    }
+    errors
+  }
+
+  // Most specific match wins: make and module, then make, then generic SAE.
+  pub fn lookup(&self, code: &str, make: Option<&str>, module: Option<&str>) -> Option<String> {
+    let code = code.trim().to_ascii_uppercase();
+    let make = make.map(normalize).unwrap_or_default();
+    let module = module.map(normalize).unwrap_or_default();
+
+    if let Some(description) = self.find(&code, &make, &module) {
+      return Some(description.clone());
+    }
+
+    let (base, failure_type) = code.split_once('-')?;
+    let description = self.find(base, &make, &module)?;
+    let failure_type = u8::from_str_radix(failure_type, 16).ok();
+    match failure_type.and_then(|byte| self.failure_types.get(&byte)) {
+      Some(failure) => Some(format!("{description} - {failure}")),
+      None => Some(description.clone()),
+    }
+  }
+
+  pub fn describe(&self, make: Option<&str>, module: Option<&str>, dtcs: &mut [DtcInfo]) {
+    for dtc in dtcs.iter_mut() {
+      if let Some(description) = self.lookup(&dtc.code, make, module) {
+        dtc.description = description;
+      }
+    }
+  }
+
+  fn find(&self, code: &str, make: &str, module: &str) -> Option<&String> {
+    let mut scopes = Vec::new();
+    if !make.is_empty() {
+      if !module.is_empty() {
+        scopes.push((make, module));
+      }
+      scopes.push((make, ""));
+    }
+    if !module.is_empty() {
+      scopes.push(("", module));
+    }
+    scopes.push(("", ""));
+    scopes.into_iter().find_map(|(make, module)| {
+      self.entries.get(&EntryKey {
+        make: make.to_string(),
+        module: module.to_string(),
+        code: code.to_string(),
+      })
+    })
+  }
+}
+
+pub fn user_db_path(app: &AppHandle) -> Option<PathBuf> {
+  let base = tauri::api::path::app_data_dir(&app.config())?;
+  Some(base.join(USER_DB_FILE))
+}
+
+fn normalize(value: &str) -> String {
+  value.trim().to_ascii_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn bundled_databases_parse() {
+    for (name, source) in BUNDLED_DBS {
+      let file = serde_json::from_str::<DtcDatabaseFile>(source)
+        .unwrap_or_else(|err| panic!("{name}: {err}"));
+      assert!(!file.entries.is_empty(), "{name} has no entries");
+    }
+    let (db, errors) = DtcDatabase::bundled();
+    assert!(errors.is_empty(), "{errors:?}");
+    assert!(db.lookup("P0123", None, None).is_some());
+  }
+
+  #[test]
+  fn reports_user_file_errors() {
+    let dir = std::env::temp_dir().join(format!("vyntool-dtc-{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(&dir).unwrap();
+    let broken = dir.join("broken.json");
+    fs::write(&broken, r#"{ "entries": [ { "code": "P0123" } ] }"#).unwrap();
+    let partial = dir.join("partial.json");
+    fs::write(
+      &partial,
+      r#"{ "entries": [ { "code": "P1ABC", "description": "Custom" } ],
+           "failureTypes": { "ZZ": "Bad key" } }"#,
+    )
+    .unwrap();
+
+    let mut db = DtcDatabase::default();
+    let mut errors = Vec::new();
+    assert!(db.merge_file(&broken, &mut errors).is_err());
+    assert_eq!(db.merge_file(&partial, &mut errors), Ok(1));
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].contains("'ZZ'"), "{errors:?}");
+    assert_eq!(db.lookup("P1ABC", None, None).as_deref(), Some("Custom"));
+    let _ = fs::remove_dir_all(&dir);
+  }
+}
@@ -1,15 +1,20 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 mod app_state;
+mod baseline;
 mod commands;
 mod discovery;
+mod dtc_db;
 mod logger;
+mod profiles;
 mod protocol;
 mod runtime;
 mod scanner;
+mod security;
 mod simulation;
 mod topology;
 mod transport;
+mod vin;
 
 use std::sync::Arc;
 
@@ -30,7 +35,19 @@ fn main() {
       commands::get_adapter_status,
       commands::start_scan,
       commands::clear_dtcs,
+      commands::set_diagnostic_session,
+      commands::security_access,
+      commands::list_j2534_devices,
+      commands::select_j2534_device,
+      commands::list_profiles,
+      commands::select_profile,
+      commands::save_profile,
+      commands::delete_profile,
+      commands::list_baselines,
+      commands::save_baseline,
+      commands::delete_baseline,
       commands::export_logs,
+      commands::export_topology,
       commands::read_log_tail,
     ])
     .run(tauri::generate_context!())
@@ -0,0 +1,319 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Runtime};
+
+use crate::discovery::{GatewaySpec, ModuleCandidate};
+use crate::topology::BusSpec;
+use crate::vin::VinInfo;
+
+const BUILTIN_PROFILES: &[(&str, &str)] = &[
+  ("generic_obd.json", include_str!("../../data/profiles/generic_obd.json")),
+  ("ford.json", include_str!("../../data/profiles/ford.json")),
+  ("ram.json", include_str!("../../data/profiles/ram.json")),
+];
+
+pub const USER_PROFILE_DIR: &str = "profiles";
+pub const SELECTION_FILE: &str = "profile_selection.json";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ProfileSource {
+  #[default]
+  Builtin,
+  User,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveryProfile {
+  pub id: String,
+  pub name: String,
+  // Empty matches any vehicle.
+  #[serde(default)]
+  pub wmis: Vec<String>,
+  #[serde(default)]
+  pub min_year: Option<u16>,
+  #[serde(default)]
+  pub max_year: Option<u16>,
+  // Probed as candidates ahead of the range sweep.
+  #[serde(default)]
+  pub modules: Vec<ModuleCandidate>,
+  // Not probed; only used to name responders the range sweep turns up.
+  #[serde(default)]
+  pub names: Vec<ModuleCandidate>,
+  #[serde(default)]
+  pub gateway: Option<GatewaySpec>,
+  #[serde(default)]
+  pub buses: Vec<BusSpec>,
+  // The modules list is the expected build; a scan missing any of them is reported.
+  #[serde(default)]
+  pub baseline: bool,
+  // OEM DIDs holding the as-built VIN, checked on every module alongside F190.
+  #[serde(default)]
+  pub vin_dids: Vec<u16>,
+  #[serde(default, skip_deserializing)]
+  pub source: ProfileSource,
+}
+
+impl DiscoveryProfile {
+  // None when the profile does not apply; a WMI match outranks a model-year match.
+  fn match_score(&self, vin: Option<&VinInfo>) -> Option<u8> {
+    let mut score = 0;
+    if !self.wmis.is_empty() {
+      let wmi = vin.map(|info| info.wmi.as_str())?;
+      if !self.wmis.iter().any(|candidate| candidate.eq_ignore_ascii_case(wmi)) {
+        return None;
+      }
+      score += 2;
+    }
+    if self.min_year.is_some() || self.max_year.is_some() {
+      // An undecodable year does not rule the profile out, it just earns nothing.
+      if let Some(year) = vin.and_then(|info| info.year) {
+        if self.min_year.is_some_and(|min| year < min) || self.max_year.is_some_and(|max| year > max) {
+          return None;
+        }
+        score += 1;
+      }
+    }
+    Some(score)
+  }
+
+  fn validate(&self) -> Result<(), String> {
+    if self.id.is_empty()
+      || !self
+        .id
+        .chars()
+        .all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_')
+    {
+      return Err(format!("Invalid profile id '{}': use letters, digits, '-' and '_'", self.id));
+    }
+    if self.name.trim().is_empty() {
+      return Err(format!("Profile {} has no name", self.id));
+    }
+    Ok(())
+  }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ProfileStore {
+  profiles: Vec<DiscoveryProfile>,
+}
+
+impl ProfileStore {
+  fn bundled() -> (Self, Vec<String>) {
+    let mut store = Self::default();
+    let mut errors = Vec::new();
+    for (name, source) in BUILTIN_PROFILES {
+      let profile = serde_json::from_str::<DiscoveryProfile>(source)
+        .map_err(|err| err.to_string())
+        .and_then(|profile| profile.validate().map(|_| profile));
+      match profile {
+        Ok(profile) => store.insert(profile),
+        Err(err) => errors.push(format!("Invalid bundled profile {name}: {err}")),
+      }
+    }
+    (store, errors)
+  }
+
+  // Bundled profiles first; a user profile with the same id replaces the bundled one.
+  pub fn load<R: Runtime>(app: &AppHandle<R>) -> (Self, Vec<String>) {
+    let (mut store, mut errors) = Self::bundled();
+    if let Some(dir) = user_profile_dir(app).filter(|dir| dir.is_dir()) {
+      errors.extend(store.load_dir(&dir));
+    }
+    (store, errors)
+  }
+
+  pub fn load_dir(&mut self, dir: &Path) -> Vec<String> {
+    let mut paths = match fs::read_dir(dir) {
+      Ok(entries) => entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect::<Vec<_>>(),
+      Err(err) => return vec![format!("Failed to read profile dir {}: {err}", dir.display())],
+    };
+    paths.sort();
+
+    let mut errors = Vec::new();
+    for path in paths {
+      let profile = fs::read_to_string(&path)
+        .map_err(|err| err.to_string())
+        .and_then(|contents| serde_json::from_str::<DiscoveryProfile>(&contents).map_err(|err| err.to_string()))
+        .and_then(|profile| profile.validate().map(|_| profile));
+      match profile {
+        Ok(mut profile) => {
+          profile.source = ProfileSource::User;
+          self.insert(profile);
+        }
+        Err(err) => errors.push(format!("Invalid profile {}: {err}", path.display())),
+      }
+    }
+    errors
+  }
+
+  fn insert(&mut self, profile: DiscoveryProfile) {
+    match self.profiles.iter_mut().find(|existing| existing.id == profile.id) {
+      Some(existing) => *existing = profile,
+      None => self.profiles.push(profile),
+    }
+  }
+
+  pub fn profiles(&self) -> &[DiscoveryProfile] {
+    &self.profiles
+  }
+
+  pub fn get(&self, id: &str) -> Option<&DiscoveryProfile> {
+    self.profiles.iter().find(|profile| profile.id == id)
+  }
+
+  // Ties go to user profiles, then to whichever was loaded last.
+  pub fn best_match(&self, vin: Option<&VinInfo>) -> Option<&DiscoveryProfile> {
+    self
+      .profiles
+      .iter()
+      .filter_map(|profile| {
+        let score = profile.match_score(vin)?;
+        Some(((score, profile.source == ProfileSource::User), profile))
+      })
+      .max_by_key(|(rank, _)| *rank)
+      .map(|(_, profile)| profile)
+  }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProfileSelection {
+  #[serde(default)]
+  profile_id: Option<String>,
+}
+
+pub fn user_profile_dir<R: Runtime>(app: &AppHandle<R>) -> Option<PathBuf> {
+  let base = tauri::api::path::app_data_dir(&app.config())?;
+  Some(base.join(USER_PROFILE_DIR))
+}
+
+// The profile picked in the UI, or None to choose by VIN.
+pub fn load_selection<R: Runtime>(app: &AppHandle<R>) -> Option<String> {
+  let base = tauri::api::path::app_data_dir(&app.config())?;
+  let contents = fs::read_to_string(base.join(SELECTION_FILE)).ok()?;
+  serde_json::from_str::<ProfileSelection>(&contents).ok()?.profile_id
+}
+
+pub fn save_selection(app: &AppHandle, profile_id: Option<String>) -> Result<(), String> {
+  let base = tauri::api::path::app_data_dir(&app.config()).ok_or_else(|| "Missing app data dir".to_string())?;
+  fs::create_dir_all(&base).map_err(|err| format!("Failed to create app data dir: {err}"))?;
+  let contents = serde_json::to_string_pretty(&ProfileSelection { profile_id }).map_err(|err| err.to_string())?;
+  fs::write(base.join(SELECTION_FILE), contents).map_err(|err| format!("Failed to save profile selection: {err}"))
+}
+
+pub fn save_user_profile(app: &AppHandle, profile: &DiscoveryProfile) -> Result<(), String> {
+  profile.validate()?;
+  let dir = user_profile_dir(app).ok_or_else(|| "Missing app data dir".to_string())?;
+  fs::create_dir_all(&dir).map_err(|err| format!("Failed to create profile dir: {err}"))?;
+  let contents = serde_json::to_string_pretty(profile).map_err(|err| err.to_string())?;
+  fs::write(dir.join(format!("{}.json", profile.id)), contents)
+    .map_err(|err| format!("Failed to save profile {}: {err}", profile.id))
+}
+
+// Bundled profiles cannot be deleted; removing a user copy brings the bundled one back.
+pub fn delete_user_profile(app: &AppHandle, profile_id: &str) -> Result<(), String> {
+  let dir = user_profile_dir(app).ok_or_else(|| "Missing app data dir".to_string())?;
+  let path = dir.join(format!("{profile_id}.json"));
+  if profile_id.contains(['/', '\\', '.']) || !path.exists() {
+    return Err(format!("No user profile {profile_id}"));
+  }
+  fs::remove_file(&path).map_err(|err| format!("Failed to delete profile {profile_id}: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+
+  use super::*;
+  use crate::vin::decode_vin;
+
+  fn profile(id: &str, rules: serde_json::Value, source: ProfileSource) -> DiscoveryProfile {
+    let mut value = json!({ "id": id, "name": id });
+    value
+      .as_object_mut()
+      .unwrap()
+      .extend(rules.as_object().unwrap().clone());
+    DiscoveryProfile {
+      source,
+      ..serde_json::from_value(value).unwrap()
+    }
+  }
+
+  fn store(profiles: Vec<DiscoveryProfile>) -> ProfileStore {
+    let mut store = ProfileStore::default();
+    for profile in profiles {
+      store.insert(profile);
+    }
+    store
+  }
+
+  #[test]
+  fn bundled_profiles_parse() {
+    for (name, source) in BUILTIN_PROFILES {
+      let profile = serde_json::from_str::<DiscoveryProfile>(source)
+        .unwrap_or_else(|err| panic!("{name}: {err}"));
+      profile
+        .validate()
+        .unwrap_or_else(|err| panic!("{name}: {err}"));
+    }
+    let (store, errors) = ProfileStore::bundled();
+    assert!(errors.is_empty(), "{errors:?}");
+    assert_eq!(store.profiles().len(), BUILTIN_PROFILES.len());
+    let ford = decode_vin("1FT7W2BT6KED12345").unwrap();
+    assert_eq!(store.best_match(Some(&ford)).unwrap().id, "ford");
+  }
+
+  #[test]
+  fn wmi_match_outranks_year_match() {
+    let ford = decode_vin("1FT7W2BT6KED12345").unwrap();
+    let store = store(vec![
+      profile("generic", json!({}), ProfileSource::Builtin),
+      profile("ford", json!({ "wmis": ["1ft"] }), ProfileSource::Builtin),
+      profile(
+        "late",
+        json!({ "minYear": 2017, "maxYear": 2022 }),
+        ProfileSource::Builtin,
+      ),
+      profile("ram", json!({ "wmis": ["1C6"] }), ProfileSource::Builtin),
+    ]);
+    let scores = store
+      .profiles()
+      .iter()
+      .map(|profile| profile.match_score(Some(&ford)))
+      .collect::<Vec<_>>();
+    assert_eq!(scores, vec![Some(0), Some(2), Some(1), None]);
+    assert_eq!(store.best_match(Some(&ford)).unwrap().id, "ford");
+    assert_eq!(store.best_match(None).unwrap().id, "late");
+  }
+
+  #[test]
+  fn user_profiles_win_ties() {
+    let ford = decode_vin("1FT7W2BT6KED12345").unwrap();
+    let store = store(vec![
+      profile("mine", json!({ "wmis": ["1FT"] }), ProfileSource::User),
+      profile("ford", json!({ "wmis": ["1FT"] }), ProfileSource::Builtin),
+    ]);
+    assert_eq!(store.best_match(Some(&ford)).unwrap().id, "mine");
+  }
+
+  #[test]
+  fn undecodable_year_neither_matches_nor_excludes() {
+    let mut ford = decode_vin("1FT7W2BT6KED12345").unwrap();
+    ford.year = None;
+    let old = profile(
+      "old",
+      json!({ "wmis": ["1FT"], "maxYear": 2010 }),
+      ProfileSource::Builtin,
+    );
+    assert_eq!(old.match_score(Some(&ford)), Some(2));
+    ford.year = Some(2019);
+    assert_eq!(old.match_score(Some(&ford)), None);
+  }
+}
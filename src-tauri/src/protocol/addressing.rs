@@ -0,0 +1,136 @@
+use serde::{Deserialize, Serialize};
+
+use crate::transport::{Filter, Frame};
+
+pub const TESTER_ADDRESS: u8 = 0xF1;
+pub const OBD_FUNCTIONAL_ID: u32 = 0x7DF;
+pub const NORMAL_FIXED_FUNCTIONAL_ID: u32 = 0x18DB_33F1;
+
+const NORMAL_FIXED_PHYSICAL_BASE: u32 = 0x18DA_0000;
+const MAX_STANDARD_ID: u32 = 0x7FF;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "camelCase")]
+pub enum AddressingMode {
+  // Plain CAN IDs, 11- or 29-bit.
+  #[default]
+  Normal,
+  // 29-bit 0x18DA_TA_SA physical IDs with 0x18DB_33F1 for functional requests.
+  NormalFixed,
+  // The first data byte carries the target address on requests and the tester's address on responses.
+  Extended { target: u8, source: u8 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EcuAddress {
+  pub tx_id: u32,
+  pub rx_id: u32,
+  pub mode: AddressingMode,
+}
+
+impl EcuAddress {
+  pub fn normal(tx_id: u32, rx_id: u32) -> Self {
+    Self {
+      tx_id,
+      rx_id,
+      mode: AddressingMode::Normal,
+    }
+  }
+
+  pub fn normal_fixed(target: u8, source: u8) -> Self {
+    Self {
+      tx_id: NORMAL_FIXED_PHYSICAL_BASE | (target as u32) << 8 | source as u32,
+      rx_id: NORMAL_FIXED_PHYSICAL_BASE | (source as u32) << 8 | target as u32,
+      mode: AddressingMode::NormalFixed,
+    }
+  }
+
+  pub fn is_29bit(&self) -> bool {
+    self.mode == AddressingMode::NormalFixed || self.tx_id > MAX_STANDARD_ID || self.rx_id > MAX_STANDARD_ID
+  }
+
+  pub fn tx_prefix(&self) -> Option<u8> {
+    match self.mode {
+      AddressingMode::Extended { target, .. } => Some(target),
+      _ => None,
+    }
+  }
+
+  pub fn rx_prefix(&self) -> Option<u8> {
+    match self.mode {
+      AddressingMode::Extended { source, .. } => Some(source),
+      _ => None,
+    }
+  }
+
+  // Data bytes left for the ISO-TP PCI and payload in an 8-byte frame.
+  pub fn frame_capacity(&self) -> usize {
+    8 - self.tx_prefix().map_or(0, |_| 1)
+  }
+
+  pub fn frame(&self, data: &[u8]) -> Frame {
+    let mut frame_data = Vec::with_capacity(8);
+    frame_data.extend(self.tx_prefix());
+    frame_data.extend_from_slice(data);
+    Frame {
+      id: self.tx_id,
+      data: frame_data,
+      timestamp_ms: 0,
+      is_extended: self.is_29bit(),
+    }
+  }
+
+  // ISO-TP bytes of a frame addressed to us, or None when the frame belongs to another link.
+  pub fn strip<'a>(&self, frame: &'a Frame) -> Option<&'a [u8]> {
+    if frame.id != self.rx_id {
+      return None;
+    }
+    match self.rx_prefix() {
+      Some(prefix) => match frame.data.split_first() {
+        Some((&first, rest)) if first == prefix => Some(rest),
+        _ => None,
+      },
+      None => Some(&frame.data),
+    }
+  }
+
+  pub fn label(&self) -> String {
+    match self.mode {
+      AddressingMode::Extended { target, .. } => format!("0x{:03X}:{target:02X}", self.tx_id),
+      _ if self.is_29bit() => format!("0x{:08X}", self.tx_id),
+      _ => format!("0x{:03X}", self.tx_id),
+    }
+  }
+}
+
+// OBD responders 0x7E8-0x7EF; each takes flow control on its physical request ID eight below.
+pub fn obd_responders() -> Vec<EcuAddress> {
+  (0x7E8..=0x7EF)
+    .map(|rx_id| EcuAddress::normal(rx_id - 8, rx_id))
+    .collect()
+}
+
+// Every normal-fixed target except the tester itself.
+pub fn normal_fixed_targets() -> Vec<EcuAddress> {
+  (0x00..=0xFF)
+    .filter(|target| *target != TESTER_ADDRESS)
+    .map(|target| EcuAddress::normal_fixed(target, TESTER_ADDRESS))
+    .collect()
+}
+
+// 11-bit 0x600-0x7FF (diagnostic and extended-addressing responses) plus 29-bit normal-fixed responses to the
+// tester; J2534 adapters pass nothing until a filter is set.
+pub fn response_filters() -> Vec<Filter> {
+  vec![
+    Filter {
+      id: 0x600,
+      mask: 0x600,
+      is_extended: false,
+    },
+    Filter {
+      id: NORMAL_FIXED_PHYSICAL_BASE | (TESTER_ADDRESS as u32) << 8,
+      mask: 0x1FFF_FF00,
+      is_extended: true,
+    },
+  ]
+}
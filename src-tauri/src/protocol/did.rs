@@ -0,0 +1,136 @@
+use crate::app_state::DidValue;
+
+#[derive(Debug, Clone, Copy)]
+pub enum DidEncoding {
+  Unsigned {
+    scale: f64,
+    offset: f64,
+    decimals: usize,
+    unit: &'static str,
+  },
+  Ascii,
+  Bcd,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DidDefinition {
+  pub did: u16,
+  pub name: &'static str,
+  pub length: Option<usize>,
+  pub encoding: DidEncoding,
+}
+
+const fn unsigned(
+  did: u16,
+  name: &'static str,
+  length: usize,
+  scale: f64,
+  offset: f64,
+  decimals: usize,
+  unit: &'static str,
+) -> DidDefinition {
+  DidDefinition {
+    did,
+    name,
+    length: Some(length),
+    encoding: DidEncoding::Unsigned {
+      scale,
+      offset,
+      decimals,
+      unit,
+    },
+  }
+}
+
+// Freeze-frame DIDs: 0xF4xx mirrors the OBD PIDs (ISO 27145), 0xDDxx are the common OEM environment DIDs.
+const SNAPSHOT_DIDS: &[DidDefinition] = &[
+  unsigned(0xF404, "Calculated Engine Load", 1, 100.0 / 255.0, 0.0, 1, "%"),
+  unsigned(0xF405, "Engine Coolant Temperature", 1, 1.0, -40.0, 0, "°C"),
+  unsigned(0xF40B, "Intake Manifold Absolute Pressure", 1, 1.0, 0.0, 0, "kPa"),
+  unsigned(0xF40C, "Engine Speed", 2, 0.25, 0.0, 0, "rpm"),
+  unsigned(0xF40D, "Vehicle Speed", 1, 1.0, 0.0, 0, "km/h"),
+  unsigned(0xF40F, "Intake Air Temperature", 1, 1.0, -40.0, 0, "°C"),
+  unsigned(0xF411, "Throttle Position", 1, 100.0 / 255.0, 0.0, 1, "%"),
+  unsigned(0xF41F, "Run Time Since Engine Start", 2, 1.0, 0.0, 0, "s"),
+  unsigned(0xF42F, "Fuel Tank Level Input", 1, 100.0 / 255.0, 0.0, 1, "%"),
+  unsigned(0xF433, "Barometric Pressure", 1, 1.0, 0.0, 0, "kPa"),
+  unsigned(0xF442, "Control Module Voltage", 2, 0.001, 0.0, 2, "V"),
+  unsigned(0xF446, "Ambient Air Temperature", 1, 1.0, -40.0, 0, "°C"),
+  unsigned(0xDD00, "Global Real Time", 4, 1.0, 0.0, 0, "s"),
+  unsigned(0xDD01, "Total Distance", 3, 1.0, 0.0, 0, "km"),
+  unsigned(0xDD02, "Vehicle Battery Voltage", 1, 0.1, 0.0, 1, "V"),
+  unsigned(0xDD05, "External Temperature", 1, 1.0, -40.0, 0, "°C"),
+  unsigned(0xDD06, "Vehicle Speed", 2, 0.01, 0.0, 1, "km/h"),
+];
+
+const fn text(did: u16, name: &'static str, encoding: DidEncoding) -> DidDefinition {
+  DidDefinition {
+    did,
+    name,
+    length: None,
+    encoding,
+  }
+}
+
+// Identification DIDs from ISO 14229-1 Annex C, plus the Ford assembly numbers.
+pub const IDENTIFICATION_DIDS: &[DidDefinition] = &[
+  text(0xF187, "Spare Part Number", DidEncoding::Ascii),
+  text(0xF188, "ECU Software Number", DidEncoding::Ascii),
+  text(0xF189, "ECU Software Version", DidEncoding::Ascii),
+  text(0xF18B, "ECU Manufacturing Date", DidEncoding::Bcd),
+  text(0xF18C, "ECU Serial Number", DidEncoding::Ascii),
+  text(0xF191, "ECU Hardware Number", DidEncoding::Ascii),
+  text(0xF192, "Supplier Hardware Number", DidEncoding::Ascii),
+  text(0xF197, "System Name", DidEncoding::Ascii),
+  text(0xF110, "Diagnostic Database Part Number", DidEncoding::Ascii),
+  text(0xF111, "ECU Core Assembly Number", DidEncoding::Ascii),
+  text(0xF113, "ECU Delivery Assembly Number", DidEncoding::Ascii),
+];
+
+pub const VIN_DID: u16 = 0xF190;
+
+pub fn snapshot_did(did: u16) -> Option<&'static DidDefinition> {
+  SNAPSHOT_DIDS.iter().find(|definition| definition.did == did)
+}
+
+pub fn decode(definition: &DidDefinition, bytes: &[u8]) -> DidValue {
+  let value = match definition.encoding {
+    DidEncoding::Unsigned {
+      scale,
+      offset,
+      decimals,
+      unit,
+    } => {
+      let raw = bytes.iter().fold(0u64, |acc, byte| (acc << 8) | *byte as u64);
+      let scaled = raw as f64 * scale + offset;
+      format!("{scaled:.decimals$} {unit}")
+    }
+    DidEncoding::Ascii => ascii(bytes),
+    DidEncoding::Bcd => bytes
+      .iter()
+      .flat_map(|byte| [byte >> 4, byte & 0x0F])
+      .map(|digit| char::from_digit(digit as u32, 16).unwrap_or('?').to_ascii_uppercase())
+      .collect(),
+  };
+  DidValue {
+    did: definition.did,
+    name: definition.name.to_string(),
+    value,
+    raw: hex(bytes),
+  }
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+  bytes.iter().map(|byte| format!("{byte:02X}")).collect()
+}
+
+// Modules pad fixed-width text fields with NULs, spaces or 0xFF.
+pub fn ascii(bytes: &[u8]) -> String {
+  bytes
+    .iter()
+    .filter(|byte| byte.is_ascii_graphic() || **byte == b' ')
+    .map(|byte| *byte as char)
+    .collect::<String>()
+    .trim()
+    .to_string()
+}
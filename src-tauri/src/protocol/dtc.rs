@@ -0,0 +1,176 @@
+use crate::app_state::DtcStatus;
+
+pub const STATUS_TEST_FAILED: u8 = 0x01;
+pub const STATUS_TEST_FAILED_THIS_OPERATION_CYCLE: u8 = 0x02;
+pub const STATUS_PENDING: u8 = 0x04;
+pub const STATUS_CONFIRMED: u8 = 0x08;
+pub const STATUS_TEST_NOT_COMPLETED_SINCE_LAST_CLEAR: u8 = 0x10;
+pub const STATUS_TEST_FAILED_SINCE_LAST_CLEAR: u8 = 0x20;
+pub const STATUS_TEST_NOT_COMPLETED_THIS_OPERATION_CYCLE: u8 = 0x40;
+pub const STATUS_WARNING_INDICATOR_REQUESTED: u8 = 0x80;
+
+// SAE J2012: two bits of system letter, one digit 0-3, three hex digits, then the failure type byte.
+pub fn decode_dtc(bytes: [u8; 3]) -> String {
+  let letter = match bytes[0] >> 6 {
+    0 => 'P',
+    1 => 'C',
+    2 => 'B',
+    _ => 'U',
+  };
+  let base = format!(
+    "{}{}{:X}{:02X}",
+    letter,
+    (bytes[0] >> 4) & 0x03,
+    bytes[0] & 0x0F,
+    bytes[1]
+  );
+  if bytes[2] == 0 {
+    base
+  } else {
+    format!("{}-{:02X}", base, bytes[2])
+  }
+}
+
+pub fn encode_dtc(code: &str) -> Option<[u8; 3]> {
+  let (base, failure_type) = match code.split_once('-') {
+    Some((base, failure_type)) => (base, u8::from_str_radix(failure_type, 16).ok()?),
+    None => (code, 0),
+  };
+  let mut chars = base.chars();
+  let system = match chars.next()? {
+    'P' => 0u8,
+    'C' => 1,
+    'B' => 2,
+    'U' => 3,
+    _ => return None,
+  };
+  let digits = chars.as_str();
+  // Byte slicing below; a multi-byte character could make four bytes out of fewer digits.
+  if digits.len() != 4 || !digits.is_ascii() {
+    return None;
+  }
+  let first = digits[0..1].parse::<u8>().ok().filter(|value| *value <= 3)?;
+  let second = u8::from_str_radix(&digits[1..2], 16).ok()?;
+  let low = u8::from_str_radix(&digits[2..4], 16).ok()?;
+  Some([(system << 6) | (first << 4) | second, low, failure_type])
+}
+
+pub fn decode_status(byte: u8) -> DtcStatus {
+  DtcStatus {
+    test_failed: byte & STATUS_TEST_FAILED != 0,
+    test_failed_this_operation_cycle: byte & STATUS_TEST_FAILED_THIS_OPERATION_CYCLE != 0,
+    pending: byte & STATUS_PENDING != 0,
+    confirmed: byte & STATUS_CONFIRMED != 0,
+    test_not_completed_since_last_clear: byte & STATUS_TEST_NOT_COMPLETED_SINCE_LAST_CLEAR != 0,
+    test_failed_since_last_clear: byte & STATUS_TEST_FAILED_SINCE_LAST_CLEAR != 0,
+    test_not_completed_this_operation_cycle: byte
+      & STATUS_TEST_NOT_COMPLETED_THIS_OPERATION_CYCLE
+      != 0,
+    warning_indicator_requested: byte & STATUS_WARNING_INDICATOR_REQUESTED != 0,
+  }
+}
+
+pub fn status_label(status: &DtcStatus) -> &'static str {
+  if status.test_failed {
+    "active"
+  } else if status.pending {
+    "pending"
+  } else if status.confirmed {
+    "stored"
+  } else {
+    "inactive"
+  }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ExtendedRecordDefinition {
+  pub record_number: u8,
+  pub name: &'static str,
+  pub length: usize,
+}
+
+// Record layouts are OEM-defined; these are the counters most modules agree on.
+const EXTENDED_RECORDS: &[ExtendedRecordDefinition] = &[
+  ExtendedRecordDefinition {
+    record_number: 0x01,
+    name: "Occurrence Counter",
+    length: 1,
+  },
+  ExtendedRecordDefinition {
+    record_number: 0x02,
+    name: "Aging Counter",
+    length: 1,
+  },
+  ExtendedRecordDefinition {
+    record_number: 0x03,
+    name: "Aged Counter",
+    length: 1,
+  },
+];
+
+pub fn extended_record(record_number: u8) -> Option<&'static ExtendedRecordDefinition> {
+  EXTENDED_RECORDS
+    .iter()
+    .find(|definition| definition.record_number == record_number)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn decodes_j2012_codes() {
+    assert_eq!(decode_dtc([0x01, 0x23, 0x00]), "P0123");
+    assert_eq!(decode_dtc([0x41, 0x31, 0x00]), "C0131");
+    assert_eq!(decode_dtc([0x9A, 0x12, 0x13]), "B1A12-13");
+    assert_eq!(decode_dtc([0xC1, 0x00, 0x87]), "U0100-87");
+  }
+
+  #[test]
+  fn encodes_and_round_trips() {
+    assert_eq!(encode_dtc("P0123"), Some([0x01, 0x23, 0x00]));
+    assert_eq!(encode_dtc("U0100-87"), Some([0xC1, 0x00, 0x87]));
+    for bytes in [
+      [0x01, 0x23, 0x00],
+      [0x41, 0x31, 0x00],
+      [0x9A, 0x12, 0x13],
+      [0xFF, 0xFF, 0xFF],
+    ] {
+      assert_eq!(encode_dtc(&decode_dtc(bytes)), Some(bytes));
+    }
+  }
+
+  #[test]
+  fn rejects_malformed_codes() {
+    assert_eq!(encode_dtc("X0123"), None);
+    assert_eq!(encode_dtc("P4123"), None);
+    assert_eq!(encode_dtc("P012"), None);
+    assert_eq!(encode_dtc("P0123-ZZ"), None);
+    assert_eq!(encode_dtc("P€1"), None);
+  }
+
+  #[test]
+  fn decodes_status_bits() {
+    let status = decode_status(0x09);
+    assert!(status.test_failed && status.confirmed);
+    assert!(!status.pending && !status.warning_indicator_requested);
+    assert_eq!(status_label(&status), "active");
+    assert_eq!(status_label(&decode_status(0x04)), "pending");
+    assert_eq!(status_label(&decode_status(0x08)), "stored");
+    assert_eq!(status_label(&decode_status(0x00)), "inactive");
+    assert!(decode_status(0x80).warning_indicator_requested);
+    assert_eq!(
+      decode_status(0xFF),
+      DtcStatus {
+        test_failed: true,
+        test_failed_this_operation_cycle: true,
+        pending: true,
+        confirmed: true,
+        test_not_completed_since_last_clear: true,
+        test_failed_since_last_clear: true,
+        test_not_completed_this_operation_cycle: true,
+        warning_indicator_requested: true,
+      }
+    );
+  }
+}
@@ -1,41 +1,76 @@
+use std::collections::{BTreeMap, HashMap};
 use std::time::{Duration, Instant};
 
+use crate::protocol::addressing::EcuAddress;
 use crate::transport::{Frame, Transport};
 
+const FC_CONTINUE_TO_SEND: u8 = 0x0;
+const FC_WAIT: u8 = 0x1;
+const FC_OVERFLOW: u8 = 0x2;
+
+#[derive(Debug, Clone)]
+pub struct IsoTpConfig {
+  pub n_bs_ms: u64,
+  pub n_cr_ms: u64,
+  pub max_wait_frames: u8,
+  pub block_size: u8,
+  pub st_min: u8,
+}
+
+impl Default for IsoTpConfig {
+  fn default() -> Self {
+    Self {
+      n_bs_ms: 1000,
+      n_cr_ms: 1000,
+      max_wait_frames: 10,
+      block_size: 0,
+      st_min: 0,
+    }
+  }
+}
+
+struct Reception {
+  total_len: usize,
+  buffer: Vec<u8>,
+  next_seq: u8,
+  block_count: u8,
+  deadline: Instant,
+}
+
+struct FlowControl {
+  status: u8,
+  block_size: u8,
+  st_min: Duration,
+}
+
 pub struct IsoTpLink<'a, T: Transport> {
   transport: &'a mut T,
-  tx_id: u32,
-  rx_id: u32,
-  is_extended: bool,
+  address: EcuAddress,
+  config: IsoTpConfig,
 }
 
 impl<'a, T: Transport> IsoTpLink<'a, T> {
-  pub fn new(transport: &'a mut T, tx_id: u32, rx_id: u32, is_extended: bool) -> Self {
+  pub fn new(transport: &'a mut T, address: EcuAddress) -> Self {
     Self {
       transport,
-      tx_id,
-      rx_id,
-      is_extended,
+      address,
+      config: IsoTpConfig::default(),
     }
   }
 
-  pub fn request(&mut self, payload: &[u8], timeout_ms: u64) -> Result<Vec<u8>, String> {
-    self.send_payload(payload)?;
-    self.recv_payload(timeout_ms)
+  pub fn with_config(mut self, config: IsoTpConfig) -> Self {
+    self.config = config;
+    self
   }
 
-  fn send_payload(&mut self, payload: &[u8]) -> Result<(), String> {
-    if payload.len() <= 7 {
-      let mut data = vec![0u8; 8];
+  // Extended addressing spends one byte of every frame on the target address.
+  pub fn send_payload(&mut self, payload: &[u8]) -> Result<(), String> {
+    let capacity = self.address.frame_capacity();
+    if payload.len() < capacity {
+      let mut data = vec![0u8; capacity];
       data[0] = payload.len() as u8;
       data[1..1 + payload.len()].copy_from_slice(payload);
-      let frame = Frame {
-        id: self.tx_id,
-        data,
-        timestamp_ms: 0,
-        is_extended: self.is_extended,
-      };
-      return self.transport.send(&frame);
+      return self.send_frame(data);
     }
 
     let total_len = payload.len();
@@ -43,101 +78,678 @@ impl<'a, T: Transport> IsoTpLink<'a, T> {
       return Err("ISO-TP payload too large".to_string());
     }
 
-    let mut data = vec![0u8; 8];
+    let mut data = vec![0u8; capacity];
     data[0] = 0x10 | ((total_len >> 8) as u8 & 0x0F);
     data[1] = (total_len & 0xFF) as u8;
-    data[2..8].copy_from_slice(&payload[0..6]);
-
-    let frame = Frame {
-      id: self.tx_id,
-      data,
-      timestamp_ms: 0,
-      is_extended: self.is_extended,
-    };
-    self.transport.send(&frame)?;
+    data[2..].copy_from_slice(&payload[0..capacity - 2]);
+    self.send_frame(data)?;
 
-    let mut offset = 6;
+    let mut offset = capacity - 2;
     let mut seq = 1u8;
+    let mut waits = 0u8;
     while offset < total_len {
-      let chunk_len = usize::min(7, total_len - offset);
-      let mut cf = vec![0u8; 8];
-      cf[0] = 0x20 | (seq & 0x0F);
-      cf[1..1 + chunk_len].copy_from_slice(&payload[offset..offset + chunk_len]);
-      let frame = Frame {
-        id: self.tx_id,
-        data: cf,
-        timestamp_ms: 0,
-        is_extended: self.is_extended,
-      };
-      self.transport.send(&frame)?;
-      offset += chunk_len;
-      seq = seq.wrapping_add(1);
+      let flow = self.wait_flow_control()?;
+      match flow.status {
+        FC_CONTINUE_TO_SEND => {}
+        FC_WAIT => {
+          waits += 1;
+          if waits > self.config.max_wait_frames {
+            return Err(format!(
+              "ISO-TP send aborted: receiver sent more than {} FC.WAIT frames",
+              self.config.max_wait_frames
+            ));
+          }
+          continue;
+        }
+        FC_OVERFLOW => return Err("ISO-TP send aborted: receiver reported buffer overflow".to_string()),
+        status => return Err(format!("ISO-TP send aborted: invalid flow status 0x{status:X}")),
+      }
+      waits = 0;
+
+      let mut sent_in_block = 0u16;
+      while offset < total_len {
+        if sent_in_block > 0 && !flow.st_min.is_zero() {
+          std::thread::sleep(flow.st_min);
+        }
+        let chunk_len = usize::min(capacity - 1, total_len - offset);
+        let mut cf = vec![0u8; capacity];
+        cf[0] = 0x20 | (seq & 0x0F);
+        cf[1..1 + chunk_len].copy_from_slice(&payload[offset..offset + chunk_len]);
+        self.send_frame(cf)?;
+        offset += chunk_len;
+        seq = seq.wrapping_add(1);
+        sent_in_block += 1;
+        if flow.block_size != 0 && sent_in_block == flow.block_size as u16 {
+          break;
+        }
+      }
     }
 
     Ok(())
   }
 
-  fn recv_payload(&mut self, timeout_ms: u64) -> Result<Vec<u8>, String> {
-    let deadline = Instant::now() + Duration::from_millis(timeout_ms);
-    let mut buffer: Vec<u8> = Vec::new();
-    let mut total_len: Option<usize> = None;
-
+  fn wait_flow_control(&mut self) -> Result<FlowControl, String> {
+    let deadline = Instant::now() + Duration::from_millis(self.config.n_bs_ms);
     while Instant::now() < deadline {
       let remaining = deadline.saturating_duration_since(Instant::now());
-      let timeout = remaining.as_millis().clamp(10, 250) as u64;
+      let timeout = remaining.as_millis().clamp(1, 250) as u64;
       let frame = match self.transport.recv(timeout)? {
         Some(frame) => frame,
         None => continue,
       };
-
-      if frame.id != self.rx_id {
+      let Some(data) = self.address.strip(&frame) else {
         continue;
+      };
+      if data.is_empty() || data[0] >> 4 != 0x3 {
+        continue;
+      }
+      return Ok(FlowControl {
+        status: data[0] & 0x0F,
+        block_size: data.get(1).copied().unwrap_or(0),
+        st_min: decode_st_min(data.get(2).copied().unwrap_or(0)),
+      });
+    }
+    Err("ISO-TP timeout waiting for flow control (N_Bs)".to_string())
+  }
+
+  fn send_frame(&mut self, data: Vec<u8>) -> Result<(), String> {
+    self.transport.send(&self.address.frame(&data))
+  }
+
+  pub fn recv_payload(&mut self, timeout_ms: u64) -> Result<Vec<u8>, String> {
+    let response_deadline = Instant::now() + Duration::from_millis(timeout_ms);
+    let mut reception: Option<Reception> = None;
+
+    loop {
+      // Once a First Frame arrives, each Consecutive Frame gets its own N_Cr window.
+      let deadline = reception
+        .as_ref()
+        .map(|active| active.deadline)
+        .unwrap_or(response_deadline);
+      let now = Instant::now();
+      if now >= deadline {
+        return Err(match reception {
+          Some(active) => format!(
+            "ISO-TP timeout waiting for consecutive frame (N_Cr) after {} of {} bytes",
+            active.buffer.len(),
+            active.total_len
+          ),
+          None => "ISO-TP timeout waiting for response".to_string(),
+        });
       }
+      let timeout = deadline.saturating_duration_since(now).as_millis().clamp(1, 250) as u64;
+      let frame = match self.transport.recv(timeout)? {
+        Some(frame) => frame,
+        None => continue,
+      };
 
-      if frame.data.is_empty() {
+      let Some(data) = self.address.strip(&frame) else {
+        continue;
+      };
+      if data.is_empty() {
         continue;
       }
 
-      let pci = frame.data[0] >> 4;
-      match pci {
+      match data[0] >> 4 {
         0x0 => {
-          let len = (frame.data[0] & 0x0F) as usize;
-          let end = usize::min(1 + len, frame.data.len());
-          return Ok(frame.data[1..end].to_vec());
+          let len = (data[0] & 0x0F) as usize;
+          if len == 0 {
+            continue;
+          }
+          if data.len() < 1 + len {
+            return Err(format!(
+              "ISO-TP single frame truncated: length {len}, frame has {} data bytes",
+              data.len() - 1
+            ));
+          }
+          return Ok(data[1..1 + len].to_vec());
         }
         0x1 => {
-          let len = (((frame.data[0] as usize) & 0x0F) << 8) | frame.data[1] as usize;
-          total_len = Some(len);
-          buffer.extend_from_slice(&frame.data[2..8]);
-
-          let mut fc = vec![0u8; 8];
-          fc[0] = 0x30;
-          fc[1] = 0x00;
-          fc[2] = 0x00;
-          let flow = Frame {
-            id: self.tx_id,
-            data: fc,
-            timestamp_ms: 0,
-            is_extended: self.is_extended,
-          };
-          self.transport.send(&flow)?;
+          if data.len() < self.address.frame_capacity() {
+            return Err(format!("ISO-TP first frame truncated: {} bytes", data.len()));
+          }
+          let total_len = (((data[0] as usize) & 0x0F) << 8) | data[1] as usize;
+          if total_len < self.address.frame_capacity() {
+            continue;
+          }
+          reception = Some(Reception {
+            total_len,
+            buffer: data[2..].to_vec(),
+            next_seq: 1,
+            block_count: 0,
+            deadline: Instant::now() + Duration::from_millis(self.config.n_cr_ms),
+          });
+          self.send_flow_control()?;
         }
         0x2 => {
-          if total_len.is_none() {
+          let Some(active) = reception.as_mut() else {
             continue;
+          };
+          let seq = data[0] & 0x0F;
+          if seq != active.next_seq {
+            return Err(format!(
+              "ISO-TP sequence error: expected consecutive frame {:X}, got {:X} after {} of {} bytes",
+              active.next_seq,
+              seq,
+              active.buffer.len(),
+              active.total_len
+            ));
           }
-          buffer.extend_from_slice(&frame.data[1..8]);
-          if let Some(len) = total_len {
-            if buffer.len() >= len {
-              buffer.truncate(len);
-              return Ok(buffer);
-            }
+          let take = usize::min(data.len() - 1, active.total_len - active.buffer.len());
+          active.buffer.extend_from_slice(&data[1..1 + take]);
+          if active.buffer.len() >= active.total_len {
+            return Ok(std::mem::take(&mut active.buffer));
+          }
+          active.next_seq = (active.next_seq + 1) & 0x0F;
+          active.deadline = Instant::now() + Duration::from_millis(self.config.n_cr_ms);
+          active.block_count = active.block_count.wrapping_add(1);
+          if self.config.block_size != 0 && active.block_count == self.config.block_size {
+            active.block_count = 0;
+            self.send_flow_control()?;
           }
         }
         _ => continue,
       }
     }
+  }
+
+  fn send_flow_control(&mut self) -> Result<(), String> {
+    self.send_frame(flow_control_data(&self.config, self.address.frame_capacity()))
+  }
+}
+
+// Adapters with a native ISO-TP channel take the payload as is; for the rest it is segmented here.
+pub fn send_payload<T: Transport>(
+  transport: &mut T,
+  address: EcuAddress,
+  payload: &[u8],
+  config: &IsoTpConfig,
+) -> Result<(), String> {
+  if transport.supports_payloads() {
+    return transport.send_payload(address, payload);
+  }
+  IsoTpLink::new(transport, address)
+    .with_config(config.clone())
+    .send_payload(payload)
+}
+
+pub fn recv_payload<T: Transport>(
+  transport: &mut T,
+  address: EcuAddress,
+  timeout_ms: u64,
+  config: &IsoTpConfig,
+) -> Result<Vec<u8>, String> {
+  if transport.supports_payloads() {
+    return transport.recv_payload(address, timeout_ms);
+  }
+  IsoTpLink::new(transport, address)
+    .with_config(config.clone())
+    .recv_payload(timeout_ms)
+}
+
+// Sends a single-frame request on a functional ID and reassembles every responder's answer side by side.
+// Flow control for a multi-frame answer goes to the responder's physical address. A payload for which
+// `pending_ms` returns a timeout is not final: collection for that responder continues that long.
+pub fn functional_request<T: Transport>(
+  transport: &mut T,
+  request_id: u32,
+  responders: &[EcuAddress],
+  payload: &[u8],
+  window_ms: u64,
+  config: &IsoTpConfig,
+  pending_ms: impl Fn(&[u8]) -> Option<u64>,
+) -> Result<BTreeMap<u32, Result<Vec<u8>, String>>, String> {
+  if payload.is_empty() || payload.len() > 7 {
+    return Err("Functional requests must fit in a single frame".to_string());
+  }
+  let mut data = vec![0u8; 8];
+  data[0] = payload.len() as u8;
+  data[1..1 + payload.len()].copy_from_slice(payload);
+  transport.send(&Frame {
+    id: request_id,
+    data,
+    timestamp_ms: 0,
+    is_extended: request_id > 0x7FF,
+  })?;
+
+  let window_deadline = Instant::now() + Duration::from_millis(window_ms);
+  let mut results = BTreeMap::new();
+  let mut receptions: HashMap<u32, Reception> = HashMap::new();
+  let mut pending: HashMap<u32, Instant> = HashMap::new();
+
+  loop {
+    let now = Instant::now();
+    receptions.retain(|rx_id, active| {
+      if now < active.deadline {
+        return true;
+      }
+      results.insert(
+        *rx_id,
+        Err(format!(
+          "ISO-TP timeout waiting for consecutive frame (N_Cr) after {} of {} bytes",
+          active.buffer.len(),
+          active.total_len
+        )),
+      );
+      false
+    });
+    pending.retain(|_, deadline| now < *deadline);
+    // The window only bounds new responders; ones already answering are followed to completion.
+    let deadline = receptions
+      .values()
+      .map(|active| active.deadline)
+      .chain(pending.values().copied())
+      .chain(std::iter::once(window_deadline))
+      .max()
+      .unwrap_or(window_deadline);
+    if now >= deadline {
+      break;
+    }
+    let timeout = deadline.saturating_duration_since(now).as_millis().clamp(1, 250) as u64;
+    let Some(frame) = transport.recv(timeout)? else {
+      continue;
+    };
+    let Some((responder, data)) = responders
+      .iter()
+      .find_map(|responder| responder.strip(&frame).map(|data| (responder, data)))
+    else {
+      continue;
+    };
+    let rx_id = responder.rx_id;
+    let late = now >= window_deadline
+      && !receptions.contains_key(&rx_id)
+      && !pending.contains_key(&rx_id);
+    if data.is_empty() || late {
+      continue;
+    }
+
+    let complete = match data[0] >> 4 {
+      0x0 => {
+        let len = (data[0] & 0x0F) as usize;
+        if len == 0 {
+          continue;
+        }
+        if data.len() < 1 + len {
+          results.insert(rx_id, Err(format!("ISO-TP single frame truncated: length {len}")));
+          pending.remove(&rx_id);
+          continue;
+        }
+        receptions.remove(&rx_id);
+        data[1..1 + len].to_vec()
+      }
+      0x1 => {
+        if data.len() < responder.frame_capacity() {
+          continue;
+        }
+        let total_len = (((data[0] as usize) & 0x0F) << 8) | data[1] as usize;
+        if total_len < responder.frame_capacity() {
+          continue;
+        }
+        receptions.insert(
+          rx_id,
+          Reception {
+            total_len,
+            buffer: data[2..].to_vec(),
+            next_seq: 1,
+            block_count: 0,
+            deadline: Instant::now() + Duration::from_millis(config.n_cr_ms),
+          },
+        );
+        send_flow_control_to(transport, responder, config)?;
+        continue;
+      }
+      0x2 => {
+        let Some(active) = receptions.get_mut(&rx_id) else {
+          continue;
+        };
+        let seq = data[0] & 0x0F;
+        if seq != active.next_seq {
+          results.insert(
+            rx_id,
+            Err(format!(
+              "ISO-TP sequence error: expected consecutive frame {:X}, got {:X}",
+              active.next_seq, seq
+            )),
+          );
+          receptions.remove(&rx_id);
+          pending.remove(&rx_id);
+          continue;
+        }
+        let take = usize::min(data.len() - 1, active.total_len - active.buffer.len());
+        active.buffer.extend_from_slice(&data[1..1 + take]);
+        if active.buffer.len() < active.total_len {
+          active.next_seq = (active.next_seq + 1) & 0x0F;
+          active.deadline = Instant::now() + Duration::from_millis(config.n_cr_ms);
+          active.block_count = active.block_count.wrapping_add(1);
+          if config.block_size != 0 && active.block_count == config.block_size {
+            active.block_count = 0;
+            send_flow_control_to(transport, responder, config)?;
+          }
+          continue;
+        }
+        let Some(active) = receptions.remove(&rx_id) else {
+          continue;
+        };
+        active.buffer
+      }
+      _ => continue,
+    };
+
+    match pending_ms(&complete) {
+      Some(wait_ms) => {
+        pending.insert(rx_id, Instant::now() + Duration::from_millis(wait_ms));
+      }
+      None => {
+        pending.remove(&rx_id);
+      }
+    }
+    results.insert(rx_id, Ok(complete));
+  }
+
+  Ok(results)
+}
+
+// Sends the same single-frame request to every address back to back, then listens once for all of them.
+// Responses are told apart by RX ID (and address byte), so the whole batch costs a single timeout.
+pub fn probe<T: Transport>(
+  transport: &mut T,
+  addresses: &[EcuAddress],
+  payload: &[u8],
+  timeout_ms: u64,
+) -> Result<Vec<(EcuAddress, Duration)>, String> {
+  let mut sent = Vec::with_capacity(addresses.len());
+  for address in addresses {
+    transport.send(&address.frame(&probe_data(payload, address.frame_capacity())?))?;
+    sent.push((*address, Instant::now()));
+  }
+  collect_probe_replies(transport, &sent, timeout_ms, true)
+}
+
+// One functional single frame; every responder that answers inside the window is reported.
+pub fn probe_functional<T: Transport>(
+  transport: &mut T,
+  request_id: u32,
+  responders: &[EcuAddress],
+  payload: &[u8],
+  timeout_ms: u64,
+) -> Result<Vec<(EcuAddress, Duration)>, String> {
+  transport.send(&Frame {
+    id: request_id,
+    data: probe_data(payload, 8)?,
+    timestamp_ms: 0,
+    is_extended: request_id > 0x7FF,
+  })?;
+  let sent_at = Instant::now();
+  let sent = responders
+    .iter()
+    .map(|responder| (*responder, sent_at))
+    .collect::<Vec<_>>();
+  collect_probe_replies(transport, &sent, timeout_ms, false)
+}
+
+fn probe_data(payload: &[u8], capacity: usize) -> Result<Vec<u8>, String> {
+  if payload.is_empty() || payload.len() >= capacity {
+    return Err("Probe requests must fit in a single frame".to_string());
+  }
+  let mut data = vec![0u8; capacity];
+  data[0] = payload.len() as u8;
+  data[1..1 + payload.len()].copy_from_slice(payload);
+  Ok(data)
+}
+
+fn collect_probe_replies<T: Transport>(
+  transport: &mut T,
+  sent: &[(EcuAddress, Instant)],
+  timeout_ms: u64,
+  stop_when_complete: bool,
+) -> Result<Vec<(EcuAddress, Duration)>, String> {
+  let deadline = Instant::now() + Duration::from_millis(timeout_ms);
+  let mut responders = Vec::new();
+  while !(stop_when_complete && responders.len() == sent.len()) {
+    let now = Instant::now();
+    if now >= deadline {
+      break;
+    }
+    let timeout = deadline.saturating_duration_since(now).as_millis().clamp(1, 250) as u64;
+    let Some(frame) = transport.recv(timeout)? else {
+      continue;
+    };
+    let received = Instant::now();
+    let Some((address, sent_at)) = sent
+      .iter()
+      .find(|(address, _)| address.strip(&frame).is_some_and(|data| !data.is_empty()))
+    else {
+      continue;
+    };
+    if !responders.iter().any(|(responder, _)| responder == address) {
+      responders.push((*address, received.saturating_duration_since(*sent_at)));
+    }
+  }
+  Ok(responders)
+}
+
+fn send_flow_control_to<T: Transport>(
+  transport: &mut T,
+  responder: &EcuAddress,
+  config: &IsoTpConfig,
+) -> Result<(), String> {
+  transport.send(&responder.frame(&flow_control_data(config, responder.frame_capacity())))
+}
+
+fn flow_control_data(config: &IsoTpConfig, capacity: usize) -> Vec<u8> {
+  let mut fc = vec![0u8; capacity];
+  fc[0] = 0x30 | FC_CONTINUE_TO_SEND;
+  fc[1] = config.block_size;
+  fc[2] = config.st_min;
+  fc
+}
+
+fn decode_st_min(raw: u8) -> Duration {
+  match raw {
+    0x00..=0x7F => Duration::from_millis(raw as u64),
+    0xF1..=0xF9 => Duration::from_micros((raw - 0xF0) as u64 * 100),
+    // Reserved values must be treated as the longest valid STmin.
+    _ => Duration::from_millis(0x7F),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::transport::SimTransport;
+
+  fn receive(frames: &[Vec<u8>]) -> Result<Vec<u8>, String> {
+    let mut transport = SimTransport::new();
+    transport.open().unwrap();
+    for data in frames {
+      transport.push_frame(Frame {
+        id: 0x7E8,
+        data: data.clone(),
+        timestamp_ms: 0,
+        is_extended: false,
+      });
+    }
+    IsoTpLink::new(&mut transport, EcuAddress::normal(0x7E0, 0x7E8)).recv_payload(50)
+  }
+
+  // Replays the receiver's flow control frames and keeps everything the sender put on the bus.
+  struct Receiver {
+    flow_control: SimTransport,
+    sent: Vec<Vec<u8>>,
+  }
+
+  impl Transport for Receiver {
+    fn open(&mut self) -> Result<(), String> {
+      self.flow_control.open()
+    }
+    fn close(&mut self) {}
+    fn send(&mut self, frame: &Frame) -> Result<(), String> {
+      assert_eq!(frame.id, 0x7E0);
+      self.sent.push(frame.data.clone());
+      Ok(())
+    }
+    fn recv(&mut self, timeout_ms: u64) -> Result<Option<Frame>, String> {
+      self.flow_control.recv(timeout_ms)
+    }
+    fn set_filters(&mut self, _filters: Vec<crate::transport::Filter>) -> Result<(), String> {
+      Ok(())
+    }
+    fn set_baud(&mut self, _baud: u32) -> Result<(), String> {
+      Ok(())
+    }
+    fn set_bus(&mut self, _bus: crate::transport::BusType) -> Result<(), String> {
+      Ok(())
+    }
+    fn set_timing(&mut self, _timing: crate::transport::TimingConfig) -> Result<(), String> {
+      Ok(())
+    }
+  }
+
+  fn send(
+    payload: &[u8],
+    flow_control: &[[u8; 3]],
+    config: IsoTpConfig,
+  ) -> (Result<(), String>, Vec<Vec<u8>>) {
+    let mut receiver = Receiver {
+      flow_control: SimTransport::new(),
+      sent: Vec::new(),
+    };
+    receiver.open().unwrap();
+    for data in flow_control {
+      receiver.flow_control.push_frame(Frame {
+        id: 0x7E8,
+        data: data.to_vec(),
+        timestamp_ms: 0,
+        is_extended: false,
+      });
+    }
+    let result = IsoTpLink::new(&mut receiver, EcuAddress::normal(0x7E0, 0x7E8))
+      .with_config(config)
+      .send_payload(payload);
+    (result, receiver.sent)
+  }
+
+  fn quick_n_bs() -> IsoTpConfig {
+    IsoTpConfig {
+      n_bs_ms: 20,
+      ..IsoTpConfig::default()
+    }
+  }
+
+  // First Frame plus Consecutive Frames the way an ECU would send them, the last one padded.
+  fn segment(payload: &[u8]) -> Vec<Vec<u8>> {
+    let mut frames = vec![[
+      &[0x10 | (payload.len() >> 8) as u8, payload.len() as u8][..],
+      &payload[..6],
+    ]
+    .concat()];
+    for (index, chunk) in payload[6..].chunks(7).enumerate() {
+      let mut cf = vec![0xAA; 8];
+      cf[0] = 0x20 | ((index + 1) & 0x0F) as u8;
+      cf[1..1 + chunk.len()].copy_from_slice(chunk);
+      frames.push(cf);
+    }
+    frames
+  }
+
+  #[test]
+  fn reads_single_frame() {
+    let payload = receive(&[vec![0x03, 0x62, 0xF1, 0x90, 0xAA, 0xAA, 0xAA, 0xAA]]);
+    assert_eq!(payload.unwrap(), vec![0x62, 0xF1, 0x90]);
+  }
+
+  #[test]
+  fn reassembles_first_and_consecutive_frames() {
+    let mut payload = vec![0x62, 0xF1, 0x90];
+    payload.extend_from_slice(b"1FT7W2BT6KED12345");
+    assert_eq!(receive(&segment(&payload)).unwrap(), payload);
+  }
+
+  #[test]
+  fn wraps_the_sequence_number() {
+    // 18 Consecutive Frames: sequence numbers 1-F, then 0, 1, 2.
+    let payload = (0..130).map(|byte| byte as u8).collect::<Vec<_>>();
+    let frames = segment(&payload);
+    assert_eq!(frames[16][0], 0x20);
+    assert_eq!(receive(&frames).unwrap(), payload);
+  }
+
+  #[test]
+  fn rejects_out_of_sequence_frames() {
+    let mut frames = segment(&(0..20).collect::<Vec<u8>>());
+    frames.swap(1, 2);
+    let err = receive(&frames).unwrap_err();
+    assert!(err.contains("sequence error"), "{err}");
+  }
+
+  #[test]
+  fn rejects_truncated_frames() {
+    let err = receive(&[vec![0x10, 0x14, 0x62, 0xF1]]).unwrap_err();
+    assert!(err.contains("first frame truncated"), "{err}");
+    let err = receive(&[vec![0x05, 0x62, 0xF1]]).unwrap_err();
+    assert!(err.contains("single frame truncated"), "{err}");
+  }
+
+  #[test]
+  fn sends_blocks_of_the_receivers_block_size() {
+    let payload = (0..30).collect::<Vec<u8>>();
+    let started = Instant::now();
+    let (result, sent) = send(
+      &payload,
+      &[[0x30, 0x02, 0x05], [0x30, 0x02, 0x05]],
+      quick_n_bs(),
+    );
+    result.unwrap();
+    let pci = sent.iter().map(|frame| frame[0]).collect::<Vec<_>>();
+    assert_eq!(pci, vec![0x10, 0x21, 0x22, 0x23, 0x24]);
+    assert_eq!(&sent[0][..2], &[0x10, 30]);
+    assert_eq!(&sent[4][1..4], &[27, 28, 29]);
+    // STmin 5 ms between the two frames of each block.
+    assert!(started.elapsed() >= Duration::from_millis(10));
+  }
+
+  #[test]
+  fn stops_after_the_first_block_without_more_flow_control() {
+    let payload = (0..30).collect::<Vec<u8>>();
+    let (result, sent) = send(&payload, &[[0x30, 0x02, 0x00]], quick_n_bs());
+    assert!(result.unwrap_err().contains("N_Bs"));
+    assert_eq!(sent.len(), 3);
+  }
+
+  #[test]
+  fn gives_up_after_too_many_wait_frames() {
+    let config = IsoTpConfig {
+      max_wait_frames: 2,
+      ..quick_n_bs()
+    };
+    let payload = (0..20).collect::<Vec<u8>>();
+    let (result, sent) = send(
+      &payload,
+      &[[0x31, 0, 0], [0x31, 0, 0], [0x30, 0, 0]],
+      config.clone(),
+    );
+    result.unwrap();
+    assert_eq!(sent.len(), 3);
+
+    let (result, sent) = send(&payload, &[[0x31, 0, 0]; 3], config);
+    assert!(result.unwrap_err().contains("more than 2 FC.WAIT"));
+    assert_eq!(sent.len(), 1);
+  }
+
+  #[test]
+  fn aborts_on_overflow() {
+    let (result, sent) = send(&(0..20).collect::<Vec<u8>>(), &[[0x32, 0, 0]], quick_n_bs());
+    assert!(result.unwrap_err().contains("buffer overflow"));
+    assert_eq!(sent.len(), 1);
+  }
 
-    Err("ISO-TP timeout waiting for response".to_string())
+  #[test]
+  fn times_out_without_flow_control() {
+    let (result, sent) = send(&(0..20).collect::<Vec<u8>>(), &[], quick_n_bs());
+    assert_eq!(
+      result.unwrap_err(),
+      "ISO-TP timeout waiting for flow control (N_Bs)"
+    );
+    assert_eq!(sent, vec![vec![0x10, 20, 0, 1, 2, 3, 4, 5]]);
   }
 }
@@ -1,2 +1,6 @@
+pub mod addressing;
+pub mod did;
+pub mod dtc;
 pub mod isotp;
+pub mod security;
 pub mod uds;
@@ -0,0 +1,93 @@
+pub trait SeedKeyAlgorithm: Send + Sync {
+  fn name(&self) -> &str;
+  fn compute_key(&self, level: u8, seed: &[u8]) -> Result<Vec<u8>, String>;
+}
+
+#[derive(Debug, Clone)]
+pub struct XorSeedKey {
+  pub constant: Vec<u8>,
+}
+
+impl SeedKeyAlgorithm for XorSeedKey {
+  fn name(&self) -> &str {
+    "xor"
+  }
+
+  fn compute_key(&self, _level: u8, seed: &[u8]) -> Result<Vec<u8>, String> {
+    if self.constant.is_empty() {
+      return Err("XOR constant is empty".to_string());
+    }
+    Ok(
+      seed
+        .iter()
+        .zip(self.constant.iter().cycle())
+        .map(|(seed, constant)| seed ^ constant)
+        .collect(),
+    )
+  }
+}
+
+// Treats seed and constant as big-endian integers of the seed's width and adds them.
+#[derive(Debug, Clone)]
+pub struct AddSeedKey {
+  pub constant: Vec<u8>,
+}
+
+impl SeedKeyAlgorithm for AddSeedKey {
+  fn name(&self) -> &str {
+    "add"
+  }
+
+  fn compute_key(&self, _level: u8, seed: &[u8]) -> Result<Vec<u8>, String> {
+    if seed.len() > 8 || self.constant.len() > 8 {
+      return Err("Add algorithm supports seeds up to 8 bytes".to_string());
+    }
+    let seed_value = seed.iter().fold(0u64, |acc, byte| (acc << 8) | *byte as u64);
+    let constant = self.constant.iter().fold(0u64, |acc, byte| (acc << 8) | *byte as u64);
+    let key = seed_value.wrapping_add(constant).to_be_bytes();
+    Ok(key[8 - seed.len()..].to_vec())
+  }
+}
+
+// Errors name only positions and lengths; the input is often key material that ends up in logs.
+pub fn parse_hex(value: &str) -> Result<Vec<u8>, String> {
+  let digits = value
+    .trim()
+    .trim_start_matches("0x")
+    .bytes()
+    .filter(|byte| !byte.is_ascii_whitespace())
+    .collect::<Vec<_>>();
+  let pairs = digits.chunks_exact(2);
+  if !pairs.remainder().is_empty() {
+    return Err(format!("Odd number of hex digits ({})", digits.len()));
+  }
+  if let Some(position) = digits.iter().position(|digit| !digit.is_ascii_hexdigit()) {
+    return Err(format!("Invalid hex digit at position {}", position + 1));
+  }
+  pairs
+    .map(|pair| {
+      std::str::from_utf8(pair)
+        .ok()
+        .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+        .ok_or_else(|| "Invalid hex value".to_string())
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parses_hex_with_prefix_and_spaces() {
+    assert_eq!(parse_hex(" 0x12 AB cd ").unwrap(), vec![0x12, 0xAB, 0xCD]);
+  }
+
+  #[test]
+  fn errors_do_not_echo_the_input() {
+    let err = parse_hex("C0FFEZ").unwrap_err();
+    assert_eq!(err, "Invalid hex digit at position 6");
+    let err = parse_hex("C0FFE").unwrap_err();
+    assert_eq!(err, "Odd number of hex digits (5)");
+  }
+}
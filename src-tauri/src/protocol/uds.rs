@@ -1,93 +1,687 @@
-use crate::app_state::DtcInfo;
-use crate::protocol::isotp::IsoTpLink;
-use crate::transport::Transport;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::Duration;
+
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::app_state::{DiagnosticSession, DidValue, DtcExtendedData, DtcInfo, DtcSnapshot};
+use crate::protocol::addressing::{response_filters, EcuAddress};
+use crate::protocol::did::{decode, hex, snapshot_did, IDENTIFICATION_DIDS, VIN_DID};
+use crate::protocol::dtc::{decode_dtc, decode_status, extended_record, status_label};
+use crate::protocol::isotp::{
+  functional_request, probe, probe_functional, recv_payload, send_payload, IsoTpConfig,
+};
+use crate::protocol::security::SeedKeyAlgorithm;
+use crate::transport::{AdapterVersions, TimingConfig, Transport};
+
+const NEGATIVE_RESPONSE: u8 = 0x7F;
+const POSITIVE_OFFSET: u8 = 0x40;
+const DEFAULT_P2_STAR_MS: u64 = 5000;
+const MAX_PENDING_RESPONSES: u32 = 30;
+const BUSY_RETRIES: u8 = 3;
+const BUSY_RETRY_DELAY_MS: u64 = 200;
+const MAX_SILENT_DIDS: usize = 2;
+const P2_MARGIN_MS: u64 = 50;
+const SECURITY_DELAY_MS: u64 = 10_000;
+const MAX_SECURITY_DELAY_MS: u64 = 60_000;
+const MAX_SECURITY_ATTEMPTS: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Nrc {
+  GeneralReject,
+  ServiceNotSupported,
+  SubFunctionNotSupported,
+  IncorrectMessageLengthOrInvalidFormat,
+  ResponseTooLong,
+  BusyRepeatRequest,
+  ConditionsNotCorrect,
+  RequestSequenceError,
+  NoResponseFromSubnetComponent,
+  FailurePreventsExecutionOfRequestedAction,
+  RequestOutOfRange,
+  SecurityAccessDenied,
+  InvalidKey,
+  ExceededNumberOfAttempts,
+  RequiredTimeDelayNotExpired,
+  UploadDownloadNotAccepted,
+  TransferDataSuspended,
+  GeneralProgrammingFailure,
+  WrongBlockSequenceCounter,
+  ResponsePending,
+  SubFunctionNotSupportedInActiveSession,
+  ServiceNotSupportedInActiveSession,
+  Other(u8),
+}
+
+impl Nrc {
+  pub fn from_byte(code: u8) -> Self {
+    match code {
+      0x10 => Nrc::GeneralReject,
+      0x11 => Nrc::ServiceNotSupported,
+      0x12 => Nrc::SubFunctionNotSupported,
+      0x13 => Nrc::IncorrectMessageLengthOrInvalidFormat,
+      0x14 => Nrc::ResponseTooLong,
+      0x21 => Nrc::BusyRepeatRequest,
+      0x22 => Nrc::ConditionsNotCorrect,
+      0x24 => Nrc::RequestSequenceError,
+      0x25 => Nrc::NoResponseFromSubnetComponent,
+      0x26 => Nrc::FailurePreventsExecutionOfRequestedAction,
+      0x31 => Nrc::RequestOutOfRange,
+      0x33 => Nrc::SecurityAccessDenied,
+      0x35 => Nrc::InvalidKey,
+      0x36 => Nrc::ExceededNumberOfAttempts,
+      0x37 => Nrc::RequiredTimeDelayNotExpired,
+      0x70 => Nrc::UploadDownloadNotAccepted,
+      0x71 => Nrc::TransferDataSuspended,
+      0x72 => Nrc::GeneralProgrammingFailure,
+      0x73 => Nrc::WrongBlockSequenceCounter,
+      0x78 => Nrc::ResponsePending,
+      0x7E => Nrc::SubFunctionNotSupportedInActiveSession,
+      0x7F => Nrc::ServiceNotSupportedInActiveSession,
+      other => Nrc::Other(other),
+    }
+  }
+
+  pub fn code(&self) -> u8 {
+    match self {
+      Nrc::GeneralReject => 0x10,
+      Nrc::ServiceNotSupported => 0x11,
+      Nrc::SubFunctionNotSupported => 0x12,
+      Nrc::IncorrectMessageLengthOrInvalidFormat => 0x13,
+      Nrc::ResponseTooLong => 0x14,
+      Nrc::BusyRepeatRequest => 0x21,
+      Nrc::ConditionsNotCorrect => 0x22,
+      Nrc::RequestSequenceError => 0x24,
+      Nrc::NoResponseFromSubnetComponent => 0x25,
+      Nrc::FailurePreventsExecutionOfRequestedAction => 0x26,
+      Nrc::RequestOutOfRange => 0x31,
+      Nrc::SecurityAccessDenied => 0x33,
+      Nrc::InvalidKey => 0x35,
+      Nrc::ExceededNumberOfAttempts => 0x36,
+      Nrc::RequiredTimeDelayNotExpired => 0x37,
+      Nrc::UploadDownloadNotAccepted => 0x70,
+      Nrc::TransferDataSuspended => 0x71,
+      Nrc::GeneralProgrammingFailure => 0x72,
+      Nrc::WrongBlockSequenceCounter => 0x73,
+      Nrc::ResponsePending => 0x78,
+      Nrc::SubFunctionNotSupportedInActiveSession => 0x7E,
+      Nrc::ServiceNotSupportedInActiveSession => 0x7F,
+      Nrc::Other(code) => *code,
+    }
+  }
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      Nrc::GeneralReject => "generalReject",
+      Nrc::ServiceNotSupported => "serviceNotSupported",
+      Nrc::SubFunctionNotSupported => "subFunctionNotSupported",
+      Nrc::IncorrectMessageLengthOrInvalidFormat => "incorrectMessageLengthOrInvalidFormat",
+      Nrc::ResponseTooLong => "responseTooLong",
+      Nrc::BusyRepeatRequest => "busyRepeatRequest",
+      Nrc::ConditionsNotCorrect => "conditionsNotCorrect",
+      Nrc::RequestSequenceError => "requestSequenceError",
+      Nrc::NoResponseFromSubnetComponent => "noResponseFromSubnetComponent",
+      Nrc::FailurePreventsExecutionOfRequestedAction => "failurePreventsExecutionOfRequestedAction",
+      Nrc::RequestOutOfRange => "requestOutOfRange",
+      Nrc::SecurityAccessDenied => "securityAccessDenied",
+      Nrc::InvalidKey => "invalidKey",
+      Nrc::ExceededNumberOfAttempts => "exceededNumberOfAttempts",
+      Nrc::RequiredTimeDelayNotExpired => "requiredTimeDelayNotExpired",
+      Nrc::UploadDownloadNotAccepted => "uploadDownloadNotAccepted",
+      Nrc::TransferDataSuspended => "transferDataSuspended",
+      Nrc::GeneralProgrammingFailure => "generalProgrammingFailure",
+      Nrc::WrongBlockSequenceCounter => "wrongBlockSequenceCounter",
+      Nrc::ResponsePending => "requestCorrectlyReceivedResponsePending",
+      Nrc::SubFunctionNotSupportedInActiveSession => "subFunctionNotSupportedInActiveSession",
+      Nrc::ServiceNotSupportedInActiveSession => "serviceNotSupportedInActiveSession",
+      Nrc::Other(_) => "unknownNrc",
+    }
+  }
+
+  // Only a busy ECU is worth asking again unchanged; everything else needs the caller to act.
+  pub fn retry_delay(&self) -> Option<Duration> {
+    match self {
+      Nrc::BusyRepeatRequest => Some(Duration::from_millis(BUSY_RETRY_DELAY_MS)),
+      _ => None,
+    }
+  }
+}
+
+impl fmt::Display for Nrc {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{} (0x{:02X})", self.name(), self.code())
+  }
+}
+
+#[derive(Debug, Clone, Error)]
+pub enum UdsError {
+  #[error("{0}")]
+  Transport(String),
+  #[error("Negative response to service 0x{service:02X}: {nrc}")]
+  Negative { service: u8, nrc: Nrc },
+  #[error("{0}")]
+  UnexpectedResponse(String),
+  #[error("Service 0x{0:02X} still pending after {MAX_PENDING_RESPONSES} responsePending replies")]
+  PendingLimit(u8),
+  #[error("{0}")]
+  Security(String),
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecurityAttempt {
+  pub level: u8,
+  pub algorithm: String,
+  pub stage: String,
+  pub outcome: String,
+  pub delay_ms: u64,
+}
+
+impl UdsError {
+  pub fn nrc(&self) -> Option<Nrc> {
+    match self {
+      UdsError::Negative { nrc, .. } => Some(*nrc),
+      _ => None,
+    }
+  }
+}
 
 pub struct UdsClient<T: Transport> {
   transport: T,
-  timeout_ms: u64,
+  timing: TimingConfig,
+  min_p2_ms: u64,
   retries: u8,
+  isotp: IsoTpConfig,
 }
 
 impl<T: Transport> UdsClient<T> {
   pub fn new(transport: T, timeout_ms: u64, retries: u8) -> Self {
     Self {
       transport,
-      timeout_ms,
+      timing: TimingConfig {
+        p2_ms: timeout_ms,
+        p2_star_ms: DEFAULT_P2_STAR_MS.max(timeout_ms),
+      },
+      min_p2_ms: timeout_ms,
       retries,
+      isotp: IsoTpConfig::default(),
     }
   }
 
+  pub fn with_isotp_config(mut self, config: IsoTpConfig) -> Self {
+    self.isotp = config;
+    self
+  }
+
+  // Restores the P2/P2* a module negotiated earlier, or the client defaults when it has none.
+  pub fn use_timing(&mut self, timing: Option<TimingConfig>) -> Result<(), UdsError> {
+    let timing = timing.unwrap_or(TimingConfig {
+      p2_ms: self.min_p2_ms,
+      p2_star_ms: DEFAULT_P2_STAR_MS.max(self.min_p2_ms),
+    });
+    self
+      .transport
+      .set_timing(timing.clone())
+      .map_err(UdsError::Transport)?;
+    self.timing = timing;
+    Ok(())
+  }
+
   pub fn open(&mut self) -> Result<(), String> {
-    self.transport.open()
+    self.transport.open()?;
+    self.transport.set_filters(response_filters())?;
+    self.transport.set_timing(self.timing.clone())
   }
 
   pub fn close(&mut self) {
     self.transport.close();
   }
 
-  pub fn read_vin(&mut self, tx_id: u32, rx_id: u32) -> Result<String, String> {
-    let payload = [0x22, 0xF1, 0x90];
-    let response = self.request(tx_id, rx_id, &payload)?;
-    if response.len() < 3 || response[0] != 0x62 {
-      return Err("Unexpected VIN response".to_string());
-    }
-    let vin_bytes = &response[3..];
-    let vin = String::from_utf8_lossy(vin_bytes).trim().to_string();
+  pub fn read_vin(&mut self, address: EcuAddress) -> Result<String, UdsError> {
+    let vin_bytes = self.read_did(address, VIN_DID)?;
+    let vin = String::from_utf8_lossy(&vin_bytes).trim().to_string();
     Ok(vin)
   }
 
-  pub fn tester_present(&mut self, tx_id: u32, rx_id: u32) -> Result<(), String> {
-    let payload = [0x3E, 0x00];
-    let response = self.request(tx_id, rx_id, &payload)?;
-    if response.is_empty() {
-      return Err("No response to tester present".to_string());
+  pub fn read_did(&mut self, address: EcuAddress, did: u16) -> Result<Vec<u8>, UdsError> {
+    let [high, low] = did.to_be_bytes();
+    let response = self.request(address, &[0x22, high, low])?;
+    if response.len() < 3 || response[1..3] != [high, low] {
+      return Err(UdsError::UnexpectedResponse(format!(
+        "Unexpected response to DID 0x{did:04X}"
+      )));
+    }
+    Ok(response[3..].to_vec())
+  }
+
+  // RoutineControl startRoutine; returns the routine status record.
+  pub fn start_routine(
+    &mut self,
+    address: EcuAddress,
+    routine_id: u16,
+    option: &[u8],
+  ) -> Result<Vec<u8>, UdsError> {
+    let [high, low] = routine_id.to_be_bytes();
+    let mut payload = vec![0x31, 0x01, high, low];
+    payload.extend_from_slice(option);
+    let response = self.request(address, &payload)?;
+    if response.len() < 4 || response[1..4] != [0x01, high, low] {
+      return Err(UdsError::UnexpectedResponse(format!(
+        "Unexpected response to routine 0x{routine_id:04X}"
+      )));
+    }
+    Ok(response[4..].to_vec())
+  }
+
+  // One DID per request so an unsupported identifier only costs that entry.
+  pub fn read_identification(
+    &mut self,
+    address: EcuAddress,
+  ) -> (Vec<DidValue>, Vec<(u16, UdsError)>) {
+    let mut values = Vec::new();
+    let mut failures = Vec::new();
+    let mut silent = 0;
+    for definition in IDENTIFICATION_DIDS {
+      match self.read_did(address, definition.did) {
+        Ok(bytes) => {
+          silent = 0;
+          values.push(decode(definition, &bytes));
+        }
+        Err(err) => {
+          silent = if matches!(err, UdsError::Transport(_)) { silent + 1 } else { 0 };
+          failures.push((definition.did, err));
+          // A module that stops answering altogether is not worth the remaining timeouts.
+          if silent >= MAX_SILENT_DIDS {
+            break;
+          }
+        }
+      }
+    }
+    (values, failures)
+  }
+
+  // The server reports P2 in 1 ms and P2* in 10 ms units; P2 never drops below the adapter's own latency.
+  pub fn start_session(
+    &mut self,
+    address: EcuAddress,
+    session: DiagnosticSession,
+  ) -> Result<TimingConfig, UdsError> {
+    let sub_function = session.sub_function();
+    let response = self.request(address, &[0x10, sub_function])?;
+    if response.len() < 2 || response[1] != sub_function {
+      return Err(UdsError::UnexpectedResponse("Unexpected session control response".to_string()));
+    }
+    if response.len() >= 6 {
+      let p2_ms = u16::from_be_bytes([response[2], response[3]]) as u64;
+      let p2_star_ms = u16::from_be_bytes([response[4], response[5]]) as u64 * 10;
+      let timing = TimingConfig {
+        p2_ms: (p2_ms + P2_MARGIN_MS).max(self.min_p2_ms),
+        p2_star_ms: (p2_star_ms + P2_MARGIN_MS).max(self.min_p2_ms),
+      };
+      self
+        .transport
+        .set_timing(timing.clone())
+        .map_err(UdsError::Transport)?;
+      self.timing = timing;
+    }
+    Ok(self.timing.clone())
+  }
+
+  pub fn request_seed(&mut self, address: EcuAddress, level: u8) -> Result<Vec<u8>, UdsError> {
+    let response = self.request(address, &[0x27, level])?;
+    if response.len() < 2 || response[1] != level {
+      return Err(UdsError::UnexpectedResponse("Unexpected seed response".to_string()));
+    }
+    Ok(response[2..].to_vec())
+  }
+
+  pub fn send_key(&mut self, address: EcuAddress, level: u8, key: &[u8]) -> Result<(), UdsError> {
+    let key_level = key_level(level)?;
+    let mut payload = vec![0x27, key_level];
+    payload.extend_from_slice(key);
+    let response = self.request(address, &payload)?;
+    if response.len() < 2 || response[1] != key_level {
+      return Err(UdsError::UnexpectedResponse("Unexpected key response".to_string()));
     }
     Ok(())
   }
 
-  pub fn read_dtcs(&mut self, tx_id: u32, rx_id: u32) -> Result<Vec<DtcInfo>, String> {
-    let payload = [0x19, 0x02, 0xFF];
-    let response = self.request(tx_id, rx_id, &payload)?;
-    if response.len() < 2 || response[0] != 0x59 {
-      return Err("Unexpected DTC response".to_string());
+  // One requestSeed/sendKey round; `Ok(true)` when the level was already unlocked.
+  pub fn security_attempt(
+    &mut self,
+    address: EcuAddress,
+    level: u8,
+    algorithm: &dyn SeedKeyAlgorithm,
+  ) -> (&'static str, Result<bool, UdsError>) {
+    let seed = match self.request_seed(address, level) {
+      Ok(seed) => seed,
+      Err(err) => return ("seed", Err(err)),
+    };
+    // An all-zero seed means this level is already unlocked.
+    if seed.iter().all(|byte| *byte == 0) {
+      return ("seed", Ok(true));
+    }
+    let key = match algorithm.compute_key(level, &seed) {
+      Ok(key) => key,
+      Err(err) => return ("compute", Err(UdsError::Security(err))),
+    };
+    ("key", self.send_key(address, level, &key).map(|_| false))
+  }
+
+  // Tester-present to many modules at once; any reply, positive or negative, counts as present.
+  pub fn probe_tester_present(
+    &mut self,
+    addresses: &[EcuAddress],
+    timeout_ms: u64,
+  ) -> Result<Vec<(EcuAddress, Duration)>, UdsError> {
+    probe(&mut self.transport, addresses, &[0x3E, 0x00], timeout_ms).map_err(UdsError::Transport)
+  }
+
+  pub fn probe_tester_present_functional(
+    &mut self,
+    request_id: u32,
+    responders: &[EcuAddress],
+    timeout_ms: u64,
+  ) -> Result<Vec<(EcuAddress, Duration)>, UdsError> {
+    probe_functional(&mut self.transport, request_id, responders, &[0x3E, 0x00], timeout_ms)
+      .map_err(UdsError::Transport)
+  }
+
+  pub fn read_dtcs(&mut self, address: EcuAddress) -> Result<Vec<DtcInfo>, UdsError> {
+    self.read_dtc_list(address, &[0x02, 0xFF])
+  }
+
+  pub fn count_dtcs(&mut self, address: EcuAddress, status_mask: u8) -> Result<u16, UdsError> {
+    let response = self.read_dtc_information(address, &[0x01, status_mask])?;
+    if response.len() < 6 {
+      return Err(UdsError::UnexpectedResponse("Unexpected DTC count response".to_string()));
+    }
+    Ok(u16::from_be_bytes([response[4], response[5]]))
+  }
+
+  pub fn read_supported_dtcs(&mut self, address: EcuAddress) -> Result<Vec<DtcInfo>, UdsError> {
+    self.read_dtc_list(address, &[0x0A])
+  }
+
+  pub fn read_permanent_dtcs(&mut self, address: EcuAddress) -> Result<Vec<DtcInfo>, UdsError> {
+    let mut dtcs = self.read_dtc_list(address, &[0x15])?;
+    for dtc in dtcs.iter_mut() {
+      dtc.permanent = true;
+    }
+    Ok(dtcs)
+  }
+
+  pub fn read_snapshot_ids(&mut self, address: EcuAddress) -> Result<Vec<([u8; 3], u8)>, UdsError> {
+    let response = self.read_dtc_information(address, &[0x03])?;
+    Ok(
+      response[2..]
+        .chunks_exact(4)
+        .map(|record| ([record[0], record[1], record[2]], record[3]))
+        .collect(),
+    )
+  }
+
+  pub fn read_snapshots(
+    &mut self,
+    address: EcuAddress,
+    dtc: [u8; 3],
+  ) -> Result<Vec<DtcSnapshot>, UdsError> {
+    let response =
+      self.read_dtc_information(address, &[0x04, dtc[0], dtc[1], dtc[2], 0xFF])?;
+    // 59 04 <dtc> <status>, then per record: number, identifier count, (DID, data)*.
+    let mut data = response.get(6..).unwrap_or_default();
+    let mut snapshots = Vec::new();
+    while data.len() >= 2 {
+      let mut snapshot = DtcSnapshot {
+        record_number: data[0],
+        values: Vec::new(),
+        undecoded: None,
+      };
+      let count = data[1];
+      data = &data[2..];
+      for _ in 0..count {
+        let definition = match data {
+          [high, low, ..] => snapshot_did(u16::from_be_bytes([*high, *low])),
+          _ => None,
+        };
+        // Without a known length the rest of the record cannot be split reliably.
+        let Some((definition, length)) = definition
+          .and_then(|def| def.length.map(|length| (def, length)))
+          .filter(|(_, length)| data.len() >= 2 + length)
+        else {
+          snapshot.undecoded = Some(hex(data));
+          data = &[];
+          break;
+        };
+        snapshot.values.push(decode(definition, &data[2..2 + length]));
+        data = &data[2 + length..];
+      }
+      snapshots.push(snapshot);
+    }
+    Ok(snapshots)
+  }
+
+  pub fn read_extended_data(
+    &mut self,
+    address: EcuAddress,
+    dtc: [u8; 3],
+  ) -> Result<Vec<DtcExtendedData>, UdsError> {
+    let response =
+      self.read_dtc_information(address, &[0x06, dtc[0], dtc[1], dtc[2], 0xFF])?;
+    let mut data = response.get(6..).unwrap_or_default();
+    let mut records = Vec::new();
+    while let Some((&record_number, rest)) = data.split_first() {
+      let Some(definition) = extended_record(record_number).filter(|def| rest.len() >= def.length)
+      else {
+        records.push(DtcExtendedData {
+          record_number,
+          name: "Unknown record".to_string(),
+          value: String::new(),
+          raw: hex(rest),
+        });
+        break;
+      };
+      let bytes = &rest[..definition.length];
+      let value = bytes.iter().fold(0u64, |acc, byte| (acc << 8) | *byte as u64);
+      records.push(DtcExtendedData {
+        record_number,
+        name: definition.name.to_string(),
+        value: value.to_string(),
+        raw: hex(bytes),
+      });
+      data = &rest[definition.length..];
+    }
+    Ok(records)
+  }
+
+  fn read_dtc_list(
+    &mut self,
+    address: EcuAddress,
+    params: &[u8],
+  ) -> Result<Vec<DtcInfo>, UdsError> {
+    let response = self.read_dtc_information(address, params)?;
+    if response.len() < 3 {
+      return Err(UdsError::UnexpectedResponse("Unexpected DTC response".to_string()));
     }
     let mut dtcs = Vec::new();
-    let mut index = 2;
-    while index + 3 <= response.len() {
-      let bytes = [response[index], response[index + 1], response[index + 2]];
-      index += 3;
+    // Records follow the availability mask: three DTC bytes and one status byte each.
+    for record in response[3..].chunks_exact(4) {
+      let bytes = [record[0], record[1], record[2]];
       if bytes == [0x00, 0x00, 0x00] {
         continue;
       }
-      let code = decode_dtc(bytes);
+      let status_byte = record[3];
+      let status_flags = decode_status(status_byte);
       dtcs.push(DtcInfo {
-        code,
+        code: decode_dtc(bytes),
         description: "DTC description unavailable".to_string(),
-        status: "active".to_string(),
+        status: status_label(&status_flags).to_string(),
+        status_byte,
+        status_flags,
+        permanent: false,
+        snapshots: Vec::new(),
+        extended_data: Vec::new(),
       });
     }
     Ok(dtcs)
   }
 
-  pub fn clear_dtcs(&mut self, tx_id: u32, rx_id: u32) -> Result<(), String> {
-    let payload = [0x14, 0xFF, 0xFF, 0xFF];
-    let response = self.request(tx_id, rx_id, &payload)?;
-    if response.is_empty() || response[0] != 0x54 {
-      return Err("Clear DTCs failed".to_string());
+  fn read_dtc_information(
+    &mut self,
+    address: EcuAddress,
+    params: &[u8],
+  ) -> Result<Vec<u8>, UdsError> {
+    let mut payload = vec![0x19];
+    payload.extend_from_slice(params);
+    let response = self.request(address, &payload)?;
+    if response.len() < 2 || response[1] != params[0] {
+      return Err(UdsError::UnexpectedResponse(format!(
+        "Unexpected ReadDTCInformation 0x{:02X} response",
+        params[0]
+      )));
     }
+    Ok(response)
+  }
+
+  pub fn clear_dtcs(&mut self, address: EcuAddress) -> Result<(), UdsError> {
+    let payload = [0x14, 0xFF, 0xFF, 0xFF];
+    self.request(address, &payload)?;
     Ok(())
   }
 
-  fn request(&mut self, tx_id: u32, rx_id: u32, payload: &[u8]) -> Result<Vec<u8>, String> {
-    let mut last_err = None;
-    for _ in 0..=self.retries {
-      let mut link = IsoTpLink::new(&mut self.transport, tx_id, rx_id, false);
-      match link.request(payload, self.timeout_ms) {
+  fn request(&mut self, address: EcuAddress, payload: &[u8]) -> Result<Vec<u8>, UdsError> {
+    let mut attempts = 0u8;
+    let mut busy_retries = 0u8;
+    loop {
+      match self.exchange(address, payload) {
         Ok(response) => return Ok(response),
-        Err(err) => last_err = Some(err),
+        Err(UdsError::Transport(_)) if attempts < self.retries => {
+          attempts += 1;
+        }
+        Err(UdsError::Negative { service, nrc }) => match nrc.retry_delay() {
+          Some(delay) if busy_retries < BUSY_RETRIES => {
+            busy_retries += 1;
+            std::thread::sleep(delay);
+          }
+          _ => return Err(UdsError::Negative { service, nrc }),
+        },
+        Err(err) => return Err(err),
+      }
+    }
+  }
+
+  fn exchange(&mut self, address: EcuAddress, payload: &[u8]) -> Result<Vec<u8>, UdsError> {
+    let service = *payload
+      .first()
+      .ok_or_else(|| UdsError::UnexpectedResponse("Empty UDS request".to_string()))?;
+    send_payload(&mut self.transport, address, payload, &self.isotp).map_err(UdsError::Transport)?;
+
+    let mut timeout_ms = self.timing.p2_ms;
+    let mut pending = 0u32;
+    loop {
+      let response = recv_payload(&mut self.transport, address, timeout_ms, &self.isotp)
+        .map_err(UdsError::Transport)?;
+      match classify(service, response) {
+        Reply::Final(result) => return result,
+        Reply::Stale => {}
+        // The ECU accepted the request and extends its deadline to P2* per pending reply.
+        Reply::Pending => {
+          pending += 1;
+          if pending > MAX_PENDING_RESPONSES {
+            return Err(UdsError::PendingLimit(service));
+          }
+          timeout_ms = self.timing.p2_star_ms;
+        }
+      }
+    }
+  }
+
+  // One request on a functional ID; every responder within P2 (or P2* after responsePending) gets an entry.
+  pub fn functional_request(
+    &mut self,
+    request_id: u32,
+    responders: &[EcuAddress],
+    payload: &[u8],
+  ) -> Result<BTreeMap<u32, Result<Vec<u8>, UdsError>>, UdsError> {
+    let service = *payload
+      .first()
+      .ok_or_else(|| UdsError::UnexpectedResponse("Empty UDS request".to_string()))?;
+    let timing = self.timing.clone();
+    let responses = functional_request(
+      &mut self.transport,
+      request_id,
+      responders,
+      payload,
+      timing.p2_ms,
+      &self.isotp,
+      |response| match classify(service, response.to_vec()) {
+        Reply::Final(_) => None,
+        Reply::Pending => Some(timing.p2_star_ms),
+        Reply::Stale => Some(timing.p2_ms),
+      },
+    )
+    .map_err(UdsError::Transport)?;
+
+    Ok(
+      responses
+        .into_iter()
+        .map(|(rx_id, response)| {
+          let result = response.map_err(UdsError::Transport).and_then(|response| {
+            match classify(service, response) {
+              Reply::Final(result) => result,
+              Reply::Pending => Err(UdsError::Transport(format!(
+                "No final response to service 0x{service:02X} after responsePending"
+              ))),
+              Reply::Stale => Err(UdsError::Transport(format!(
+                "No response to service 0x{service:02X}"
+              ))),
+            }
+          });
+          (rx_id, result)
+        })
+        .collect(),
+    )
+  }
+
+  // Returns the first VIN in responder order; modules without one are skipped.
+  pub fn read_vin_functional(
+    &mut self,
+    request_id: u32,
+    responders: &[EcuAddress],
+  ) -> Result<String, UdsError> {
+    let [high, low] = VIN_DID.to_be_bytes();
+    let responses = self.functional_request(request_id, responders, &[0x22, high, low])?;
+    let mut last_error = None;
+    for response in responses.into_values() {
+      match response {
+        Ok(response) if response.len() > 3 && response[1..3] == [high, low] => {
+          return Ok(String::from_utf8_lossy(&response[3..]).trim().to_string())
+        }
+        Ok(_) => {
+          last_error = Some(UdsError::UnexpectedResponse(format!(
+            "Unexpected response to DID 0x{VIN_DID:04X}"
+          )))
+        }
+        Err(err) => last_error = Some(err),
       }
     }
-    Err(last_err.unwrap_or_else(|| "UDS request failed".to_string()))
+    Err(last_error.unwrap_or_else(|| {
+      UdsError::Transport("No module answered the functional VIN request".to_string())
+    }))
+  }
+
+  // True when the adapter does ISO-TP itself and `IsoTpLink` is bypassed.
+  pub fn native_isotp(&self) -> bool {
+    self.transport.supports_payloads()
+  }
+
+  pub fn battery_voltage(&mut self) -> Option<f32> {
+    self.transport.battery_voltage().ok()
+  }
+
+  pub fn adapter_versions(&mut self) -> Option<AdapterVersions> {
+    self.transport.adapter_versions().ok()
   }
 
   pub fn into_transport(self) -> T {
@@ -95,14 +689,259 @@ impl<T: Transport> UdsClient<T> {
   }
 }
 
-fn decode_dtc(bytes: [u8; 3]) -> String {
-  let raw = ((bytes[0] as u32) << 16) | ((bytes[1] as u32) << 8) | bytes[2] as u32;
-  let letter = match (raw >> 22) & 0x3 {
-    0 => 'P',
-    1 => 'C',
-    2 => 'B',
-    _ => 'U',
-  };
-  let code_value = raw & 0x3F_FFFF;
-  format!("{}{:06X}", letter, code_value)
+enum Reply {
+  Final(Result<Vec<u8>, UdsError>),
+  Pending,
+  // Late NRCs to earlier requests, such as a suppressed keep-alive, are not ours.
+  Stale,
+}
+
+fn classify(service: u8, response: Vec<u8>) -> Reply {
+  match response.first() {
+    Some(&NEGATIVE_RESPONSE) if response.len() >= 3 && response[1] != service => Reply::Stale,
+    Some(&NEGATIVE_RESPONSE) if response.len() >= 3 => match Nrc::from_byte(response[2]) {
+      Nrc::ResponsePending => Reply::Pending,
+      nrc => Reply::Final(Err(UdsError::Negative { service, nrc })),
+    },
+    Some(&sid) if sid == service.wrapping_add(POSITIVE_OFFSET) => Reply::Final(Ok(response)),
+    _ => Reply::Final(Err(UdsError::UnexpectedResponse(format!(
+      "Unexpected response to service 0x{service:02X}: {:02X?}",
+      response
+    )))),
+  }
+}
+
+// requestSeed levels are odd and at most 0x7D; sendKey uses the level above.
+fn key_level(seed_level: u8) -> Result<u8, UdsError> {
+  if seed_level & 1 == 0 || seed_level > 0x7D {
+    return Err(UdsError::Security(format!(
+      "Security level 0x{seed_level:02X} is not a requestSeed level"
+    )));
+  }
+  Ok(seed_level + 1)
+}
+
+// Lockout NRCs back off with a growing delay before the next attempt; a rejected key is final so the
+// ECU attempt counter is not burned. `wait` is handed each delay so the caller can release the bus meanwhile.
+pub fn unlock(
+  level: u8,
+  algorithm: &dyn SeedKeyAlgorithm,
+  mut attempt: impl FnMut() -> (&'static str, Result<bool, UdsError>),
+  mut wait: impl FnMut(u64),
+) -> (Result<(), UdsError>, Vec<SecurityAttempt>) {
+  let mut attempts = Vec::new();
+  if let Err(err) = key_level(level) {
+    return (Err(err), attempts);
+  }
+  let mut delay_ms = SECURITY_DELAY_MS;
+  loop {
+    let (stage, result) = attempt();
+    let lockout = matches!(
+      result.as_ref().err().and_then(UdsError::nrc),
+      Some(Nrc::RequiredTimeDelayNotExpired | Nrc::ExceededNumberOfAttempts)
+    );
+    let backoff = if lockout && attempts.len() + 1 < MAX_SECURITY_ATTEMPTS {
+      delay_ms
+    } else {
+      0
+    };
+    attempts.push(SecurityAttempt {
+      level,
+      algorithm: algorithm.name().to_string(),
+      stage: stage.to_string(),
+      outcome: match &result {
+        Ok(true) => "alreadyUnlocked".to_string(),
+        Ok(false) => "unlocked".to_string(),
+        Err(err) => err.to_string(),
+      },
+      delay_ms: backoff,
+    });
+    if backoff == 0 {
+      return (result.map(|_| ()), attempts);
+    }
+    wait(backoff);
+    delay_ms = (delay_ms * 2).min(MAX_SECURITY_DELAY_MS);
+  }
+}
+
+// Sends 3E 80 without waiting: the server stays silent on success, and a stray NRC is skipped by the next exchange.
+pub fn send_keep_alive<T: Transport>(transport: &mut T, address: EcuAddress) -> Result<(), String> {
+  send_payload(transport, address, &[0x3E, 0x80], &IsoTpConfig::default())
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::Instant;
+
+  use super::*;
+  use crate::protocol::security::XorSeedKey;
+  use crate::simulation::SimulationSession;
+  use crate::transport::{Frame, SimTransport};
+
+  fn pcm() -> EcuAddress {
+    EcuAddress::normal(0x7E0, 0x7E8)
+  }
+
+  // The replies are queued up front; SimTransport hands them out in order whatever was sent.
+  fn scripted(replies: &[&[u8]]) -> UdsClient<SimTransport> {
+    let mut transport = SimTransport::new();
+    for reply in replies {
+      let mut data = vec![0xAA; 8];
+      data[0] = reply.len() as u8;
+      data[1..1 + reply.len()].copy_from_slice(reply);
+      transport.push_frame(Frame {
+        id: 0x7E8,
+        data,
+        timestamp_ms: 0,
+        is_extended: false,
+      });
+    }
+    let mut uds = UdsClient::new(transport, 20, 0);
+    uds.open().unwrap();
+    uds
+  }
+
+  fn virtual_pcm(pending_responses: u8) -> UdsClient<SimTransport> {
+    let session: SimulationSession = serde_json::from_value(serde_json::json!({
+      "vin": "1FT7W2BT6KED12345",
+      "vehicle": { "make": "Ford", "model": "F-250", "year": "2019" },
+      "modules": [{
+        "id": "pcm",
+        "name": "PCM",
+        "bus": "HS-CAN",
+        "category": "Powertrain",
+        "txId": 0x7E0,
+        "rxId": 0x7E8,
+        "dtcs": [],
+        "pendingResponses": pending_responses
+      }]
+    }))
+    .unwrap();
+    let mut uds = UdsClient::new(SimTransport::from_session(&session), 20, 0);
+    uds.open().unwrap();
+    uds
+  }
+
+  #[test]
+  fn skips_negative_responses_to_other_services() {
+    let mut uds = scripted(&[&[0x7F, 0x3E, 0x12], &[0x62, 0xF1, 0x88, 0x41, 0x42]]);
+    assert_eq!(uds.read_did(pcm(), 0xF188).unwrap(), b"AB");
+  }
+
+  #[test]
+  fn returns_named_negative_responses() {
+    let mut uds = scripted(&[&[0x7F, 0x22, 0x31]]);
+    let err = uds.read_did(pcm(), 0xF188).unwrap_err();
+    assert_eq!(err.nrc(), Some(Nrc::RequestOutOfRange));
+  }
+
+  #[test]
+  fn waits_out_response_pending() {
+    assert!(virtual_pcm(5).clear_dtcs(pcm()).is_ok());
+  }
+
+  #[test]
+  fn waits_p2_star_after_response_pending() {
+    let mut uds = scripted(&[&[0x7F, 0x22, 0x78]]);
+    uds
+      .use_timing(Some(TimingConfig {
+        p2_ms: 20,
+        p2_star_ms: 300,
+      }))
+      .unwrap();
+    let started = Instant::now();
+    assert!(matches!(
+      uds.read_did(pcm(), 0xF188),
+      Err(UdsError::Transport(_))
+    ));
+    assert!(started.elapsed() >= Duration::from_millis(300));
+  }
+
+  #[test]
+  fn gives_up_after_too_many_pending_responses() {
+    let result = virtual_pcm(MAX_PENDING_RESPONSES as u8 + 1).clear_dtcs(pcm());
+    assert!(matches!(result, Err(UdsError::PendingLimit(0x14))));
+  }
+
+  #[test]
+  fn retries_busy_repeat_request() {
+    let busy: &[u8] = &[0x7F, 0x22, 0x21];
+    let positive: &[u8] = &[0x62, 0xF1, 0x88, 0x41];
+    let mut replies = vec![busy; BUSY_RETRIES as usize];
+    replies.push(positive);
+    assert_eq!(scripted(&replies).read_did(pcm(), 0xF188).unwrap(), b"A");
+
+    let mut replies = vec![busy; BUSY_RETRIES as usize + 1];
+    replies.push(positive);
+    let err = scripted(&replies).read_did(pcm(), 0xF188).unwrap_err();
+    assert_eq!(err.nrc(), Some(Nrc::BusyRepeatRequest));
+  }
+
+  #[test]
+  fn send_key_rejects_levels_without_a_key_level() {
+    let mut uds = UdsClient::new(SimTransport::new(), 50, 0);
+    uds.open().unwrap();
+    for level in [0x00, 0x02, 0x7F, 0xFF] {
+      let result = uds.send_key(EcuAddress::normal(0x7E0, 0x7E8), level, &[0x12, 0x34]);
+      assert!(
+        matches!(result, Err(UdsError::Security(_))),
+        "level 0x{level:02X}"
+      );
+    }
+  }
+
+  fn unlock_scripted(replies: &[&[u8]]) -> (Result<(), UdsError>, Vec<SecurityAttempt>, Vec<u64>) {
+    let mut uds = scripted(replies);
+    let algorithm = XorSeedKey {
+      constant: vec![0x55],
+    };
+    let mut delays = Vec::new();
+    let (result, attempts) = unlock(
+      0x01,
+      &algorithm,
+      || uds.security_attempt(pcm(), 0x01, &algorithm),
+      |delay_ms| delays.push(delay_ms),
+    );
+    (result, attempts, delays)
+  }
+
+  #[test]
+  fn unlocks_after_the_lockout_delay() {
+    let (result, attempts, delays) = unlock_scripted(&[
+      &[0x7F, 0x27, 0x37],
+      &[0x67, 0x01, 0x12, 0x34],
+      &[0x67, 0x02],
+    ]);
+    result.unwrap();
+    assert_eq!(delays, vec![SECURITY_DELAY_MS]);
+    assert_eq!(attempts.len(), 2);
+    assert_eq!(attempts[1].outcome, "unlocked");
+  }
+
+  #[test]
+  fn does_not_retry_a_rejected_key() {
+    let (result, attempts, delays) = unlock_scripted(&[
+      &[0x67, 0x01, 0x12, 0x34],
+      &[0x7F, 0x27, 0x35],
+      &[0x67, 0x01, 0x12, 0x34],
+      &[0x67, 0x02],
+    ]);
+    assert_eq!(result.unwrap_err().nrc(), Some(Nrc::InvalidKey));
+    assert!(delays.is_empty());
+    assert_eq!(attempts.len(), 1);
+    assert_eq!(attempts[0].stage, "key");
+  }
+
+  #[test]
+  fn does_not_wait_after_the_last_attempt() {
+    let lockout: &[u8] = &[0x7F, 0x27, 0x36];
+    let (result, attempts, delays) = unlock_scripted(&[lockout; MAX_SECURITY_ATTEMPTS]);
+    assert_eq!(
+      result.unwrap_err().nrc(),
+      Some(Nrc::ExceededNumberOfAttempts)
+    );
+    assert_eq!(delays, vec![SECURITY_DELAY_MS, SECURITY_DELAY_MS * 2]);
+    assert_eq!(attempts.len(), MAX_SECURITY_ATTEMPTS);
+    assert_eq!(attempts[MAX_SECURITY_ATTEMPTS - 1].delay_ms, 0);
+  }
 }
@@ -1,20 +1,39 @@
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 
 use parking_lot::Mutex;
-use tauri::AppHandle;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Runtime};
 
-use crate::app_state::{AppSnapshot, AppState, SessionSummary};
+use crate::app_state::{
+  AppSnapshot, AppState, DiagnosticSession, ModuleInfo, SessionSummary, VehicleInfo,
+};
+use crate::dtc_db::DtcDatabase;
 use crate::logger::{LogEvent, Logger};
-use crate::transport::Transport;
+use crate::protocol::uds::send_keep_alive;
+use crate::security::SecurityRegistry;
+use crate::transport::{TimingConfig, Transport};
+use crate::vin::decode_vin;
 
 pub struct AppRuntime {
   pub state: Mutex<AppState>,
   pub logger: Mutex<Option<Logger>>,
   pub transport: Mutex<Option<Box<dyn Transport>>>,
   pub simulation: Mutex<Option<crate::simulation::SimulationSession>>,
+  pub dtc_db: Mutex<DtcDatabase>,
+  pub security: Mutex<SecurityRegistry>,
+  // P2/P2* each module negotiated when it left the default session, keyed by module id.
+  pub timings: Mutex<HashMap<String, TimingConfig>>,
+  keep_alive_running: AtomicBool,
 }
 
+// Well inside the 5 s S3 server timeout.
+const KEEP_ALIVE_INTERVAL_MS: u64 = 2000;
+
 impl AppRuntime {
   pub fn new(last_session: Option<SessionSummary>) -> Self {
     let mut state = AppState::default();
@@ -24,6 +43,10 @@ impl AppRuntime {
       logger: Mutex::new(None),
       transport: Mutex::new(None),
       simulation: Mutex::new(None),
+      dtc_db: Mutex::new(DtcDatabase::builtin()),
+      security: Mutex::new(SecurityRegistry::default()),
+      timings: Mutex::new(HashMap::new()),
+      keep_alive_running: AtomicBool::new(false),
     }
   }
 
@@ -31,7 +54,7 @@ impl AppRuntime {
     self.state.lock().snapshot()
   }
 
-  pub fn update_state<F>(&self, app: &AppHandle, update: F) -> AppSnapshot
+  pub fn update_state<R: Runtime, F>(&self, app: &AppHandle<R>, update: F) -> AppSnapshot
   where
     F: FnOnce(&mut AppState),
   {
@@ -54,9 +77,74 @@ impl AppRuntime {
       logger.log(event);
     }
   }
+
+  // The simulated vehicle in simulation mode, otherwise whatever the VIN decodes to.
+  pub fn vehicle(&self, vin: &str) -> Option<VehicleInfo> {
+    match self.simulation.lock().as_ref() {
+      Some(session) => Some(session.vehicle.clone()),
+      None => decode_vin(vin).map(|info| info.vehicle()),
+    }
+  }
+
+  pub fn vehicle_make(&self, vin: &str) -> Option<String> {
+    match self.simulation.lock().as_ref() {
+      Some(session) => Some(session.vehicle.make.clone()),
+      None => decode_vin(vin).and_then(|info| info.make),
+    }
+  }
+
+  pub fn module_timing(&self, module_id: &str) -> Option<TimingConfig> {
+    self.timings.lock().get(module_id).cloned()
+  }
+
+  pub fn set_module_timing(
+    &self,
+    module_id: &str,
+    session: DiagnosticSession,
+    timing: TimingConfig,
+  ) {
+    let mut timings = self.timings.lock();
+    if session == DiagnosticSession::Default {
+      timings.remove(module_id);
+    } else {
+      timings.insert(module_id.to_string(), timing);
+    }
+  }
+
+  // Runs until no module is left outside the default session or the transport goes away.
+  pub fn ensure_keep_alive(self: &Arc<Self>) {
+    if self.keep_alive_running.swap(true, Ordering::SeqCst) {
+      return;
+    }
+    let runtime = self.clone();
+    std::thread::spawn(move || {
+      loop {
+        std::thread::sleep(Duration::from_millis(KEEP_ALIVE_INTERVAL_MS));
+        let targets = runtime
+          .state
+          .lock()
+          .modules
+          .iter()
+          .filter(|module| module.session != DiagnosticSession::Default)
+          .map(ModuleInfo::address)
+          .collect::<Vec<_>>();
+        if targets.is_empty() {
+          break;
+        }
+        let mut guard = runtime.transport.lock();
+        let Some(transport) = guard.as_mut() else {
+          break;
+        };
+        for address in targets {
+          let _ = send_keep_alive(transport, address);
+        }
+      }
+      runtime.keep_alive_running.store(false, Ordering::SeqCst);
+    });
+  }
 }
 
-pub fn last_session_path(app: &AppHandle) -> Option<PathBuf> {
+pub fn last_session_path<R: Runtime>(app: &AppHandle<R>) -> Option<PathBuf> {
   let base = tauri::api::path::app_data_dir(&app.config())?;
   Some(base.join("last_session.json"))
 }
@@ -67,7 +155,10 @@ pub fn load_last_session(app: &AppHandle) -> Option<SessionSummary> {
   serde_json::from_str(&contents).ok()
 }
 
-pub fn save_last_session(app: &AppHandle, summary: &SessionSummary) -> Result<(), String> {
+pub fn save_last_session<R: Runtime>(
+  app: &AppHandle<R>,
+  summary: &SessionSummary,
+) -> Result<(), String> {
   let path = last_session_path(app).ok_or_else(|| "Missing app data dir".to_string())?;
   if let Some(parent) = path.parent() {
     fs::create_dir_all(parent).map_err(|err| format!("Failed to create app data dir: {err}"))?;
@@ -75,3 +166,30 @@ pub fn save_last_session(app: &AppHandle, summary: &SessionSummary) -> Result<()
   let contents = serde_json::to_string_pretty(summary).map_err(|err| err.to_string())?;
   fs::write(path, contents).map_err(|err| format!("Failed to save last session: {err}"))
 }
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DeviceSelection {
+  library: Option<String>,
+}
+
+fn device_selection_path(app: &AppHandle) -> Option<PathBuf> {
+  let base = tauri::api::path::app_data_dir(&app.config())?;
+  Some(base.join("j2534_device.json"))
+}
+
+// The J2534 driver the user picked; None leaves the choice to `find_j2534_dll`.
+pub fn load_device_selection(app: &AppHandle) -> Option<String> {
+  let contents = fs::read_to_string(device_selection_path(app)?).ok()?;
+  serde_json::from_str::<DeviceSelection>(&contents).ok()?.library
+}
+
+pub fn save_device_selection(app: &AppHandle, library: Option<String>) -> Result<(), String> {
+  let path = device_selection_path(app).ok_or_else(|| "Missing app data dir".to_string())?;
+  if let Some(parent) = path.parent() {
+    fs::create_dir_all(parent).map_err(|err| format!("Failed to create app data dir: {err}"))?;
+  }
+  let contents =
+    serde_json::to_string_pretty(&DeviceSelection { library }).map_err(|err| err.to_string())?;
+  fs::write(path, contents).map_err(|err| format!("Failed to save device selection: {err}"))
+}
@@ -1,32 +1,59 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
 use std::sync::Arc;
-use std::time::Duration;
 
 use chrono::Utc;
 use serde_json::json;
-use tauri::AppHandle;
+use tauri::{AppHandle, Runtime};
 
 use crate::app_state::{
-  AppPhase, ErrorInfo, ModuleStatus, ProgressInfo, SessionSummary, TransportMode,
+  AppPhase, BaselineStatus, DtcInfo, ErrorInfo, ModuleInfo, ModuleStatus, ProgressInfo,
+  SessionSummary, TransportMode, VinCheck, VinCheckStatus,
 };
-use crate::discovery::{decode_vin, discover_modules, ModuleCandidate};
+use crate::baseline::{compare, Baseline, BaselineStore};
+use crate::discovery::{
+  discover_modules, gateway_inventory, merge_inventory, DiscoveryConfig, ModuleCandidate,
+};
+use crate::dtc_db::DtcDatabase;
 use crate::logger::{LogEvent, LogKind, Logger};
-use crate::protocol::uds::UdsClient;
+use crate::profiles::{load_selection, ProfileStore};
+use crate::protocol::addressing::{
+  normal_fixed_targets, obd_responders, EcuAddress, NORMAL_FIXED_FUNCTIONAL_ID, OBD_FUNCTIONAL_ID,
+};
+use crate::protocol::did::VIN_DID;
+use crate::protocol::dtc::encode_dtc;
+use crate::protocol::isotp::IsoTpConfig;
+use crate::protocol::uds::{UdsClient, UdsError};
 use crate::runtime::{save_last_session, AppRuntime};
+use crate::security::SecurityRegistry;
 use crate::simulation::SimulationSession;
-use crate::topology::build_topology;
-use crate::transport::{Transport, VLinkerFsJ2534Transport};
+use crate::topology::TopologyHints;
+use crate::transport::{
+  SimTransport, SocketCanTransport, Transport, VLinkerDirectTransport, VLinkerFsJ2534Transport,
+};
+use crate::vin::{decode_vin, CheckDigit};
+
+const LIVE_TIMEOUT_MS: u64 = 500;
+const SIMULATION_TIMEOUT_MS: u64 = 50;
+const MAX_DTC_DETAILS: usize = 16;
 
 struct LoggingTransport<T: Transport> {
   inner: T,
   runtime: Arc<AppRuntime>,
+  // Arbitration IDs whose consecutive frames continue a SecurityAccess sendKey.
+  redacting: HashSet<u32>,
 }
 
 impl<T: Transport> LoggingTransport<T> {
   fn new(inner: T, runtime: Arc<AppRuntime>) -> Self {
-    Self { inner, runtime }
+    Self {
+      inner,
+      runtime,
+      redacting: HashSet::new(),
+    }
   }
 
-  fn log_frame(&self, direction: &str, frame: &crate::transport::Frame) {
+  fn log_frame(&self, direction: &str, frame: &crate::transport::Frame, data: &[u8]) {
     self.runtime.log_event(LogEvent {
       timestamp: Utc::now(),
       level: "debug".to_string(),
@@ -34,11 +61,62 @@ impl<T: Transport> LoggingTransport<T> {
       message: format!("{direction} CAN frame"),
       payload: json!({
         "id": format!("0x{:03X}", frame.id),
-        "data": frame.data,
+        "data": data,
         "extended": frame.is_extended
       }),
     });
   }
+
+  fn log_payload(&self, direction: &str, id: u32, data: &[u8]) {
+    self.runtime.log_event(LogEvent {
+      timestamp: Utc::now(),
+      level: "debug".to_string(),
+      kind: LogKind::Transport,
+      message: format!("{direction} ISO-TP payload"),
+      payload: json!({
+        "id": format!("0x{id:03X}"),
+        "data": data
+      }),
+    });
+  }
+
+  // Key bytes of 0x27 sendKey requests never reach the log, including their ISO-TP continuation.
+  fn redact_key(&mut self, frame: &crate::transport::Frame) -> Vec<u8> {
+    let mut data = frame.data.clone();
+    // Extended addressing puts the target address ahead of the PCI byte.
+    let pci_index = self
+      .runtime
+      .state
+      .lock()
+      .modules
+      .iter()
+      .find(|module| module.tx_id == frame.id)
+      .and_then(|module| module.address().tx_prefix())
+      .map_or(0, |_| 1);
+    let payload_start = match data.get(pci_index).map(|pci| pci >> 4) {
+      Some(0) => pci_index + 1,
+      Some(1) => pci_index + 2,
+      Some(2) => {
+        if self.redacting.contains(&frame.id) {
+          data[pci_index + 1..].fill(0);
+        }
+        return data;
+      }
+      _ => return data,
+    };
+    self.redacting.remove(&frame.id);
+    let is_send_key = data.get(payload_start) == Some(&0x27)
+      && data
+        .get(payload_start + 1)
+        .is_some_and(|sub| *sub != 0 && sub & 1 == 0);
+    if is_send_key {
+      data[payload_start + 2..].fill(0);
+      if payload_start == pci_index + 2 {
+        self.redacting.insert(frame.id);
+      }
+    }
+    data
+  }
 }
 
 impl<T: Transport> Transport for LoggingTransport<T> {
@@ -51,14 +129,15 @@ impl<T: Transport> Transport for LoggingTransport<T> {
   }
 
   fn send(&mut self, frame: &crate::transport::Frame) -> Result<(), String> {
-    self.log_frame("TX", frame);
+    let data = self.redact_key(frame);
+    self.log_frame("TX", frame, &data);
     self.inner.send(frame)
   }
 
   fn recv(&mut self, timeout_ms: u64) -> Result<Option<crate::transport::Frame>, String> {
     let frame = self.inner.recv(timeout_ms)?;
     if let Some(ref frame) = frame {
-      self.log_frame("RX", frame);
+      self.log_frame("RX", frame, &frame.data);
     }
     Ok(frame)
   }
@@ -78,6 +157,43 @@ impl<T: Transport> Transport for LoggingTransport<T> {
   fn set_timing(&mut self, timing: crate::transport::TimingConfig) -> Result<(), String> {
     self.inner.set_timing(timing)
   }
+
+  fn supports_payloads(&self) -> bool {
+    self.inner.supports_payloads()
+  }
+
+  fn send_payload(&mut self, address: EcuAddress, payload: &[u8]) -> Result<(), String> {
+    let mut data = payload.to_vec();
+    if data.len() > 2 && data[0] == 0x27 && data[1] != 0 && data[1] & 1 == 0 {
+      data[2..].fill(0);
+    }
+    self.log_payload("TX", address.tx_id, &data);
+    self.inner.send_payload(address, payload)
+  }
+
+  fn recv_payload(&mut self, address: EcuAddress, timeout_ms: u64) -> Result<Vec<u8>, String> {
+    let payload = self.inner.recv_payload(address, timeout_ms)?;
+    self.log_payload("RX", address.rx_id, &payload);
+    Ok(payload)
+  }
+
+  fn battery_voltage(&mut self) -> Result<f32, String> {
+    self.inner.battery_voltage()
+  }
+
+  fn adapter_versions(&mut self) -> Result<crate::transport::AdapterVersions, String> {
+    self.inner.adapter_versions()
+  }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AdapterOptions {
+  // Serial port or CAN interface for the direct and SocketCAN transports.
+  pub device: Option<String>,
+  // J2534 only: the driver the user picked; None looks one up.
+  pub dll_path: Option<PathBuf>,
+  // J2534 only: let the adapter do ISO-TP on an ISO15765 channel.
+  pub native_isotp: bool,
 }
 
 pub async fn run_scan(
@@ -85,21 +201,28 @@ pub async fn run_scan(
   runtime: Arc<AppRuntime>,
   mode: TransportMode,
   simulation_path: Option<String>,
+  adapter: AdapterOptions,
   extra_candidates: Vec<ModuleCandidate>,
+  discovery: DiscoveryConfig,
 ) -> Result<(), String> {
   let session_id = uuid::Uuid::new_v4().to_string();
   let logger = Logger::new(&app, &session_id)?;
   let logs_path = logger.path_str();
   runtime.set_logger(Some(logger));
+  runtime.timings.lock().clear();
 
   runtime.update_state(&app, |state| {
     state.phase = AppPhase::Connecting;
     state.transport = mode.clone();
     state.adapter_connected = mode == TransportMode::Simulation;
     state.vin = None;
+    state.vehicle = None;
+    state.battery_voltage = None;
+    state.baseline = None;
     state.modules.clear();
     state.dtcs.clear();
-    state.topology = crate::app_state::TopologyGraph { buses: Vec::new() };
+    state.topology = crate::app_state::TopologyGraph::default();
+    state.topology_hints = TopologyHints::default();
     state.progress = Some(ProgressInfo {
       stage: "connecting".to_string(),
       percent: 5,
@@ -115,127 +238,101 @@ pub async fn run_scan(
     level: "info".to_string(),
     kind: LogKind::System,
     message: "Session started".to_string(),
-    payload: json!({ "sessionId": session_id, "mode": format!("{mode:?}") }),
+    payload: json!({ "sessionId": session_id, "mode": format!("{mode:?}"), "device": adapter.device }),
   });
 
-  match mode {
-    TransportMode::Simulation => run_simulation(&app, runtime, simulation_path).await,
-    TransportMode::J2534 => run_real_scan(&app, runtime, extra_candidates).await,
-  }
-}
-
-async fn run_simulation(
-  app: &AppHandle,
-  runtime: Arc<AppRuntime>,
-  simulation_path: Option<String>,
-) -> Result<(), String> {
-  let path = simulation_path.unwrap_or_else(|| "samples/f250_session.json".to_string());
-  let session = resolve_simulation(&path).ok_or_else(|| {
-    format!("Simulation file not found: {path}. Provide a valid path or keep /samples in the repo.")
-  })?;
-  *runtime.simulation.lock() = Some(session.clone());
-
-  runtime.update_state(app, |state| {
-    state.adapter_connected = true;
-    state.phase = AppPhase::Identifying;
-    state.progress = Some(ProgressInfo {
-      stage: "identifying".to_string(),
-      percent: 15,
-      message: "Reading VIN".to_string(),
-    });
-  });
-  runtime.log_event(LogEvent {
-    timestamp: Utc::now(),
-    level: "info".to_string(),
-    kind: LogKind::Protocol,
-    message: "VIN read (simulation)".to_string(),
-    payload: json!({ "vin": session.vin }),
-  });
-  if let Some(info) = decode_vin(&session.vin) {
+  let (dtc_db, user_entries, dtc_db_errors) = DtcDatabase::load(&app);
+  *runtime.dtc_db.lock() = dtc_db;
+  if user_entries > 0 {
     runtime.log_event(LogEvent {
       timestamp: Utc::now(),
       level: "info".to_string(),
-      kind: LogKind::Protocol,
-      message: "VIN decode (simulation)".to_string(),
-      payload: json!({ "wmi": info.wmi, "year": info.year }),
+      kind: LogKind::System,
+      message: "User DTC database loaded".to_string(),
+      payload: json!({ "entries": user_entries }),
     });
   }
-
-  tauri::async_runtime::sleep(Duration::from_millis(350)).await;
-
-  runtime.update_state(app, |state| {
-    state.vin = Some(session.vin.clone());
-    state.phase = AppPhase::Discovering;
-    state.progress = Some(ProgressInfo {
-      stage: "discovering".to_string(),
-      percent: 30,
-      message: "Discovering modules".to_string(),
+  for err in dtc_db_errors {
+    runtime.log_event(LogEvent {
+      timestamp: Utc::now(),
+      level: "warn".to_string(),
+      kind: LogKind::System,
+      message: "DTC database ignored".to_string(),
+      payload: json!({ "error": err }),
     });
-  });
+  }
 
-  let mut modules = Vec::new();
-  for (index, module) in session.modules.iter().enumerate() {
-    modules.push(crate::app_state::ModuleInfo {
-      id: module.id.clone(),
-      name: module.name.clone(),
-      bus: module.bus.clone(),
-      category: module.category.clone(),
-      tx_id: module.tx_id,
-      rx_id: module.rx_id,
-      status: ModuleStatus::Ok,
-      dtc_count: module.dtcs.len(),
-    });
-    let percent = 30 + ((index + 1) * 30 / session.modules.len().max(1));
-    runtime.update_state(app, |state| {
-      state.modules = modules.clone();
-      state.topology = build_topology(&state.modules);
-      state.progress = Some(ProgressInfo {
-        stage: "discovering".to_string(),
-        percent: percent as u8,
-        message: format!("Discovered {}", module.name),
-      });
-    });
+  let (security, security_errors) = SecurityRegistry::load(&app);
+  *runtime.security.lock() = security;
+  for err in security_errors {
     runtime.log_event(LogEvent {
       timestamp: Utc::now(),
-      level: "info".to_string(),
-      kind: LogKind::Protocol,
-      message: "Module discovered".to_string(),
-      payload: json!({ "module": module.id, "name": module.name }),
+      level: "warn".to_string(),
+      kind: LogKind::System,
+      message: "Seed-key algorithm ignored".to_string(),
+      payload: json!({ "error": err }),
     });
-    tauri::async_runtime::sleep(Duration::from_millis(180)).await;
   }
 
-  runtime.update_state(app, |state| {
-    state.phase = AppPhase::ScanningDtc;
-    state.progress = Some(ProgressInfo {
-      stage: "scanning".to_string(),
-      percent: 70,
-      message: "Reading DTCs".to_string(),
-    });
-  });
+  let mut candidates = extra_candidates;
+  let transport = match mode {
+    TransportMode::Simulation => {
+      let mut session = load_simulation(simulation_path)?;
+      session.fill_descriptions(&runtime.dtc_db.lock());
+      candidates.extend(session.module_candidates());
+      let transport = SimTransport::from_session(&session);
+      *runtime.simulation.lock() = Some(session);
+      Box::new(transport) as Box<dyn Transport>
+    }
+    _ => {
+      *runtime.simulation.lock() = None;
+      create_transport(&mode, adapter)
+    }
+  };
+  run_real_scan(&app, runtime, &mode, transport, candidates, discovery).await
+}
 
-  let mut dtcs_map = std::collections::HashMap::new();
-  for (index, module) in session.modules.iter().enumerate() {
-    dtcs_map.insert(module.id.clone(), module.dtcs.clone());
-    let percent = 70 + ((index + 1) * 25 / session.modules.len().max(1));
-    runtime.update_state(app, |state| {
-      state.dtcs = dtcs_map.clone();
-      for info in state.modules.iter_mut() {
-        if let Some(dtcs) = state.dtcs.get(&info.id) {
-          info.dtc_count = dtcs.len();
-        }
-      }
-      state.progress = Some(ProgressInfo {
-        stage: "scanning".to_string(),
-        percent: percent as u8,
-        message: format!("Scanned {}", module.name),
-      });
-    });
-    tauri::async_runtime::sleep(Duration::from_millis(160)).await;
+// Every UDS client goes through here so commands run with the same timing and ISO-TP pacing as the scan.
+pub fn uds_client<T: Transport>(mode: &TransportMode, transport: T) -> UdsClient<T> {
+  let (timeout_ms, retries) = match mode {
+    TransportMode::Simulation => (SIMULATION_TIMEOUT_MS, 0),
+    _ => (LIVE_TIMEOUT_MS, 1),
+  };
+  UdsClient::new(transport, timeout_ms, retries).with_isotp_config(isotp_config(mode))
+}
+
+fn isotp_config(mode: &TransportMode) -> IsoTpConfig {
+  match mode {
+    // ELM-style adapters overflow their receive buffer on long unpaced bursts.
+    TransportMode::Direct => IsoTpConfig {
+      block_size: 8,
+      st_min: 2,
+      ..IsoTpConfig::default()
+    },
+    _ => IsoTpConfig::default(),
   }
+}
 
-  finish_session(app, runtime).await;
-  Ok(())
+fn create_transport(mode: &TransportMode, adapter: AdapterOptions) -> Box<dyn Transport> {
+  match mode {
+    TransportMode::Direct => Box::new(VLinkerDirectTransport::new(adapter.device)),
+    TransportMode::SocketCan => Box::new(SocketCanTransport::new(adapter.device)),
+    _ => {
+      let isotp = isotp_config(mode);
+      Box::new(
+        VLinkerFsJ2534Transport::new(adapter.dll_path)
+          .with_iso15765(adapter.native_isotp)
+          .with_iso15765_flow(isotp.block_size, isotp.st_min),
+      )
+    }
+  }
+}
+
+fn load_simulation(simulation_path: Option<String>) -> Result<SimulationSession, String> {
+  let path = simulation_path.unwrap_or_else(|| "samples/f250_session.json".to_string());
+  resolve_simulation(&path).ok_or_else(|| {
+    format!("Simulation file not found: {path}. Provide a valid path or keep /samples in the repo.")
+  })
 }
 
 fn resolve_simulation(path: &str) -> Option<SimulationSession> {
@@ -256,14 +353,16 @@ fn resolve_simulation(path: &str) -> Option<SimulationSession> {
   None
 }
 
-async fn run_real_scan(
-  app: &AppHandle,
+async fn run_real_scan<R: Runtime>(
+  app: &AppHandle<R>,
   runtime: Arc<AppRuntime>,
+  mode: &TransportMode,
+  transport: Box<dyn Transport>,
   extra_candidates: Vec<ModuleCandidate>,
+  discovery: DiscoveryConfig,
 ) -> Result<(), String> {
-  let transport = VLinkerFsJ2534Transport::new(None);
   let transport = LoggingTransport::new(transport, runtime.clone());
-  let mut uds = UdsClient::new(transport, 500, 1);
+  let mut uds = uds_client(mode, transport);
 
   uds.open().map_err(|err| {
     runtime.update_state(app, |state| {
@@ -277,8 +376,38 @@ async fn run_real_scan(
     err
   })?;
 
+  runtime.log_event(LogEvent {
+    timestamp: Utc::now(),
+    level: "info".to_string(),
+    kind: LogKind::System,
+    message: "ISO-TP mode".to_string(),
+    payload: json!({ "native": uds.native_isotp() }),
+  });
+
+  if let Some(versions) = uds.adapter_versions() {
+    runtime.log_event(LogEvent {
+      timestamp: Utc::now(),
+      level: "info".to_string(),
+      kind: LogKind::System,
+      message: "Adapter versions".to_string(),
+      payload: json!(versions),
+    });
+  }
+
+  let battery_voltage = uds.battery_voltage();
+  if let Some(volts) = battery_voltage {
+    runtime.log_event(LogEvent {
+      timestamp: Utc::now(),
+      level: "info".to_string(),
+      kind: LogKind::System,
+      message: "Battery voltage".to_string(),
+      payload: json!({ "volts": volts }),
+    });
+  }
+
   runtime.update_state(app, |state| {
     state.adapter_connected = true;
+    state.battery_voltage = battery_voltage;
     state.phase = AppPhase::Identifying;
     state.progress = Some(ProgressInfo {
       stage: "identifying".to_string(),
@@ -288,18 +417,19 @@ async fn run_real_scan(
   });
 
   let vin = uds
-    .read_vin(0x7E0, 0x7E8)
-    .or_else(|_| uds.read_vin(0x7DF, 0x7E8))
+    .read_vin(EcuAddress::normal(0x7E0, 0x7E8))
+    .or_else(|_| uds.read_vin_functional(OBD_FUNCTIONAL_ID, &obd_responders()))
+    .or_else(|_| uds.read_vin_functional(NORMAL_FIXED_FUNCTIONAL_ID, &normal_fixed_targets()))
     .map_err(|err| {
       runtime.update_state(app, |state| {
         state.phase = AppPhase::Error;
         state.last_error = Some(ErrorInfo {
           summary: "VIN read failed".to_string(),
-          details: err.clone(),
+          details: err.to_string(),
         });
         state.progress = None;
       });
-      err
+      err.to_string()
     })?;
 
   runtime.log_event(LogEvent {
@@ -309,18 +439,85 @@ async fn run_real_scan(
     message: "VIN read".to_string(),
     payload: json!({ "vin": vin }),
   });
-  if let Some(info) = decode_vin(&vin) {
+  let vin_info = decode_vin(&vin);
+  if let Some(info) = &vin_info {
+    let level = if info.check_digit == CheckDigit::Invalid { "warn" } else { "info" };
     runtime.log_event(LogEvent {
       timestamp: Utc::now(),
-      level: "info".to_string(),
+      level: level.to_string(),
       kind: LogKind::Protocol,
       message: "VIN decode".to_string(),
-      payload: json!({ "wmi": info.wmi, "year": info.year }),
+      payload: json!({
+        "wmi": info.wmi,
+        "manufacturer": info.manufacturer,
+        "make": info.make,
+        "country": info.country,
+        "year": info.year,
+        "checkDigit": info.check_digit,
+        "fields": info.fields,
+      }),
+    });
+  }
+  let make = runtime.vehicle_make(&vin);
+  let vehicle = runtime.vehicle(&vin);
+
+  let (profiles, profile_errors) = ProfileStore::load(app);
+  for err in profile_errors {
+    runtime.log_event(LogEvent {
+      timestamp: Utc::now(),
+      level: "warn".to_string(),
+      kind: LogKind::System,
+      message: "Discovery profile ignored".to_string(),
+      payload: json!({ "error": err }),
+    });
+  }
+  let selected = load_selection(app);
+  let profile = selected
+    .as_deref()
+    .and_then(|id| profiles.get(id))
+    .or_else(|| profiles.best_match(vin_info.as_ref()));
+  let mut candidates = extra_candidates;
+  let mut names = Vec::new();
+  if let Some(profile) = profile {
+    candidates.extend(profile.modules.iter().cloned());
+    names.extend(profile.names.iter().cloned());
+  }
+  runtime.log_event(LogEvent {
+    timestamp: Utc::now(),
+    level: "info".to_string(),
+    kind: LogKind::System,
+    message: "Discovery profile".to_string(),
+    payload: json!({
+      "profile": profile.map(|profile| &profile.id),
+      "selected": selected,
+      "candidates": candidates.len(),
+    }),
+  });
+
+  let (baselines, baseline_errors) = BaselineStore::load(app);
+  for err in baseline_errors {
+    runtime.log_event(LogEvent {
+      timestamp: Utc::now(),
+      level: "warn".to_string(),
+      kind: LogKind::System,
+      message: "Baseline ignored".to_string(),
+      payload: json!({ "error": err }),
     });
   }
+  let baseline = baselines
+    .best_match(&vin)
+    .cloned()
+    .or_else(|| profile.and_then(Baseline::from_profile));
+  // Expected modules are probed even when they sit outside the sweep ranges.
+  for module in baseline.iter().flat_map(|baseline| &baseline.modules) {
+    if !candidates.iter().any(|candidate| candidate.address() == module.address()) {
+      candidates.push(module.candidate());
+    }
+  }
 
   runtime.update_state(app, |state| {
     state.vin = Some(vin.clone());
+    state.vehicle = vehicle;
     state.phase = AppPhase::Discovering;
     state.progress = Some(ProgressInfo {
       stage: "discovering".to_string(),
@@ -329,10 +526,53 @@ async fn run_real_scan(
     });
   });
 
-  let modules = discover_modules(&mut uds, &extra_candidates);
+  let mut fitted = Vec::new();
+  if let Some(spec) = profile.and_then(|profile| profile.gateway.as_ref()) {
+    let catalog = candidates.iter().chain(names.iter()).cloned().collect::<Vec<_>>();
+    match gateway_inventory(&mut uds, spec, &catalog) {
+      Ok(inventory) => {
+        runtime.log_event(LogEvent {
+          timestamp: Utc::now(),
+          level: "info".to_string(),
+          kind: LogKind::Protocol,
+          message: "Gateway inventory".to_string(),
+          payload: json!({
+            "gateway": spec.address().label(),
+            "modules": inventory
+              .iter()
+              .map(|module| json!({ "name": module.name, "address": module.address().label() }))
+              .collect::<Vec<_>>(),
+          }),
+        });
+        fitted = inventory;
+      }
+      Err(err) => runtime.log_event(LogEvent {
+        timestamp: Utc::now(),
+        level: "warn".to_string(),
+        kind: LogKind::Protocol,
+        message: "Gateway inventory failed".to_string(),
+        payload: json!({ "gateway": spec.address().label(), "error": err.to_string() }),
+      }),
+    }
+  }
+  // Fitted modules are probed too, so ones outside the sweep ranges still get found.
+  for module in &fitted {
+    if !candidates.iter().any(|candidate| candidate.address() == module.address()) {
+      candidates.push(module.clone());
+    }
+  }
+
+  let mut modules = discover_modules(&mut uds, &candidates, &names, &discovery);
+  merge_inventory(&mut modules, &fitted);
   runtime.update_state(app, |state| {
     state.modules = modules.clone();
-    state.topology = build_topology(&state.modules);
+    state.topology_hints = TopologyHints {
+      buses: profile.map(|profile| profile.buses.clone()).unwrap_or_default(),
+      gateway: profile
+        .and_then(|profile| profile.gateway.as_ref())
+        .map(|spec| spec.address()),
+    };
+    state.refresh_topology();
     state.progress = Some(ProgressInfo {
       stage: "discovering".to_string(),
       percent: 55,
@@ -340,6 +580,101 @@ async fn run_real_scan(
     });
   });
 
+  for module in modules.iter_mut() {
+    if module.status == ModuleStatus::NoResponse {
+      continue;
+    }
+    let (values, failures) = uds.read_identification(module.address());
+    runtime.log_event(LogEvent {
+      timestamp: Utc::now(),
+      level: "info".to_string(),
+      kind: LogKind::Protocol,
+      message: "Module identification".to_string(),
+      payload: json!({
+        "module": module.id,
+        "values": values,
+        "failures": failures
+          .iter()
+          .map(|(did, err)| json!({ "did": format!("{did:04X}"), "error": err.to_string() }))
+          .collect::<Vec<_>>(),
+      }),
+    });
+    module.identification = values;
+  }
+  runtime.update_state(app, |state| {
+    state.modules = modules.clone();
+    state.progress = Some(ProgressInfo {
+      stage: "discovering".to_string(),
+      percent: 60,
+      message: "Read module identification".to_string(),
+    });
+  });
+
+  if let Some(baseline) = &baseline {
+    let report = compare(baseline, &mut modules);
+    let deviations = report
+      .entries
+      .iter()
+      .filter(|entry| entry.status != BaselineStatus::Match)
+      .count();
+    runtime.log_event(LogEvent {
+      timestamp: Utc::now(),
+      level: if deviations > 0 { "warn" } else { "info" }.to_string(),
+      kind: LogKind::System,
+      message: "Baseline comparison".to_string(),
+      payload: json!({
+        "baseline": report.name,
+        "source": report.source,
+        "deviations": deviations,
+        "entries": report.entries,
+      }),
+    });
+    runtime.update_state(app, |state| {
+      state.modules = modules.clone();
+      state.baseline = Some(report);
+      state.refresh_topology();
+    });
+  }
+
+  let vin_dids = profile.map(|profile| profile.vin_dids.as_slice()).unwrap_or_default();
+  for module in modules.iter_mut() {
+    module.vin_checks = check_module_vin(&mut uds, module, &vin, vin_dids);
+  }
+  let flagged = modules
+    .iter()
+    .filter(|module| {
+      module
+        .vin_checks
+        .iter()
+        .any(|check| matches!(check.status, VinCheckStatus::Mismatch | VinCheckStatus::Blank))
+    })
+    .count();
+  runtime.log_event(LogEvent {
+    timestamp: Utc::now(),
+    level: if flagged > 0 { "warn" } else { "info" }.to_string(),
+    kind: LogKind::Protocol,
+    message: "Module VIN check".to_string(),
+    payload: json!({
+      "vin": vin,
+      "flagged": flagged,
+      "modules": modules
+        .iter()
+        .map(|module| json!({ "module": module.id, "checks": module.vin_checks }))
+        .collect::<Vec<_>>(),
+    }),
+  });
+  runtime.update_state(app, |state| {
+    state.modules = modules.clone();
+    state.progress = Some(ProgressInfo {
+      stage: "discovering".to_string(),
+      percent: 63,
+      message: match flagged {
+        0 => "Module VINs consistent".to_string(),
+        count => format!("{count} modules with a different or blank VIN"),
+      },
+    });
+  });
+
   runtime.update_state(app, |state| {
     state.phase = AppPhase::ScanningDtc;
     state.progress = Some(ProgressInfo {
@@ -352,8 +687,19 @@ async fn run_real_scan(
   let mut dtcs_map = std::collections::HashMap::new();
   let module_count = modules.len().max(1);
   for (index, module) in modules.iter().enumerate() {
-    match uds.read_dtcs(module.tx_id, module.rx_id) {
-      Ok(dtcs) => {
+    if module.status == ModuleStatus::NoResponse {
+      continue;
+    }
+    match uds.read_dtcs(module.address()) {
+      Ok(mut dtcs) => {
+        read_dtc_details(&mut uds, &runtime, module, &mut dtcs);
+        runtime
+          .dtc_db
+          .lock()
+          .describe(make.as_deref(), Some(&module.name), &mut dtcs);
+        if let Some(session) = runtime.simulation.lock().as_ref() {
+          session.describe_dtcs(&module.id, &mut dtcs);
+        }
         dtcs_map.insert(module.id.clone(), dtcs);
       }
       Err(err) => {
@@ -362,7 +708,11 @@ async fn run_real_scan(
           level: "warn".to_string(),
           kind: LogKind::Protocol,
           message: "DTC read failed".to_string(),
-          payload: json!({ "module": module.id, "error": err }),
+          payload: json!({
+            "module": module.id,
+            "error": err.to_string(),
+            "nrc": err.nrc().map(|nrc| nrc.name()),
+          }),
         });
       }
     }
@@ -375,6 +725,7 @@ async fn run_real_scan(
           info.dtc_count = dtcs.len();
         }
       }
+      state.refresh_topology();
       state.progress = Some(ProgressInfo {
         stage: "scanning".to_string(),
         percent: percent as u8,
@@ -390,7 +741,129 @@ async fn run_real_scan(
   Ok(())
 }
 
-async fn finish_session(app: &AppHandle, runtime: Arc<AppRuntime>) {
+// F190 plus the profile's as-built VIN DIDs; modules that never answered discovery are not asked.
+fn check_module_vin<T: Transport>(
+  uds: &mut UdsClient<T>,
+  module: &ModuleInfo,
+  vin: &str,
+  vin_dids: &[u16],
+) -> Vec<VinCheck> {
+  let mut dids = vec![VIN_DID];
+  dids.extend(vin_dids.iter().filter(|did| **did != VIN_DID));
+  dids
+    .into_iter()
+    .map(|did| {
+      if module.status == ModuleStatus::NoResponse {
+        return VinCheck {
+          did,
+          vin: None,
+          status: VinCheckStatus::NoResponse,
+        };
+      }
+      match uds.read_did(module.address(), did) {
+        Ok(bytes) if bytes.iter().all(|byte| matches!(byte, 0x00 | 0x20 | 0xFF)) => VinCheck {
+          did,
+          vin: None,
+          status: VinCheckStatus::Blank,
+        },
+        Ok(bytes) => {
+          let read = String::from_utf8_lossy(&bytes)
+            .trim_matches(|ch: char| ch == '\0' || ch.is_whitespace())
+            .to_string();
+          VinCheck {
+            did,
+            status: if read.eq_ignore_ascii_case(vin) {
+              VinCheckStatus::Match
+            } else {
+              VinCheckStatus::Mismatch
+            },
+            vin: Some(read),
+          }
+        }
+        Err(UdsError::Negative { .. }) => VinCheck {
+          did,
+          vin: None,
+          status: VinCheckStatus::NotSupported,
+        },
+        Err(_) => VinCheck {
+          did,
+          vin: None,
+          status: VinCheckStatus::NoResponse,
+        },
+      }
+    })
+    .collect()
+}
+
+// Best effort: modules answer unsupported sub-functions with an NRC, which only ends that step.
+fn read_dtc_details<T: Transport>(
+  uds: &mut UdsClient<T>,
+  runtime: &AppRuntime,
+  module: &ModuleInfo,
+  dtcs: &mut Vec<DtcInfo>,
+) {
+  let mut errors = Vec::new();
+  let count = uds
+    .count_dtcs(module.address(), 0xFF)
+    .map_err(|err| errors.push(format!("count: {err}")))
+    .ok();
+  let supported = uds
+    .read_supported_dtcs(module.address())
+    .map_err(|err| errors.push(format!("supported: {err}")))
+    .ok();
+
+  match uds.read_permanent_dtcs(module.address()) {
+    Ok(permanent) => {
+      for dtc in permanent {
+        match dtcs.iter_mut().find(|known| known.code == dtc.code) {
+          Some(known) => known.permanent = true,
+          None => dtcs.push(dtc),
+        }
+      }
+    }
+    Err(err) => errors.push(format!("permanent: {err}")),
+  }
+
+  let snapshot_ids = uds
+    .read_snapshot_ids(module.address())
+    .map_err(|err| errors.push(format!("snapshotIds: {err}")))
+    .ok();
+  for dtc in dtcs.iter_mut().take(MAX_DTC_DETAILS) {
+    let Some(bytes) = encode_dtc(&dtc.code) else {
+      continue;
+    };
+    let has_snapshot = snapshot_ids
+      .as_ref()
+      .map(|ids| ids.iter().any(|(id, _)| *id == bytes))
+      .unwrap_or(true);
+    if has_snapshot {
+      match uds.read_snapshots(module.address(), bytes) {
+        Ok(snapshots) => dtc.snapshots = snapshots,
+        Err(err) => errors.push(format!("snapshot {}: {err}", dtc.code)),
+      }
+    }
+    match uds.read_extended_data(module.address(), bytes) {
+      Ok(records) => dtc.extended_data = records,
+      Err(err) => errors.push(format!("extendedData {}: {err}", dtc.code)),
+    }
+  }
+
+  runtime.log_event(LogEvent {
+    timestamp: Utc::now(),
+    level: "info".to_string(),
+    kind: LogKind::Protocol,
+    message: "DTC details".to_string(),
+    payload: json!({
+      "module": module.id,
+      "count": count,
+      "supported": supported.map(|list| list.iter().map(|dtc| dtc.code.clone()).collect::<Vec<_>>()),
+      "dtcs": dtcs,
+      "errors": errors,
+    }),
+  });
+}
+
+async fn finish_session<R: Runtime>(app: &AppHandle<R>, runtime: Arc<AppRuntime>) {
   runtime.update_state(app, |state| {
     state.phase = AppPhase::Ready;
     state.progress = None;
@@ -425,3 +898,80 @@ async fn finish_session(app: &AppHandle, runtime: Arc<AppRuntime>) {
     state.last_session = Some(summary);
   });
 }
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+  use std::path::Path;
+  use std::sync::atomic::{AtomicBool, Ordering};
+  use std::thread;
+
+  use super::*;
+
+  const VCAN: &str = "vcan0";
+
+  // The app data dir is the bundle identifier joined onto the data home, so an absolute one replaces it.
+  fn mock_app(data_dir: &Path) -> tauri::App<tauri::test::MockRuntime> {
+    let mut context = tauri::test::mock_context(tauri::test::noop_assets());
+    context.config_mut().tauri.bundle.identifier = data_dir.to_string_lossy().into_owned();
+    tauri::test::mock_builder().build(context).unwrap()
+  }
+
+  #[test]
+  #[ignore = "needs a vcan0 interface"]
+  fn real_scan_over_vcan() {
+    let data_dir = std::env::temp_dir().join(format!("vyntool-vcan-{}", uuid::Uuid::new_v4()));
+
+    let session = load_simulation(None).unwrap();
+    let mut bus = SocketCanTransport::new(Some(VCAN.to_string()));
+    let mut ecus = SimTransport::from_session(&session);
+    bus.open().unwrap();
+    ecus.open().unwrap();
+    let stop = Arc::new(AtomicBool::new(false));
+    let bridge = {
+      let stop = stop.clone();
+      thread::spawn(move || {
+        while !stop.load(Ordering::SeqCst) {
+          let Some(frame) = bus.recv(20).unwrap() else {
+            continue;
+          };
+          ecus.send(&frame).unwrap();
+          while let Some(response) = ecus.recv(0).unwrap() {
+            bus.send(&response).unwrap();
+          }
+        }
+      })
+    };
+
+    let app = mock_app(&data_dir);
+    let runtime = Arc::new(AppRuntime::new(None));
+    let result = tauri::async_runtime::block_on(run_real_scan(
+      &app.handle(),
+      runtime.clone(),
+      &TransportMode::SocketCan,
+      Box::new(SocketCanTransport::new(Some(VCAN.to_string()))),
+      session.module_candidates(),
+      DiscoveryConfig::default(),
+    ));
+    stop.store(true, Ordering::SeqCst);
+    bridge.join().unwrap();
+    let _ = std::fs::remove_dir_all(&data_dir);
+    result.unwrap();
+
+    let state = runtime.state.lock();
+    assert_eq!(state.vin.as_deref(), Some(session.vin.as_str()));
+    for module in &session.modules {
+      let found = state
+        .modules
+        .iter()
+        .find(|info| info.id == module.id)
+        .unwrap_or_else(|| panic!("{} not discovered", module.id));
+      assert_eq!(found.status, ModuleStatus::Ok, "{}", module.id);
+      assert_eq!(
+        state.dtcs.get(&module.id).map_or(0, Vec::len),
+        module.dtcs.len(),
+        "{}",
+        module.id
+      );
+    }
+  }
+}
@@ -0,0 +1,257 @@
+use std::ffi::{c_char, CString};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+
+use libloading::Library;
+use serde::Deserialize;
+use tauri::AppHandle;
+
+use crate::protocol::did::hex;
+use crate::protocol::security::{parse_hex, AddSeedKey, SeedKeyAlgorithm, XorSeedKey};
+
+pub const SECURITY_CONFIG_FILE: &str = "security.json";
+
+const MAX_KEY_LENGTH: usize = 64;
+
+// Vector-style key generator export: status 0 means the key was written.
+type GenerateKeyEx =
+  unsafe extern "C" fn(*const u8, u32, u32, *const c_char, *mut u8, u32, *mut u32) -> i32;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecurityConfig {
+  #[serde(default)]
+  pub algorithms: Vec<AlgorithmEntry>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlgorithmEntry {
+  #[serde(default)]
+  pub make: Option<String>,
+  #[serde(default)]
+  pub module: Option<String>,
+  #[serde(default)]
+  pub levels: Vec<u8>,
+  #[serde(flatten)]
+  pub spec: AlgorithmSpec,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum AlgorithmSpec {
+  Xor {
+    constant: String,
+  },
+  Add {
+    constant: String,
+  },
+  Library {
+    path: PathBuf,
+    #[serde(default)]
+    variant: String,
+  },
+  Script {
+    path: PathBuf,
+    #[serde(default)]
+    args: Vec<String>,
+  },
+}
+
+pub struct SharedLibrarySeedKey {
+  name: String,
+  library: Library,
+  variant: CString,
+}
+
+impl SharedLibrarySeedKey {
+  pub fn load(path: &Path, variant: &str) -> Result<Self, String> {
+    let library = unsafe { Library::new(path) }
+      .map_err(|err| format!("Failed to load seed-key library {}: {err}", path.display()))?;
+    unsafe { library.get::<GenerateKeyEx>(b"GenerateKeyEx\0") }
+      .map_err(|err| format!("{} has no GenerateKeyEx export: {err}", path.display()))?;
+    Ok(Self {
+      name: format!("library:{}", path.display()),
+      library,
+      variant: CString::new(variant).map_err(|_| "Invalid seed-key variant".to_string())?,
+    })
+  }
+}
+
+impl SeedKeyAlgorithm for SharedLibrarySeedKey {
+  fn name(&self) -> &str {
+    &self.name
+  }
+
+  fn compute_key(&self, level: u8, seed: &[u8]) -> Result<Vec<u8>, String> {
+    let mut key = vec![0u8; MAX_KEY_LENGTH];
+    let mut key_length = 0u32;
+    let status = unsafe {
+      let generate = self
+        .library
+        .get::<GenerateKeyEx>(b"GenerateKeyEx\0")
+        .map_err(|err| err.to_string())?;
+      generate(
+        seed.as_ptr(),
+        seed.len() as u32,
+        level as u32,
+        self.variant.as_ptr(),
+        key.as_mut_ptr(),
+        key.len() as u32,
+        &mut key_length,
+      )
+    };
+    if status != 0 {
+      return Err(format!("GenerateKeyEx failed with status {status}"));
+    }
+    key.truncate((key_length as usize).min(MAX_KEY_LENGTH));
+    Ok(key)
+  }
+}
+
+// Runs `<path> [args..] <level> <seed hex>` and reads the key as hex from stdout.
+pub struct ScriptSeedKey {
+  name: String,
+  path: PathBuf,
+  args: Vec<String>,
+}
+
+impl ScriptSeedKey {
+  pub fn new(path: PathBuf, args: Vec<String>) -> Self {
+    Self {
+      name: format!("script:{}", path.display()),
+      path,
+      args,
+    }
+  }
+}
+
+impl SeedKeyAlgorithm for ScriptSeedKey {
+  fn name(&self) -> &str {
+    &self.name
+  }
+
+  fn compute_key(&self, level: u8, seed: &[u8]) -> Result<Vec<u8>, String> {
+    let output = Command::new(&self.path)
+      .args(&self.args)
+      .arg(format!("{level:02X}"))
+      .arg(hex(seed))
+      .output()
+      .map_err(|err| format!("Failed to run seed-key script {}: {err}", self.path.display()))?;
+    if !output.status.success() {
+      // stderr is not echoed back; scripts may print intermediate key material there.
+      return Err(format!("Seed-key script exited with {}", output.status));
+    }
+    parse_hex(&String::from_utf8_lossy(&output.stdout))
+  }
+}
+
+struct Registration {
+  make: Option<String>,
+  module: Option<String>,
+  levels: Vec<u8>,
+  algorithm: Arc<dyn SeedKeyAlgorithm>,
+}
+
+#[derive(Default)]
+pub struct SecurityRegistry {
+  registrations: Vec<Registration>,
+}
+
+impl SecurityRegistry {
+  pub fn load(app: &AppHandle) -> (Self, Vec<String>) {
+    let mut registry = Self::default();
+    let errors = match config_path(app) {
+      Some(path) if path.exists() => registry.load_file(&path),
+      _ => Vec::new(),
+    };
+    (registry, errors)
+  }
+
+  pub fn load_file(&mut self, path: &Path) -> Vec<String> {
+    let config = match fs::read_to_string(path)
+      .map_err(|err| err.to_string())
+      .and_then(|contents| serde_json::from_str::<SecurityConfig>(&contents).map_err(|err| err.to_string()))
+    {
+      Ok(config) => config,
+      Err(err) => return vec![format!("Invalid security config {}: {err}", path.display())],
+    };
+    let mut errors = Vec::new();
+    for entry in config.algorithms {
+      match build_algorithm(&entry.spec) {
+        Ok(algorithm) => self.register(entry.make, entry.module, entry.levels, algorithm),
+        Err(err) => errors.push(err),
+      }
+    }
+    errors
+  }
+
+  // Empty `levels` and missing make/module act as wildcards.
+  pub fn register(
+    &mut self,
+    make: Option<String>,
+    module: Option<String>,
+    levels: Vec<u8>,
+    algorithm: Arc<dyn SeedKeyAlgorithm>,
+  ) {
+    self.registrations.push(Registration {
+      make,
+      module,
+      levels,
+      algorithm,
+    });
+  }
+
+  // A module match outranks a make match; later registrations win ties.
+  pub fn find(
+    &self,
+    make: Option<&str>,
+    module_names: &[&str],
+    level: u8,
+  ) -> Option<Arc<dyn SeedKeyAlgorithm>> {
+    self
+      .registrations
+      .iter()
+      .filter_map(|registration| {
+        if !registration.levels.is_empty() && !registration.levels.contains(&level) {
+          return None;
+        }
+        let mut score = 0;
+        if let Some(expected) = &registration.make {
+          if !make.is_some_and(|make| make.eq_ignore_ascii_case(expected)) {
+            return None;
+          }
+          score += 1;
+        }
+        if let Some(expected) = &registration.module {
+          if !module_names.iter().any(|name| name.eq_ignore_ascii_case(expected)) {
+            return None;
+          }
+          score += 2;
+        }
+        Some((score, registration))
+      })
+      .max_by_key(|(score, _)| *score)
+      .map(|(_, registration)| registration.algorithm.clone())
+  }
+}
+
+pub fn config_path(app: &AppHandle) -> Option<PathBuf> {
+  let base = tauri::api::path::app_data_dir(&app.config())?;
+  Some(base.join(SECURITY_CONFIG_FILE))
+}
+
+fn build_algorithm(spec: &AlgorithmSpec) -> Result<Arc<dyn SeedKeyAlgorithm>, String> {
+  Ok(match spec {
+    AlgorithmSpec::Xor { constant } => Arc::new(XorSeedKey {
+      constant: parse_hex(constant)?,
+    }),
+    AlgorithmSpec::Add { constant } => Arc::new(AddSeedKey {
+      constant: parse_hex(constant)?,
+    }),
+    AlgorithmSpec::Library { path, variant } => Arc::new(SharedLibrarySeedKey::load(path, variant)?),
+    AlgorithmSpec::Script { path, args } => Arc::new(ScriptSeedKey::new(path.clone(), args.clone())),
+  })
+}
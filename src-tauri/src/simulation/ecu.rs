@@ -0,0 +1,556 @@
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::app_state::DtcInfo;
+use crate::protocol::addressing::{
+  AddressingMode, EcuAddress, NORMAL_FIXED_FUNCTIONAL_ID, OBD_FUNCTIONAL_ID,
+};
+use crate::protocol::dtc::encode_dtc;
+use crate::protocol::security::parse_hex;
+use crate::transport::Frame;
+
+use super::SimulationModule;
+
+const NRC_SERVICE_NOT_SUPPORTED: u8 = 0x11;
+const NRC_SUBFUNCTION_NOT_SUPPORTED: u8 = 0x12;
+const NRC_INCORRECT_LENGTH: u8 = 0x13;
+const NRC_CONDITIONS_NOT_CORRECT: u8 = 0x22;
+const NRC_REQUEST_SEQUENCE_ERROR: u8 = 0x24;
+const NRC_REQUEST_OUT_OF_RANGE: u8 = 0x31;
+const NRC_INVALID_KEY: u8 = 0x35;
+const NRC_EXCEEDED_ATTEMPTS: u8 = 0x36;
+const NRC_TIME_DELAY_NOT_EXPIRED: u8 = 0x37;
+const NRC_SERVICE_NOT_SUPPORTED_IN_SESSION: u8 = 0x7F;
+const NRC_RESPONSE_PENDING: u8 = 0x78;
+
+const DTC_AVAILABILITY_MASK: u8 = 0xFF;
+const DTC_FORMAT_ISO14229: u8 = 0x01;
+const SNAPSHOT_RECORD: u8 = 0x01;
+
+const SESSION_DEFAULT: u8 = 0x01;
+const SESSION_EXTENDED: u8 = 0x03;
+const P2_SERVER_MS: u16 = 50;
+const P2_STAR_SERVER_MS: u16 = 5000;
+const S3_SERVER_MS: u64 = 5000;
+const MAX_KEY_ATTEMPTS: u8 = 3;
+const SECURITY_DELAY_MS: u64 = 10_000;
+
+struct RxState {
+  total_len: usize,
+  buffer: Vec<u8>,
+  next_seq: u8,
+}
+
+struct TxState {
+  payload: Vec<u8>,
+  offset: usize,
+  seq: u8,
+}
+
+pub struct VirtualEcu {
+  pub request_id: u32,
+  // The tester's address seen from the ECU: receives on the request ID, answers on the response ID.
+  link: EcuAddress,
+  vin: String,
+  dids: HashMap<u16, Vec<u8>>,
+  routines: HashMap<u16, Vec<u8>>,
+  dtcs: Vec<DtcInfo>,
+  permanent: Vec<DtcInfo>,
+  pending_responses: u8,
+  extended_clear: bool,
+  session: u8,
+  security_constant: Option<Vec<u8>>,
+  // Level and seed of the outstanding requestSeed.
+  pending_seed: Option<(u8, Vec<u8>)>,
+  unlocked_level: Option<u8>,
+  failed_keys: u8,
+  locked_until: Option<Instant>,
+  last_request: Instant,
+  rx: Option<RxState>,
+  tx: Option<TxState>,
+}
+
+impl VirtualEcu {
+  pub fn from_module(module: &SimulationModule, vin: &str) -> Self {
+    Self {
+      request_id: module.tx_id,
+      link: EcuAddress {
+        tx_id: module.rx_id,
+        rx_id: module.tx_id,
+        mode: match module.addressing {
+          AddressingMode::Extended { target, source } => AddressingMode::Extended {
+            target: source,
+            source: target,
+          },
+          mode => mode,
+        },
+      },
+      vin: vin.to_string(),
+      dids: module
+        .identification
+        .iter()
+        .filter_map(|(did, value)| {
+          let did = u16::from_str_radix(did.trim_start_matches("0x"), 16).ok()?;
+          Some((did, value.as_bytes().to_vec()))
+        })
+        .chain(hex_entries(&module.raw_dids))
+        .collect(),
+      routines: hex_entries(&module.routines).collect(),
+      dtcs: module.dtcs.clone(),
+      permanent: module.dtcs.iter().filter(|dtc| dtc.permanent).cloned().collect(),
+      pending_responses: module.pending_responses,
+      extended_clear: module.extended_clear,
+      session: SESSION_DEFAULT,
+      security_constant: module
+        .security_constant
+        .as_deref()
+        .and_then(|constant| parse_hex(constant).ok())
+        .filter(|constant| !constant.is_empty()),
+      pending_seed: None,
+      unlocked_level: None,
+      failed_keys: 0,
+      locked_until: None,
+      last_request: Instant::now(),
+      rx: None,
+      tx: None,
+    }
+  }
+
+  // Functional requests are addressed by ID alone; only normal and normal-fixed modules listen for them.
+  pub fn functional_id(&self) -> Option<u32> {
+    match self.link.mode {
+      AddressingMode::NormalFixed => Some(NORMAL_FIXED_FUNCTIONAL_ID),
+      AddressingMode::Normal if !self.link.is_29bit() => Some(OBD_FUNCTIONAL_ID),
+      _ => None,
+    }
+  }
+
+  pub fn handle_frame(&mut self, frame: &Frame, functional: bool) -> Vec<Frame> {
+    let data = if functional {
+      &frame.data[..]
+    } else {
+      match self.link.strip(frame) {
+        Some(data) => data,
+        None => return Vec::new(),
+      }
+    };
+    let Some(&pci) = data.first() else {
+      return Vec::new();
+    };
+    match pci >> 4 {
+      0x0 => {
+        let len = (pci & 0x0F) as usize;
+        if len == 0 || data.len() < 1 + len {
+          return Vec::new();
+        }
+        self.rx = None;
+        let request = data[1..1 + len].to_vec();
+        self.respond(&request)
+      }
+      // Segmented requests are only valid on physical addressing.
+      0x1 if !functional => {
+        if data.len() < self.link.frame_capacity() {
+          return Vec::new();
+        }
+        let total_len = (((pci & 0x0F) as usize) << 8) | data[1] as usize;
+        self.rx = Some(RxState {
+          total_len,
+          buffer: data[2..].to_vec(),
+          next_seq: 1,
+        });
+        let mut flow_control = vec![0u8; self.link.frame_capacity()];
+        flow_control[0] = 0x30;
+        vec![self.frame(flow_control)]
+      }
+      0x2 if !functional => {
+        let Some(mut rx) = self.rx.take() else {
+          return Vec::new();
+        };
+        if pci & 0x0F != rx.next_seq {
+          return Vec::new();
+        }
+        rx.buffer.extend_from_slice(&data[1..]);
+        rx.next_seq = (rx.next_seq + 1) & 0x0F;
+        if rx.buffer.len() < rx.total_len {
+          self.rx = Some(rx);
+          return Vec::new();
+        }
+        rx.buffer.truncate(rx.total_len);
+        self.respond(&rx.buffer)
+      }
+      0x3 if !functional => {
+        let flow_control = data.to_vec();
+        self.continue_transmit(&flow_control)
+      }
+      _ => Vec::new(),
+    }
+  }
+
+  fn respond(&mut self, request: &[u8]) -> Vec<Frame> {
+    let Some(response) = self.handle_request(request) else {
+      return Vec::new();
+    };
+    let mut frames = Vec::new();
+    // Slow modules answer DTC reads and clears with responsePending before the real reply.
+    if matches!(request[0], 0x19 | 0x14) && response[0] != 0x7F {
+      for _ in 0..self.pending_responses {
+        frames.extend(self.segment(negative(request[0], NRC_RESPONSE_PENDING)));
+      }
+    }
+    frames.extend(self.segment(response));
+    frames
+  }
+
+  fn handle_request(&mut self, request: &[u8]) -> Option<Vec<u8>> {
+    let service = *request.first()?;
+    // S3 server timer: without traffic the ECU falls back to the default session.
+    if self.last_request.elapsed() > Duration::from_millis(S3_SERVER_MS) {
+      self.set_session(SESSION_DEFAULT);
+    }
+    self.last_request = Instant::now();
+    let response = match service {
+      0x10 => {
+        if request.len() != 2 {
+          return Some(negative(service, NRC_INCORRECT_LENGTH));
+        }
+        let session = request[1] & 0x7F;
+        if !(SESSION_DEFAULT..=SESSION_EXTENDED).contains(&session) {
+          return Some(negative(service, NRC_SUBFUNCTION_NOT_SUPPORTED));
+        }
+        self.set_session(session);
+        if request[1] & 0x80 != 0 {
+          return None;
+        }
+        let [p2_high, p2_low] = P2_SERVER_MS.to_be_bytes();
+        let [p2_star_high, p2_star_low] = (P2_STAR_SERVER_MS / 10).to_be_bytes();
+        vec![0x50, session, p2_high, p2_low, p2_star_high, p2_star_low]
+      }
+      0x3E => {
+        if request.len() != 2 {
+          return Some(negative(service, NRC_INCORRECT_LENGTH));
+        }
+        if request[1] & 0x80 != 0 {
+          return None;
+        }
+        vec![0x7E, request[1]]
+      }
+      0x22 => self.read_data_by_identifier(request),
+      0x27 => self.security_access(request),
+      0x31 => self.routine_control(request),
+      0x19 => self.read_dtc_information(request),
+      0x14 => {
+        if request.len() != 4 {
+          return Some(negative(service, NRC_INCORRECT_LENGTH));
+        }
+        if self.extended_clear && self.session != SESSION_EXTENDED {
+          return Some(negative(service, NRC_CONDITIONS_NOT_CORRECT));
+        }
+        self.dtcs.clear();
+        vec![0x54]
+      }
+      _ => negative(service, NRC_SERVICE_NOT_SUPPORTED),
+    };
+    Some(response)
+  }
+
+  // Security access is tied to the session it was granted in.
+  fn set_session(&mut self, session: u8) {
+    if session != self.session {
+      self.unlocked_level = None;
+      self.pending_seed = None;
+    }
+    self.session = session;
+  }
+
+  // Key = seed XOR constant; three bad keys lock the ECU out for the delay period.
+  fn security_access(&mut self, request: &[u8]) -> Vec<u8> {
+    let Some(constant) = self.security_constant.clone() else {
+      return negative(0x27, NRC_SERVICE_NOT_SUPPORTED);
+    };
+    if request.len() < 2 {
+      return negative(0x27, NRC_INCORRECT_LENGTH);
+    }
+    if self.session == SESSION_DEFAULT {
+      return negative(0x27, NRC_SERVICE_NOT_SUPPORTED_IN_SESSION);
+    }
+    let sub_function = request[1] & 0x7F;
+    if sub_function == 0 || sub_function > 0x7E {
+      return negative(0x27, NRC_SUBFUNCTION_NOT_SUPPORTED);
+    }
+    if sub_function & 1 == 1 {
+      if request.len() != 2 {
+        return negative(0x27, NRC_INCORRECT_LENGTH);
+      }
+      if self.locked_until.is_some_and(|until| Instant::now() < until) {
+        return negative(0x27, NRC_TIME_DELAY_NOT_EXPIRED);
+      }
+      self.locked_until = None;
+      if self.unlocked_level == Some(sub_function) {
+        return vec![0x67, sub_function, 0x00, 0x00, 0x00, 0x00];
+      }
+      let seed = generate_seed();
+      self.pending_seed = Some((sub_function, seed.clone()));
+      let mut response = vec![0x67, sub_function];
+      response.extend(seed);
+      return response;
+    }
+
+    let Some((level, seed)) = self.pending_seed.take() else {
+      return negative(0x27, NRC_REQUEST_SEQUENCE_ERROR);
+    };
+    if level + 1 != sub_function {
+      return negative(0x27, NRC_REQUEST_SEQUENCE_ERROR);
+    }
+    let expected = seed
+      .iter()
+      .zip(constant.iter().cycle())
+      .map(|(seed, constant)| seed ^ constant)
+      .collect::<Vec<_>>();
+    if request[2..] != expected[..] {
+      self.failed_keys += 1;
+      if self.failed_keys >= MAX_KEY_ATTEMPTS {
+        self.failed_keys = 0;
+        self.locked_until = Some(Instant::now() + Duration::from_millis(SECURITY_DELAY_MS));
+        return negative(0x27, NRC_EXCEEDED_ATTEMPTS);
+      }
+      return negative(0x27, NRC_INVALID_KEY);
+    }
+    self.failed_keys = 0;
+    self.unlocked_level = Some(level);
+    vec![0x67, sub_function]
+  }
+
+  fn routine_control(&self, request: &[u8]) -> Vec<u8> {
+    if request.len() < 4 {
+      return negative(0x31, NRC_INCORRECT_LENGTH);
+    }
+    if request[1] != 0x01 {
+      return negative(0x31, NRC_SUBFUNCTION_NOT_SUPPORTED);
+    }
+    let Some(status) = self.routines.get(&u16::from_be_bytes([request[2], request[3]])) else {
+      return negative(0x31, NRC_REQUEST_OUT_OF_RANGE);
+    };
+    let mut response = vec![0x71, 0x01, request[2], request[3]];
+    response.extend_from_slice(status);
+    response
+  }
+
+  fn read_data_by_identifier(&self, request: &[u8]) -> Vec<u8> {
+    let pairs = request[1..].chunks_exact(2);
+    if request.len() < 3 || !pairs.remainder().is_empty() {
+      return negative(0x22, NRC_INCORRECT_LENGTH);
+    }
+    let mut response = vec![0x62];
+    for pair in pairs {
+      let did = u16::from_be_bytes([pair[0], pair[1]]);
+      let value = match did {
+        // A module-specific F190 stands in for a transplanted or unprogrammed module.
+        0xF190 => self.dids.get(&did).map_or(self.vin.as_bytes(), Vec::as_slice),
+        _ => match self.dids.get(&did) {
+          Some(value) => value.as_slice(),
+          None => return negative(0x22, NRC_REQUEST_OUT_OF_RANGE),
+        },
+      };
+      response.extend_from_slice(&pair[..2]);
+      response.extend_from_slice(value);
+    }
+    response
+  }
+
+  fn read_dtc_information(&self, request: &[u8]) -> Vec<u8> {
+    if request.len() < 2 {
+      return negative(0x19, NRC_INCORRECT_LENGTH);
+    }
+    let expected_len = match request[1] {
+      0x01 | 0x02 => 3,
+      0x03 | 0x0A | 0x15 => 2,
+      0x04 | 0x06 => 6,
+      _ => return negative(0x19, NRC_SUBFUNCTION_NOT_SUPPORTED),
+    };
+    if request.len() != expected_len {
+      return negative(0x19, NRC_INCORRECT_LENGTH);
+    }
+    match request[1] {
+      0x01 => {
+        let count = self.records(&self.dtcs, request[2]).len() as u16;
+        let [high, low] = count.to_be_bytes();
+        vec![0x59, 0x01, DTC_AVAILABILITY_MASK, DTC_FORMAT_ISO14229, high, low]
+      }
+      0x02 => self.dtc_list(0x02, &self.dtcs, request[2]),
+      0x0A => self.dtc_list(0x0A, &self.dtcs, 0x00),
+      0x15 => self.dtc_list(0x15, &self.permanent, 0x00),
+      0x03 => {
+        let mut response = vec![0x59, 0x03];
+        for (bytes, _) in self.records(&self.dtcs, 0x00) {
+          response.extend_from_slice(&bytes);
+          response.push(SNAPSHOT_RECORD);
+        }
+        response
+      }
+      sub_function => {
+        let target = [request[2], request[3], request[4]];
+        let record = request[5];
+        let records = self.records(&self.dtcs, 0x00);
+        let Some(index) = records.iter().position(|(bytes, _)| *bytes == target) else {
+          return negative(0x19, NRC_REQUEST_OUT_OF_RANGE);
+        };
+        let mut response = vec![0x59, sub_function];
+        response.extend_from_slice(&target);
+        response.push(records[index].1);
+        if sub_function == 0x04 {
+          if record != SNAPSHOT_RECORD && record != 0xFF {
+            return negative(0x19, NRC_REQUEST_OUT_OF_RANGE);
+          }
+          response.extend_from_slice(&snapshot_record(index));
+        } else {
+          if record != 0xFF && !(0x01..=0x02).contains(&record) {
+            return negative(0x19, NRC_REQUEST_OUT_OF_RANGE);
+          }
+          // Occurrence counter, then aging counter.
+          if record == 0xFF || record == 0x01 {
+            response.extend_from_slice(&[0x01, index as u8 + 1]);
+          }
+          if record == 0xFF || record == 0x02 {
+            response.extend_from_slice(&[0x02, 0x00]);
+          }
+        }
+        response
+      }
+    }
+  }
+
+  fn dtc_list(&self, sub_function: u8, dtcs: &[DtcInfo], mask: u8) -> Vec<u8> {
+    let mut response = vec![0x59, sub_function, DTC_AVAILABILITY_MASK];
+    for (bytes, status) in self.records(dtcs, mask) {
+      response.extend_from_slice(&bytes);
+      response.push(status);
+    }
+    response
+  }
+
+  // A zero mask means "every stored DTC", as used by the sub-functions without a mask.
+  fn records(&self, dtcs: &[DtcInfo], mask: u8) -> Vec<([u8; 3], u8)> {
+    dtcs
+      .iter()
+      .filter_map(|dtc| {
+        let bytes = encode_dtc(&dtc.code)?;
+        let status = if dtc.status_byte != 0 {
+          dtc.status_byte
+        } else {
+          status_byte(&dtc.status)
+        };
+        (mask == 0 || status & mask != 0).then_some((bytes, status))
+      })
+      .collect()
+  }
+
+  fn segment(&mut self, payload: Vec<u8>) -> Vec<Frame> {
+    let capacity = self.link.frame_capacity();
+    if payload.len() < capacity {
+      let mut data = vec![0u8; capacity];
+      data[0] = payload.len() as u8;
+      data[1..1 + payload.len()].copy_from_slice(&payload);
+      return vec![self.frame(data)];
+    }
+
+    let mut data = vec![0u8; capacity];
+    data[0] = 0x10 | ((payload.len() >> 8) as u8 & 0x0F);
+    data[1] = (payload.len() & 0xFF) as u8;
+    data[2..].copy_from_slice(&payload[0..capacity - 2]);
+    self.tx = Some(TxState {
+      payload,
+      offset: capacity - 2,
+      seq: 1,
+    });
+    vec![self.frame(data)]
+  }
+
+  fn continue_transmit(&mut self, flow_control: &[u8]) -> Vec<Frame> {
+    let Some(mut tx) = self.tx.take() else {
+      return Vec::new();
+    };
+    match flow_control[0] & 0x0F {
+      0x0 => {}
+      0x1 => {
+        self.tx = Some(tx);
+        return Vec::new();
+      }
+      _ => return Vec::new(),
+    }
+
+    let block_size = flow_control.get(1).copied().unwrap_or(0) as usize;
+    let capacity = self.link.frame_capacity();
+    let mut frames = Vec::new();
+    while tx.offset < tx.payload.len() {
+      let chunk_len = usize::min(capacity - 1, tx.payload.len() - tx.offset);
+      let mut data = vec![0u8; capacity];
+      data[0] = 0x20 | (tx.seq & 0x0F);
+      data[1..1 + chunk_len].copy_from_slice(&tx.payload[tx.offset..tx.offset + chunk_len]);
+      frames.push(self.frame(data));
+      tx.offset += chunk_len;
+      tx.seq = tx.seq.wrapping_add(1);
+      if block_size != 0 && frames.len() == block_size && tx.offset < tx.payload.len() {
+        self.tx = Some(tx);
+        return frames;
+      }
+    }
+    frames
+  }
+
+  fn frame(&self, data: Vec<u8>) -> Frame {
+    self.link.frame(&data)
+  }
+}
+
+fn hex_entries(entries: &BTreeMap<String, String>) -> impl Iterator<Item = (u16, Vec<u8>)> + '_ {
+  entries.iter().filter_map(|(id, value)| {
+    let id = u16::from_str_radix(id.trim_start_matches("0x"), 16).ok()?;
+    Some((id, parse_hex(value).ok()?))
+  })
+}
+
+fn negative(service: u8, nrc: u8) -> Vec<u8> {
+  vec![0x7F, service, nrc]
+}
+
+// Never all zero, which would read as "already unlocked".
+fn generate_seed() -> Vec<u8> {
+  let nanos = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|elapsed| elapsed.subsec_nanos())
+    .unwrap_or_default();
+  (nanos.wrapping_mul(2_654_435_761) | 1).to_be_bytes().to_vec()
+}
+
+fn status_byte(status: &str) -> u8 {
+  match status {
+    "active" => 0x0B,
+    "pending" => 0x24,
+    "stored" => 0x28,
+    _ => 0x08,
+  }
+}
+
+// Freeze frame: engine speed, vehicle speed, coolant temperature, module voltage.
+fn snapshot_record(index: usize) -> Vec<u8> {
+  let rpm = ((750 + index * 150).min(8000) as u16 * 4).to_be_bytes();
+  let speed = (index * 12).min(255) as u8;
+  vec![
+    SNAPSHOT_RECORD,
+    0x04,
+    0xF4,
+    0x0C,
+    rpm[0],
+    rpm[1],
+    0xF4,
+    0x0D,
+    speed,
+    0xF4,
+    0x05,
+    130,
+    0xF4,
+    0x42,
+    0x35,
+    0xE8,
+  ]
+}
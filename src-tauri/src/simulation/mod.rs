@@ -1,8 +1,16 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::Path;
 
-use crate::app_state::{DtcInfo, ModuleInfo};
+use crate::app_state::{DiagnosticSession, DtcInfo, ModuleInfo, VehicleInfo};
+use crate::discovery::ModuleCandidate;
+use crate::dtc_db::DtcDatabase;
+use crate::protocol::addressing::AddressingMode;
+
+mod ecu;
+
+pub use ecu::VirtualEcu;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -12,15 +20,6 @@ pub struct SimulationSession {
   pub modules: Vec<SimulationModule>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct VehicleInfo {
-  pub make: String,
-  pub model: String,
-  pub year: String,
-  pub trim: String,
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SimulationModule {
@@ -30,7 +29,22 @@ pub struct SimulationModule {
   pub category: String,
   pub tx_id: u32,
   pub rx_id: u32,
+  #[serde(default)]
+  pub addressing: AddressingMode,
   pub dtcs: Vec<DtcInfo>,
+  #[serde(default)]
+  pub pending_responses: u8,
+  #[serde(default)]
+  pub extended_clear: bool,
+  #[serde(default)]
+  pub identification: BTreeMap<String, String>,
+  #[serde(default)]
+  pub security_constant: Option<String>,
+  // Hex-encoded values for binary DIDs and startRoutine status records, keyed by hex ID.
+  #[serde(default)]
+  pub raw_dids: BTreeMap<String, String>,
+  #[serde(default)]
+  pub routines: BTreeMap<String, String>,
 }
 
 impl SimulationSession {
@@ -51,9 +65,58 @@ impl SimulationSession {
         category: module.category.clone(),
         tx_id: module.tx_id,
         rx_id: module.rx_id,
+        addressing: module.addressing,
         status: crate::app_state::ModuleStatus::Ok,
         dtc_count: module.dtcs.len(),
+        identification: Vec::new(),
+        session: DiagnosticSession::Default,
+        security_level: None,
+        vin_checks: Vec::new(),
       })
       .collect()
   }
+
+  pub fn module_candidates(&self) -> Vec<ModuleCandidate> {
+    self.modules
+      .iter()
+      .map(|module| ModuleCandidate {
+        tx_id: module.tx_id,
+        rx_id: module.rx_id,
+        addressing: module.addressing,
+        name: module.name.clone(),
+        bus: module.bus.clone(),
+        category: module.category.clone(),
+      })
+      .collect()
+  }
+
+  // Sessions may leave descriptions blank and rely on the offline database.
+  pub fn fill_descriptions(&mut self, db: &DtcDatabase) {
+    let make = self.vehicle.make.clone();
+    for module in self.modules.iter_mut() {
+      for dtc in module.dtcs.iter_mut() {
+        if !dtc.description.is_empty() {
+          continue;
+        }
+        if let Some(description) = db.lookup(&dtc.code, Some(&make), Some(&module.name)) {
+          dtc.description = description;
+        }
+      }
+    }
+  }
+
+  pub fn describe_dtcs(&self, module_id: &str, dtcs: &mut [DtcInfo]) {
+    let Some(module) = self.modules.iter().find(|module| module.id == module_id) else {
+      return;
+    };
+    for dtc in dtcs.iter_mut() {
+      let known = module
+        .dtcs
+        .iter()
+        .find(|known| known.code == dtc.code && !known.description.is_empty());
+      if let Some(known) = known {
+        dtc.description = known.description.clone();
+      }
+    }
+  }
 }
@@ -1,22 +1,418 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write;
 
-use crate::app_state::{BusInfo, ModuleInfo, TopologyGraph};
+use serde::{Deserialize, Serialize};
 
-pub fn build_topology(modules: &[ModuleInfo]) -> TopologyGraph {
-  let mut buses: HashMap<String, Vec<String>> = HashMap::new();
+use crate::app_state::{
+  BusAddressing, BusInfo, DtcInfo, GatewayLink, ModuleInfo, ModuleStatus, TopologyGraph,
+  TopologyNode,
+};
+use crate::protocol::addressing::{AddressingMode, EcuAddress};
+
+// What a profile knows about a bus that discovery cannot tell.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BusSpec {
+  pub name: String,
+  #[serde(default)]
+  pub baud_rate: Option<u32>,
+  #[serde(default)]
+  pub tester: bool,
+  // Name of the module that bridges this bus to the tester's side.
+  #[serde(default)]
+  pub gateway: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TopologyHints {
+  pub buses: Vec<BusSpec>,
+  // The profile's inventory gateway, assumed to bridge every bus that names no gateway of its own.
+  pub gateway: Option<EcuAddress>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TopologyFormat {
+  Json,
+  Dot,
+}
+
+pub fn build_topology(
+  modules: &[ModuleInfo],
+  dtcs: &HashMap<String, Vec<DtcInfo>>,
+  hints: &TopologyHints,
+) -> TopologyGraph {
+  let mut buses: BTreeMap<String, Vec<&ModuleInfo>> = BTreeMap::new();
+  for spec in &hints.buses {
+    buses.entry(spec.name.clone()).or_default();
+  }
   for module in modules {
-    buses
-      .entry(module.bus.clone())
-      .or_default()
-      .push(module.id.clone());
+    buses.entry(module.bus.clone()).or_default().push(module);
+  }
+
+  let inventory_gateway = hints
+    .gateway
+    .and_then(|address| modules.iter().find(|module| module.address() == address));
+  let tester_bus = hints
+    .buses
+    .iter()
+    .find(|spec| spec.tester)
+    .map(|spec| spec.name.clone())
+    .or_else(|| inventory_gateway.map(|module| module.bus.clone()))
+    .or_else(|| {
+      modules
+        .iter()
+        .find(|module| module.tx_id == 0x7E0)
+        .map(|module| module.bus.clone())
+    });
+
+  let mut links = Vec::new();
+  for name in buses.keys() {
+    if tester_bus.as_ref() == Some(name) {
+      continue;
+    }
+    let named = hints
+      .buses
+      .iter()
+      .find(|spec| &spec.name == name)
+      .and_then(|spec| spec.gateway.as_ref())
+      .and_then(|gateway| {
+        modules
+          .iter()
+          .find(|module| module.name.eq_ignore_ascii_case(gateway))
+      });
+    if let Some(gateway) = named.or(inventory_gateway) {
+      if &gateway.bus != name {
+        links.push(GatewayLink {
+          gateway: gateway.id.clone(),
+          from: gateway.bus.clone(),
+          to: name.clone(),
+        });
+      }
+    }
+  }
+
+  let nodes = modules
+    .iter()
+    .map(|module| TopologyNode {
+      id: module.id.clone(),
+      name: module.name.clone(),
+      bus: module.bus.clone(),
+      category: module.category.clone(),
+      status: module.status.clone(),
+      dtc_count: module.dtc_count,
+      gateway: links.iter().any(|link| link.gateway == module.id),
+      network_dtcs: dtcs
+        .get(&module.id)
+        .into_iter()
+        .flatten()
+        .filter(|dtc| dtc.code.starts_with('U'))
+        .map(|dtc| dtc.code.clone())
+        .collect(),
+    })
+    .collect();
+
+  let buses = buses
+    .into_iter()
+    .map(|(name, members)| {
+      let spec = hints.buses.iter().find(|spec| spec.name == name);
+      BusInfo {
+        modules: members.iter().map(|module| module.id.clone()).collect(),
+        baud_rate: spec.and_then(|spec| spec.baud_rate),
+        addressing: members
+          .iter()
+          .map(|module| bus_addressing(module.address()))
+          .collect::<BTreeSet<_>>()
+          .into_iter()
+          .collect(),
+        tester: tester_bus.as_ref() == Some(&name),
+        name,
+      }
+    })
+    .collect();
+
+  TopologyGraph {
+    buses,
+    nodes,
+    links,
+  }
+}
+
+fn bus_addressing(address: EcuAddress) -> BusAddressing {
+  match address.mode {
+    AddressingMode::NormalFixed => BusAddressing::NormalFixed,
+    AddressingMode::Extended { .. } => BusAddressing::Extended,
+    AddressingMode::Normal if address.is_29bit() => BusAddressing::Normal29Bit,
+    AddressingMode::Normal => BusAddressing::Normal11Bit,
+  }
+}
+
+pub fn export_topology(graph: &TopologyGraph, format: TopologyFormat) -> Result<String, String> {
+  match format {
+    TopologyFormat::Json => serde_json::to_string_pretty(graph).map_err(|err| err.to_string()),
+    TopologyFormat::Dot => Ok(to_dot(graph)),
+  }
+}
+
+// Buses are boxes, modules ellipses and gateways hexagons; gateway links are dashed and the tester hangs off its bus.
+fn to_dot(graph: &TopologyGraph) -> String {
+  let mut dot = String::from("graph topology {\n  rankdir=LR;\n  node [fontname=\"Helvetica\"];\n");
+  for bus in &graph.buses {
+    let mut label = bus.name.clone();
+    if let Some(baud_rate) = bus.baud_rate {
+      label.push_str(&format!("\n{} kbit/s", baud_rate / 1000));
+    }
+    let _ = writeln!(
+      dot,
+      "  \"bus:{}\" [shape=box, style=bold, label=\"{}\"];",
+      escape(&bus.name),
+      escape(&label)
+    );
+    if bus.tester {
+      let _ = writeln!(dot, "  \"tester\" [shape=diamond, label=\"Tester\"];");
+      let _ = writeln!(dot, "  \"tester\" -- \"bus:{}\";", escape(&bus.name));
+    }
   }
+  for node in &graph.nodes {
+    let mut label = format!("{}\n{}", node.name, node.id);
+    if node.dtc_count > 0 {
+      label.push_str(&format!("\n{} DTCs", node.dtc_count));
+    }
+    if !node.network_dtcs.is_empty() {
+      label.push_str(&format!("\n{}", node.network_dtcs.join(", ")));
+    }
+    let color = match node.status {
+      ModuleStatus::NoResponse | ModuleStatus::Error => "red",
+      ModuleStatus::Ok if node.dtc_count > 0 => "orange",
+      ModuleStatus::Ok => "darkgreen",
+    };
+    let shape = if node.gateway { "hexagon" } else { "ellipse" };
+    let _ = writeln!(
+      dot,
+      "  \"{}\" [shape={shape}, color={color}, label=\"{}\"];",
+      escape(&node.id),
+      escape(&label)
+    );
+    let _ = writeln!(
+      dot,
+      "  \"{}\" -- \"bus:{}\";",
+      escape(&node.id),
+      escape(&node.bus)
+    );
+  }
+  for link in &graph.links {
+    let _ = writeln!(
+      dot,
+      "  \"{}\" -- \"bus:{}\" [style=dashed, label=\"gateway\"];",
+      escape(&link.gateway),
+      escape(&link.to)
+    );
+  }
+  dot.push_str("}\n");
+  dot
+}
+
+fn escape(value: &str) -> String {
+  value
+    .replace('\\', "\\\\")
+    .replace('"', "\\\"")
+    .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+
+  use super::*;
+
+  fn module(tx_id: u32, name: &str, bus: &str) -> ModuleInfo {
+    serde_json::from_value(json!({
+      "id": format!("0x{tx_id:03X}"),
+      "name": name,
+      "bus": bus,
+      "category": "Body",
+      "txId": tx_id,
+      "rxId": tx_id + 8,
+      "status": "ok",
+      "dtcCount": 0
+    }))
+    .unwrap()
+  }
+
+  fn bus(name: &str, tester: bool, gateway: Option<&str>) -> BusSpec {
+    BusSpec {
+      name: name.to_string(),
+      baud_rate: None,
+      tester,
+      gateway: gateway.map(str::to_string),
+    }
+  }
+
+  fn tester_bus(graph: &TopologyGraph) -> Option<&str> {
+    graph
+      .buses
+      .iter()
+      .find(|bus| bus.tester)
+      .map(|bus| bus.name.as_str())
+  }
+
+  fn links(graph: &TopologyGraph) -> Vec<(&str, &str, &str)> {
+    graph
+      .links
+      .iter()
+      .map(|link| (link.gateway.as_str(), link.from.as_str(), link.to.as_str()))
+      .collect()
+  }
+
+  #[test]
+  fn tester_bus_falls_back_from_profile_to_gateway_to_pcm() {
+    let modules = vec![
+      module(0x7E0, "PCM", "HS-CAN"),
+      module(0x716, "GWM", "HS-CAN2"),
+      module(0x726, "BCM", "MS-CAN"),
+    ];
+    let dtcs = HashMap::new();
+    let gateway = Some(EcuAddress::normal(0x716, 0x71E));
+
+    let hints = TopologyHints {
+      buses: vec![bus("MS-CAN", true, None)],
+      gateway,
+    };
+    let graph = build_topology(&modules, &dtcs, &hints);
+    assert_eq!(tester_bus(&graph), Some("MS-CAN"));
+
+    let hints = TopologyHints {
+      buses: Vec::new(),
+      gateway,
+    };
+    let graph = build_topology(&modules, &dtcs, &hints);
+    assert_eq!(tester_bus(&graph), Some("HS-CAN2"));
 
-  let mut bus_list = Vec::new();
-  for (name, modules) in buses {
-    bus_list.push(BusInfo { name, modules });
+    let graph = build_topology(&modules, &dtcs, &TopologyHints::default());
+    assert_eq!(tester_bus(&graph), Some("HS-CAN"));
+
+    let graph = build_topology(&modules[2..], &dtcs, &TopologyHints::default());
+    assert_eq!(tester_bus(&graph), None);
+    assert!(graph.links.is_empty());
+  }
+
+  #[test]
+  fn named_gateways_override_the_inventory_gateway() {
+    let modules = vec![
+      module(0x716, "GWM", "HS-CAN"),
+      module(0x726, "BCM", "MS-CAN"),
+      module(0x7D0, "APIM", "I-CAN"),
+      module(0x720, "IPC", "HS-CAN3"),
+    ];
+    let hints = TopologyHints {
+      buses: vec![
+        bus("HS-CAN", true, None),
+        bus("I-CAN", false, Some("bcm")),
+        // Naming a module on the bus itself yields no self-link, and the inventory gateway does not stand in.
+        bus("MS-CAN", false, Some("BCM")),
+        bus("Spare", false, None),
+      ],
+      gateway: Some(EcuAddress::normal(0x716, 0x71E)),
+    };
+    let graph = build_topology(&modules, &HashMap::new(), &hints);
+    assert_eq!(
+      links(&graph),
+      vec![
+        ("0x716", "HS-CAN", "HS-CAN3"),
+        ("0x726", "MS-CAN", "I-CAN"),
+        ("0x716", "HS-CAN", "Spare"),
+      ]
+    );
+    let gateways = graph
+      .nodes
+      .iter()
+      .filter(|node| node.gateway)
+      .map(|node| node.id.as_str())
+      .collect::<Vec<_>>();
+    assert_eq!(gateways, vec!["0x716", "0x726"]);
+    assert!(graph
+      .buses
+      .iter()
+      .any(|bus| bus.name == "Spare" && bus.modules.is_empty()));
   }
 
-  bus_list.sort_by(|a, b| a.name.cmp(&b.name));
+  #[test]
+  fn bus_addressing_lists_each_scheme_once() {
+    let mut extended = module(0x7D0, "APIM", "HS-CAN");
+    extended.addressing = AddressingMode::Extended {
+      target: 0x40,
+      source: 0xF1,
+    };
+    let mut fixed = module(0, "TCM", "HS-CAN");
+    let address = EcuAddress::normal_fixed(0x18, 0xF1);
+    (fixed.tx_id, fixed.rx_id, fixed.addressing) = (address.tx_id, address.rx_id, address.mode);
+    let modules = vec![
+      extended,
+      module(0x7E0, "PCM", "HS-CAN"),
+      fixed,
+      module(0x18DA10F1, "BECM", "HS-CAN"),
+      module(0x7E1, "ECM", "HS-CAN"),
+    ];
+    let graph = build_topology(&modules, &HashMap::new(), &TopologyHints::default());
+    assert_eq!(
+      graph.buses[0].addressing,
+      vec![
+        BusAddressing::Normal11Bit,
+        BusAddressing::Normal29Bit,
+        BusAddressing::NormalFixed,
+        BusAddressing::Extended,
+      ]
+    );
+  }
 
-  TopologyGraph { buses: bus_list }
+  #[test]
+  fn dot_export_escapes_labels() {
+    let mut bcm = module(0x726, "BCM", "HS-CAN");
+    bcm.dtc_count = 2;
+    let mut radio = module(0x7D0, "Radio \"SYNC\" \\ APIM", "MS-CAN");
+    radio.status = ModuleStatus::NoResponse;
+    let modules = vec![module(0x7E0, "PCM", "HS-CAN"), bcm, radio];
+    let dtcs = HashMap::from([(
+      "0x726".to_string(),
+      serde_json::from_value(json!([
+        { "code": "P0420", "status": "confirmed" },
+        { "code": "U0100", "status": "confirmed" }
+      ]))
+      .unwrap(),
+    )]);
+    let hints = TopologyHints {
+      buses: vec![
+        BusSpec {
+          baud_rate: Some(500_000),
+          ..bus("HS-CAN", true, None)
+        },
+        BusSpec {
+          baud_rate: Some(125_000),
+          ..bus("MS-CAN", false, Some("BCM"))
+        },
+      ],
+      gateway: None,
+    };
+    let graph = build_topology(&modules, &dtcs, &hints);
+    let dot = export_topology(&graph, TopologyFormat::Dot).unwrap();
+    assert_eq!(
+      dot,
+      r#"graph topology {
+  rankdir=LR;
+  node [fontname="Helvetica"];
+  "bus:HS-CAN" [shape=box, style=bold, label="HS-CAN\n500 kbit/s"];
+  "tester" [shape=diamond, label="Tester"];
+  "tester" -- "bus:HS-CAN";
+  "bus:MS-CAN" [shape=box, style=bold, label="MS-CAN\n125 kbit/s"];
+  "0x7E0" [shape=ellipse, color=darkgreen, label="PCM\n0x7E0"];
+  "0x7E0" -- "bus:HS-CAN";
+  "0x726" [shape=hexagon, color=orange, label="BCM\n0x726\n2 DTCs\nU0100"];
+  "0x726" -- "bus:HS-CAN";
+  "0x7D0" [shape=ellipse, color=red, label="Radio \"SYNC\" \\ APIM\n0x7D0"];
+  "0x7D0" -- "bus:MS-CAN";
+  "0x726" -- "bus:MS-CAN" [style=dashed, label="gateway"];
+}
+"#
+    );
+  }
 }
@@ -1,33 +1,428 @@
+use std::collections::VecDeque;
+use std::io::{ErrorKind, Read, Write};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serialport::{ClearBuffer, SerialPort, SerialPortType};
+
 use super::{BusType, Filter, Frame, TimingConfig, Transport};
 
-pub struct VLinkerDirectTransport;
+const SERIAL_BAUD: u32 = 115_200;
+const COMMAND_TIMEOUT_MS: u64 = 1_000;
+const RESET_TIMEOUT_MS: u64 = 3_000;
+const PROMPT: u8 = b'>';
+// Ends ATMA; the adapter ignores spaces, so it is harmless if monitoring already stopped.
+const STOP_MONITOR: &[u8] = b" ";
+
+// A multi-frame request being sent: payload bytes left for Consecutive Frames and the receiver's block size.
+struct OutgoingMessage {
+  id: u32,
+  remaining: usize,
+  block_size: u8,
+  sent_in_block: u8,
+}
+
+// CAN auto-formatting is off: frames go out exactly as IsoTpLink builds them (PCI included)
+// and responses come back as raw `<id><data>` hex lines.
+pub struct VLinkerDirectTransport {
+  port_path: Option<String>,
+  port: Option<Box<dyn SerialPort>>,
+  baud: u32,
+  protocol: Option<(u32, bool)>,
+  header: Option<(u32, bool)>,
+  filters: Vec<Filter>,
+  response_timeout_ms: u64,
+  rx_queue: VecDeque<Frame>,
+  pending: Vec<u8>,
+  // ATMA is running, so the adapter keeps receiving between requests.
+  monitoring: bool,
+  // Whether the adapter waits for replies after sending (ATR1) or returns at once (ATR0).
+  responses: bool,
+  outgoing: Option<OutgoingMessage>,
+}
 
 impl VLinkerDirectTransport {
-  pub fn new() -> Self {
-    Self
+  pub fn new(port_path: Option<String>) -> Self {
+    Self {
+      port_path,
+      port: None,
+      baud: 500_000,
+      protocol: None,
+      header: None,
+      filters: Vec::new(),
+      response_timeout_ms: 200,
+      rx_queue: VecDeque::new(),
+      pending: Vec::new(),
+      monitoring: false,
+      responses: true,
+      outgoing: None,
+    }
+  }
+
+  fn initialize(&mut self) -> Result<(), String> {
+    // Drop whatever is left from a previous session, then reset to known defaults.
+    if let Some(port) = self.port.as_ref() {
+      let _ = port.clear(ClearBuffer::All);
+    }
+    let banner = self.command_with_timeout("ATZ", RESET_TIMEOUT_MS)?;
+    if !banner.iter().any(|line| line.contains("ELM") || line.contains("STN")) {
+      return Err(format!("Unexpected adapter banner: {}", banner.join(" ")));
+    }
+    for cmd in ["ATE0", "ATL0", "ATS0", "ATH1", "ATD0", "ATCAF0", "ATCFC0", "ATAT0", "ATR1"] {
+      self.expect_ok(cmd)?;
+    }
+    self.responses = true;
+    self.apply_response_timeout()?;
+    self.select_protocol(false)
+  }
+
+  fn select_protocol(&mut self, is_extended: bool) -> Result<(), String> {
+    if self.protocol == Some((self.baud, is_extended)) {
+      return Ok(());
+    }
+    let code = match (self.baud, is_extended) {
+      (500_000, false) => "6".to_string(),
+      (500_000, true) => "7".to_string(),
+      (250_000, false) => "8".to_string(),
+      (250_000, true) => "9".to_string(),
+      (baud, extended) => {
+        // User-defined protocol B: raw data, variable DLC, both ID lengths received, divisor of 500 kbps.
+        let divisor = 500_000 / baud.max(1);
+        if !(1..=64).contains(&divisor) || 500_000 % baud != 0 {
+          return Err(format!("Unsupported CAN baud rate for ELM adapter: {baud}"));
+        }
+        let options = if extended { 0x60 } else { 0xE0 };
+        self.expect_ok(&format!("ATPB{options:02X}{divisor:02X}"))?;
+        "B".to_string()
+      }
+    };
+    self.expect_ok(&format!("ATSP{code}"))?;
+    self.protocol = Some((self.baud, is_extended));
+    self.header = None;
+    self.apply_filters()
+  }
+
+  fn select_header(&mut self, id: u32, is_extended: bool) -> Result<(), String> {
+    if self.header == Some((id, is_extended)) {
+      return Ok(());
+    }
+    if is_extended {
+      self.expect_ok(&format!("ATCP{:02X}", (id >> 24) & 0x1F))?;
+      self.expect_ok(&format!("ATSH{:06X}", id & 0xFF_FFFF))?;
+    } else {
+      self.expect_ok(&format!("ATSH{:03X}", id & 0x7FF))?;
+    }
+    self.header = Some((id, is_extended));
+    Ok(())
+  }
+
+  fn apply_filters(&mut self) -> Result<(), String> {
+    if self.port.is_none() {
+      return Ok(());
+    }
+    let is_extended = self.is_extended();
+    let matching = self
+      .filters
+      .iter()
+      .filter(|filter| filter.is_extended == is_extended)
+      .collect::<Vec<_>>();
+
+    // The adapter holds a single filter/mask pair, so widen the mask until it admits every filter.
+    let (id, mask) = match matching.split_first() {
+      Some((first, rest)) => rest.iter().fold((first.id, first.mask), |(id, mask), filter| {
+        let mask = mask & filter.mask & !(id ^ filter.id);
+        (id & mask, mask)
+      }),
+      None if is_extended => (0x18DA_F100, 0x1FFF_FF00),
+      None => (0x700, 0x700),
+    };
+
+    if is_extended {
+      self.expect_ok(&format!("ATCF{:08X}", id & 0x1FFF_FFFF))?;
+      self.expect_ok(&format!("ATCM{:08X}", mask & 0x1FFF_FFFF))
+    } else {
+      self.expect_ok(&format!("ATCF{:03X}", id & 0x7FF))?;
+      self.expect_ok(&format!("ATCM{:03X}", mask & 0x7FF))
+    }
+  }
+
+  fn set_responses(&mut self, responses: bool) -> Result<(), String> {
+    if self.responses == responses {
+      return Ok(());
+    }
+    self.expect_ok(if responses { "ATR1" } else { "ATR0" })?;
+    self.responses = responses;
+    Ok(())
+  }
+
+  // Whether the receiver answers this frame: everything but a Consecutive Frame that neither
+  // ends a block nor the message, since those go out back to back under BS/STmin.
+  fn expects_reply(&mut self, frame: &Frame) -> bool {
+    let data = &frame.data;
+    match data[0] >> 4 {
+      0x1 if data.len() == 8 => {
+        let length = (((data[0] & 0x0F) as usize) << 8) | data[1] as usize;
+        let (length, carried) = match length {
+          0 => (u32::from_be_bytes([data[2], data[3], data[4], data[5]]) as usize, 2),
+          length => (length, 6),
+        };
+        self.outgoing = Some(OutgoingMessage {
+          id: frame.id,
+          remaining: length.saturating_sub(carried),
+          block_size: 0,
+          sent_in_block: 0,
+        });
+        true
+      }
+      0x2 => {
+        let Some(message) = self.outgoing.as_mut().filter(|message| message.id == frame.id) else {
+          return true;
+        };
+        message.remaining = message.remaining.saturating_sub(7);
+        message.sent_in_block = message.sent_in_block.wrapping_add(1);
+        let reply = message.remaining == 0
+          || (message.block_size != 0 && message.sent_in_block == message.block_size);
+        if message.remaining == 0 {
+          self.outgoing = None;
+        }
+        reply
+      }
+      _ => {
+        self.outgoing = None;
+        true
+      }
+    }
+  }
+
+  fn apply_response_timeout(&mut self) -> Result<(), String> {
+    let units = (self.response_timeout_ms / 4).clamp(1, 0xFF);
+    self.expect_ok(&format!("ATST{units:02X}"))
+  }
+
+  fn expect_ok(&mut self, cmd: &str) -> Result<(), String> {
+    let lines = self.command(cmd)?;
+    if lines.iter().any(|line| line == "OK") {
+      return Ok(());
+    }
+    Err(format!("Adapter did not acknowledge {cmd}: {}", lines.join(" ")))
+  }
+
+  fn command(&mut self, cmd: &str) -> Result<Vec<String>, String> {
+    self.command_with_timeout(cmd, COMMAND_TIMEOUT_MS)
+  }
+
+  fn command_with_timeout(&mut self, cmd: &str, timeout_ms: u64) -> Result<Vec<String>, String> {
+    self.stop_monitor()?;
+    self.write_line(cmd)?;
+    let lines = self.read_until_prompt(timeout_ms)?;
+    if lines.iter().any(|line| line == "?") {
+      return Err(format!("Adapter rejected command {cmd}"));
+    }
+    Ok(lines)
+  }
+
+  fn write_line(&mut self, line: &str) -> Result<(), String> {
+    let port = self.port.as_mut().ok_or_else(|| "Transport not open".to_string())?;
+    port
+      .write_all(format!("{line}\r").as_bytes())
+      .and_then(|_| port.flush())
+      .map_err(|err| format!("Serial write failed: {err}"))
+  }
+
+  // The adapter only receives while a request is outstanding, and ATST tops out at 1020 ms.
+  // ATMA keeps it listening until the next command, so a response that follows
+  // responsePending or arrives after the prompt is still caught.
+  fn start_monitor(&mut self) -> Result<(), String> {
+    if !self.monitoring {
+      self.write_line("ATMA")?;
+      self.monitoring = true;
+    }
+    Ok(())
+  }
+
+  fn stop_monitor(&mut self) -> Result<(), String> {
+    if !self.monitoring {
+      return Ok(());
+    }
+    self.monitoring = false;
+    let port = self.port.as_mut().ok_or_else(|| "Transport not open".to_string())?;
+    port
+      .write_all(STOP_MONITOR)
+      .and_then(|_| port.flush())
+      .map_err(|err| format!("Serial write failed: {err}"))?;
+    let lines = self.read_until_prompt(COMMAND_TIMEOUT_MS)?;
+    self.queue_response_lines(lines, self.is_extended())
+  }
+
+  // Queues monitored frames until one arrives, monitoring ends or the timeout passes.
+  fn read_monitor(&mut self, timeout_ms: u64) -> Result<(), String> {
+    let deadline = Instant::now() + Duration::from_millis(timeout_ms);
+    let mut chunk = [0u8; 256];
+    loop {
+      while let Some(index) = self
+        .pending
+        .iter()
+        .position(|byte| *byte == b'\r' || *byte == PROMPT)
+      {
+        if self.pending[index] == PROMPT {
+          self.monitoring = false;
+        }
+        let text = String::from_utf8_lossy(&self.pending[..index]).to_string();
+        self.pending.drain(..=index);
+        self.queue_response_lines(split_lines(&text), self.is_extended())?;
+      }
+      if !self.rx_queue.is_empty() || !self.monitoring || Instant::now() >= deadline {
+        return Ok(());
+      }
+      let port = self.port.as_mut().ok_or_else(|| "Transport not open".to_string())?;
+      match port.read(&mut chunk) {
+        Ok(0) => continue,
+        Ok(read) => self.pending.extend_from_slice(&chunk[..read]),
+        Err(err) if err.kind() == ErrorKind::TimedOut || err.kind() == ErrorKind::WouldBlock => continue,
+        Err(err) => return Err(format!("Serial read failed: {err}")),
+      }
+    }
+  }
+
+  fn is_extended(&self) -> bool {
+    self.protocol.map(|(_, extended)| extended).unwrap_or(false)
+  }
+
+  fn read_until_prompt(&mut self, timeout_ms: u64) -> Result<Vec<String>, String> {
+    let deadline = Instant::now() + Duration::from_millis(timeout_ms);
+    let port = self.port.as_mut().ok_or_else(|| "Transport not open".to_string())?;
+    let mut chunk = [0u8; 256];
+
+    loop {
+      if let Some(index) = self.pending.iter().position(|byte| *byte == PROMPT) {
+        let text = String::from_utf8_lossy(&self.pending[..index]).to_string();
+        self.pending.drain(..=index);
+        return Ok(split_lines(&text));
+      }
+      if Instant::now() >= deadline {
+        return Err("Timed out waiting for adapter prompt".to_string());
+      }
+      match port.read(&mut chunk) {
+        Ok(0) => continue,
+        Ok(read) => self.pending.extend_from_slice(&chunk[..read]),
+        Err(err) if err.kind() == ErrorKind::TimedOut || err.kind() == ErrorKind::WouldBlock => continue,
+        Err(err) => return Err(format!("Serial read failed: {err}")),
+      }
+    }
+  }
+
+  fn queue_response_lines(&mut self, lines: Vec<String>, is_extended: bool) -> Result<(), String> {
+    for line in lines {
+      match line.as_str() {
+        "NO DATA" | "OK" | "STOPPED" => continue,
+        "CAN ERROR" | "BUS ERROR" | "BUS INIT: ...ERROR" | "UNABLE TO CONNECT" => {
+          return Err(format!("Adapter reported {line}"));
+        }
+        "BUFFER FULL" => return Err("Adapter receive buffer overflow".to_string()),
+        _ => {}
+      }
+      if let Some(frame) = parse_frame_line(&line, is_extended) {
+        self.note_flow_control(&frame);
+        self.rx_queue.push_back(frame);
+      }
+    }
+    Ok(())
+  }
+
+  fn note_flow_control(&mut self, frame: &Frame) {
+    let Some(message) = self.outgoing.as_mut() else {
+      return;
+    };
+    match frame.data.first() {
+      Some(0x30) => {
+        message.block_size = frame.data.get(1).copied().unwrap_or(0);
+        message.sent_in_block = 0;
+      }
+      Some(0x32) => self.outgoing = None,
+      _ => {}
+    }
   }
 }
 
 impl Transport for VLinkerDirectTransport {
   fn open(&mut self) -> Result<(), String> {
-    Err("VLinker direct transport not implemented (TODO)".to_string())
+    if self.port.is_some() {
+      return Ok(());
+    }
+    let path = match self.port_path.clone() {
+      Some(path) => path,
+      None => find_serial_port()?,
+    };
+    let port = serialport::new(&path, SERIAL_BAUD)
+      .timeout(Duration::from_millis(20))
+      .open()
+      .map_err(|err| format!("Failed to open serial port {path}: {err}"))?;
+    self.port = Some(port);
+    self.protocol = None;
+    self.header = None;
+    self.rx_queue.clear();
+    self.pending.clear();
+    self.monitoring = false;
+    self.outgoing = None;
+
+    if let Err(err) = self.initialize() {
+      self.port = None;
+      return Err(err);
+    }
+    Ok(())
   }
 
-  fn close(&mut self) {}
+  fn close(&mut self) {
+    if self.port.is_some() {
+      let _ = self.command("ATPC");
+    }
+    self.port = None;
+    self.protocol = None;
+    self.header = None;
+    self.rx_queue.clear();
+    self.monitoring = false;
+    self.outgoing = None;
+  }
 
-  fn send(&mut self, _frame: &Frame) -> Result<(), String> {
-    Err("VLinker direct transport not implemented (TODO)".to_string())
+  fn send(&mut self, frame: &Frame) -> Result<(), String> {
+    if self.port.is_none() {
+      return Err("Transport not open".to_string());
+    }
+    if frame.data.is_empty() || frame.data.len() > 8 {
+      return Err(format!("Invalid CAN frame length: {}", frame.data.len()));
+    }
+    self.select_protocol(frame.is_extended)?;
+    self.select_header(frame.id, frame.is_extended)?;
+    let expects_reply = self.expects_reply(frame);
+    self.set_responses(expects_reply)?;
+
+    let hex = frame.data.iter().map(|byte| format!("{byte:02X}")).collect::<String>();
+    let timeout = self.response_timeout_ms + COMMAND_TIMEOUT_MS;
+    let lines = self.command_with_timeout(&hex, timeout)?;
+    self.queue_response_lines(lines, frame.is_extended)
   }
 
-  fn recv(&mut self, _timeout_ms: u64) -> Result<Option<Frame>, String> {
-    Err("VLinker direct transport not implemented (TODO)".to_string())
+  fn recv(&mut self, timeout_ms: u64) -> Result<Option<Frame>, String> {
+    if self.port.is_none() {
+      return Err("Transport not open".to_string());
+    }
+    if let Some(frame) = self.rx_queue.pop_front() {
+      return Ok(Some(frame));
+    }
+    self.start_monitor()?;
+    self.read_monitor(timeout_ms)?;
+    Ok(self.rx_queue.pop_front())
   }
 
-  fn set_filters(&mut self, _filters: Vec<Filter>) -> Result<(), String> {
-    Ok(())
+  fn set_filters(&mut self, filters: Vec<Filter>) -> Result<(), String> {
+    self.filters = filters;
+    self.apply_filters()
   }
 
-  fn set_baud(&mut self, _baud: u32) -> Result<(), String> {
+  fn set_baud(&mut self, baud: u32) -> Result<(), String> {
+    self.baud = baud;
+    if self.port.is_some() {
+      self.select_protocol(self.is_extended())?;
+    }
     Ok(())
   }
 
@@ -35,7 +430,208 @@ impl Transport for VLinkerDirectTransport {
     Ok(())
   }
 
-  fn set_timing(&mut self, _timing: TimingConfig) -> Result<(), String> {
+  fn set_timing(&mut self, timing: TimingConfig) -> Result<(), String> {
+    self.response_timeout_ms = timing.p2_ms;
+    if self.port.is_some() {
+      self.apply_response_timeout()?;
+    }
     Ok(())
   }
 }
+
+fn split_lines(text: &str) -> Vec<String> {
+  text
+    .split(['\r', '\n'])
+    .map(|line| line.trim().to_string())
+    .filter(|line| !line.is_empty())
+    .collect()
+}
+
+fn parse_frame_line(line: &str, is_extended: bool) -> Option<Frame> {
+  let compact = line.replace(' ', "");
+  let id_len = if is_extended { 8 } else { 3 };
+  if compact.len() <= id_len || compact.len() % 2 != id_len % 2 {
+    return None;
+  }
+  let id = u32::from_str_radix(&compact[..id_len], 16).ok()?;
+  let data = (id_len..compact.len())
+    .step_by(2)
+    .map(|index| u8::from_str_radix(&compact[index..index + 2], 16).ok())
+    .collect::<Option<Vec<_>>>()?;
+  if data.len() > 8 {
+    return None;
+  }
+  let timestamp_ms = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|elapsed| elapsed.as_millis())
+    .unwrap_or(0);
+  Some(Frame {
+    id,
+    data,
+    timestamp_ms,
+    is_extended,
+  })
+}
+
+fn find_serial_port() -> Result<String, String> {
+  if let Ok(path) = std::env::var("VLINKER_PORT") {
+    if !path.is_empty() {
+      return Ok(path);
+    }
+  }
+
+  let ports = serialport::available_ports().map_err(|err| format!("Failed to list serial ports: {err}"))?;
+  ports
+    .into_iter()
+    .find(|port| matches!(port.port_type, SerialPortType::UsbPort(_)))
+    .map(|port| port.port_name)
+    .ok_or_else(|| "vLinker serial port not found. Connect the adapter or set VLINKER_PORT.".to_string())
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+  use std::ffi::CStr;
+  use std::fs::File;
+  use std::os::fd::FromRawFd;
+  use std::ptr;
+  use std::thread::{self, JoinHandle};
+
+  use super::*;
+
+  const OK: &str = "OK\r\r>";
+
+  // Plays an ELM327 on the master side of a pty; each step is the command expected and the reply.
+  fn spawn_adapter(script: Vec<(&'static str, &'static str)>) -> (String, JoinHandle<()>) {
+    let (mut master, mut slave) = (0, 0);
+    let status = unsafe {
+      libc::openpty(
+        &mut master,
+        &mut slave,
+        ptr::null_mut(),
+        ptr::null(),
+        ptr::null(),
+      )
+    };
+    assert_eq!(status, 0, "openpty failed");
+    let path = unsafe { CStr::from_ptr(libc::ttyname(slave)) }
+      .to_string_lossy()
+      .to_string();
+    let adapter = thread::spawn(move || {
+      let mut master = unsafe { File::from_raw_fd(master) };
+      for (command, reply) in script {
+        let mut received = Vec::new();
+        let mut byte = [0u8; 1];
+        while received != STOP_MONITOR && !received.ends_with(b"\r") {
+          master.read_exact(&mut byte).unwrap();
+          received.push(byte[0]);
+        }
+        assert_eq!(
+          String::from_utf8_lossy(&received).trim_end_matches('\r'),
+          command
+        );
+        master.write_all(reply.as_bytes()).unwrap();
+      }
+      unsafe { libc::close(slave) };
+    });
+    (path, adapter)
+  }
+
+  fn request(data: &[u8]) -> Frame {
+    Frame {
+      id: 0x7E0,
+      data: data.to_vec(),
+      timestamp_ms: 0,
+      is_extended: false,
+    }
+  }
+
+  fn next(transport: &mut VLinkerDirectTransport) -> (u32, Vec<u8>) {
+    let frame = transport.recv(500).unwrap().expect("no frame received");
+    (frame.id, frame.data)
+  }
+
+  #[test]
+  fn elm327_script_over_pty() {
+    let (path, adapter) = spawn_adapter(vec![
+      ("ATZ", "\r\rELM327 v1.5\r\r>"),
+      ("ATE0", OK),
+      ("ATL0", OK),
+      ("ATS0", OK),
+      ("ATH1", OK),
+      ("ATD0", OK),
+      ("ATCAF0", OK),
+      ("ATCFC0", OK),
+      ("ATAT0", OK),
+      ("ATR1", OK),
+      ("ATST32", OK),
+      ("ATSP6", OK),
+      ("ATCF700", OK),
+      ("ATCM700", OK),
+      // Single frame each way.
+      ("ATSH7E0", OK),
+      ("021003", "7E8065003003201F4\r\r>"),
+      // VIN in a First Frame, then two Consecutive Frames after our Flow Control.
+      ("0322F190", "7E8101462F190314654\r\r>"),
+      ("300000", "7E8213757324254364B\r7E82245443132333435\r\r>"),
+      // Multi-frame request: the middle Consecutive Frame does not wait for a reply.
+      ("10142EF190AABBCC", "7E8300000\r\r>"),
+      ("ATR0", OK),
+      ("21DDEEFF00112233", "\r>"),
+      ("ATR1", OK),
+      ("22445566778899AA", "7E8036EF190\r\r>"),
+      // The final answer after responsePending arrives while the adapter monitors.
+      ("0431010203", "7E8037F3178\r\r>"),
+      ("ATMA", "7E80471010203\r"),
+      (" ", "STOPPED\r\r>"),
+      ("ATPC", OK),
+    ]);
+
+    let mut transport = VLinkerDirectTransport::new(Some(path));
+    transport.open().unwrap();
+
+    transport.send(&request(&[0x02, 0x10, 0x03])).unwrap();
+    assert_eq!(
+      next(&mut transport),
+      (0x7E8, vec![0x06, 0x50, 0x03, 0x00, 0x32, 0x01, 0xF4])
+    );
+
+    transport.send(&request(&[0x03, 0x22, 0xF1, 0x90])).unwrap();
+    assert_eq!(
+      next(&mut transport),
+      (0x7E8, vec![0x10, 0x14, 0x62, 0xF1, 0x90, 0x31, 0x46, 0x54])
+    );
+    transport.send(&request(&[0x30, 0x00, 0x00])).unwrap();
+    assert_eq!(
+      next(&mut transport),
+      (0x7E8, vec![0x21, 0x37, 0x57, 0x32, 0x42, 0x54, 0x36, 0x4B])
+    );
+    assert_eq!(
+      next(&mut transport),
+      (0x7E8, vec![0x22, 0x45, 0x44, 0x31, 0x32, 0x33, 0x34, 0x35])
+    );
+
+    transport
+      .send(&request(&[0x10, 0x14, 0x2E, 0xF1, 0x90, 0xAA, 0xBB, 0xCC]))
+      .unwrap();
+    assert_eq!(next(&mut transport), (0x7E8, vec![0x30, 0x00, 0x00]));
+    transport
+      .send(&request(&[0x21, 0xDD, 0xEE, 0xFF, 0x00, 0x11, 0x22, 0x33]))
+      .unwrap();
+    transport
+      .send(&request(&[0x22, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xAA]))
+      .unwrap();
+    assert_eq!(next(&mut transport), (0x7E8, vec![0x03, 0x6E, 0xF1, 0x90]));
+
+    transport
+      .send(&request(&[0x04, 0x31, 0x01, 0x02, 0x03]))
+      .unwrap();
+    assert_eq!(next(&mut transport), (0x7E8, vec![0x03, 0x7F, 0x31, 0x78]));
+    assert_eq!(
+      next(&mut transport),
+      (0x7E8, vec![0x04, 0x71, 0x01, 0x02, 0x03])
+    );
+
+    transport.close();
+    adapter.join().unwrap();
+  }
+}
@@ -0,0 +1,290 @@
+use std::collections::HashSet;
+#[cfg(not(windows))]
+use std::fs;
+use std::path::Path;
+#[cfg(not(windows))]
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+// Protocol flags a driver advertises, as registry values (Windows) or config keys (Linux).
+const PROTOCOL_KEYS: &[&str] = &[
+  "CAN", "ISO15765", "J1850VPW", "J1850PWM", "ISO9141", "ISO14230",
+];
+#[cfg(windows)]
+const PASSTHRU_REGISTRY_KEY: &str = "SOFTWARE\\PassThruSupport.04.04";
+// Fallbacks for vLinker installs that never registered themselves.
+const KNOWN_PATHS: &[&str] = &[
+  "C:\\Program Files (x86)\\vLinker\\J2534.dll",
+  "C:\\Program Files\\vLinker\\J2534.dll",
+  "C:\\Windows\\System32\\J2534.dll",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum J2534DeviceSource {
+  Environment,
+  #[cfg(windows)]
+  Registry,
+  #[cfg(not(windows))]
+  Config,
+  KnownPath,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct J2534Device {
+  pub name: String,
+  pub vendor: Option<String>,
+  pub library: String,
+  pub source: J2534DeviceSource,
+  pub protocols: Vec<String>,
+}
+
+// Every installed PassThru driver, `J2534_DLL` first; entries whose library is missing come back as errors.
+pub fn list_j2534_devices() -> (Vec<J2534Device>, Vec<String>) {
+  let mut devices = Vec::new();
+  let mut errors = Vec::new();
+
+  if let Ok(path) = std::env::var("J2534_DLL") {
+    devices.push(J2534Device {
+      name: file_stem(Path::new(&path)),
+      vendor: None,
+      library: path,
+      source: J2534DeviceSource::Environment,
+      protocols: Vec::new(),
+    });
+  }
+  #[cfg(windows)]
+  devices.extend(registry_devices());
+  #[cfg(not(windows))]
+  for dir in config_dirs() {
+    config_devices(&dir, &mut devices, &mut errors);
+  }
+  devices.extend(KNOWN_PATHS.iter().map(|path| J2534Device {
+    name: "vLinker FS".to_string(),
+    vendor: Some("vLinker".to_string()),
+    library: path.to_string(),
+    source: J2534DeviceSource::KnownPath,
+    protocols: Vec::new(),
+  }));
+
+  retain_installed(&mut devices, &mut errors);
+  (devices, errors)
+}
+
+// Drops drivers whose library is missing and later entries for a library already listed.
+fn retain_installed(devices: &mut Vec<J2534Device>, errors: &mut Vec<String>) {
+  let mut seen = HashSet::new();
+  devices.retain(|device| {
+    if !Path::new(&device.library).exists() {
+      // Known paths are only guesses; a driver that claims to be installed is worth reporting.
+      if device.source != J2534DeviceSource::KnownPath {
+        errors.push(format!(
+          "{}: library {} not found",
+          device.name, device.library
+        ));
+      }
+      return false;
+    }
+    seen.insert(device.library.to_ascii_lowercase())
+  });
+}
+
+fn file_stem(path: &Path) -> String {
+  path
+    .file_stem()
+    .unwrap_or_default()
+    .to_string_lossy()
+    .to_string()
+}
+
+// The registry view matches the process, so a 64-bit build only sees drivers it can load.
+#[cfg(windows)]
+fn registry_devices() -> Vec<J2534Device> {
+  use winreg::enums::HKEY_LOCAL_MACHINE;
+  use winreg::RegKey;
+
+  let Ok(root) = RegKey::predef(HKEY_LOCAL_MACHINE).open_subkey(PASSTHRU_REGISTRY_KEY) else {
+    return Vec::new();
+  };
+  root
+    .enum_keys()
+    .filter_map(Result::ok)
+    .filter_map(|name| {
+      let key = root.open_subkey(&name).ok()?;
+      let library = key.get_value::<String, _>("FunctionLibrary").ok()?;
+      Some(J2534Device {
+        name: key.get_value::<String, _>("Name").unwrap_or(name),
+        vendor: key.get_value::<String, _>("Vendor").ok(),
+        library,
+        source: J2534DeviceSource::Registry,
+        protocols: PROTOCOL_KEYS
+          .iter()
+          .filter(|protocol| {
+            key
+              .get_value::<u32, _>(protocol)
+              .is_ok_and(|value| value != 0)
+          })
+          .map(|protocol| protocol.to_string())
+          .collect(),
+      })
+    })
+    .collect()
+}
+
+// Open-source drivers drop one JSON file per device here, with NAME, VENDOR, FUNCTION_LIB and protocol flags.
+#[cfg(not(windows))]
+fn config_dirs() -> Vec<PathBuf> {
+  let mut dirs = Vec::new();
+  if let Some(home) = std::env::var_os("HOME") {
+    dirs.push(PathBuf::from(home).join(".passthru"));
+  }
+  dirs.push(PathBuf::from("/etc/passthru"));
+  dirs
+}
+
+#[cfg(not(windows))]
+fn config_devices(dir: &Path, devices: &mut Vec<J2534Device>, errors: &mut Vec<String>) {
+  let Ok(entries) = fs::read_dir(dir) else {
+    return;
+  };
+  let mut paths = entries
+    .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+    .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+    .collect::<Vec<_>>();
+  paths.sort();
+
+  for path in paths {
+    let config = fs::read_to_string(&path)
+      .map_err(|err| err.to_string())
+      .and_then(|contents| {
+        serde_json::from_str::<serde_json::Value>(&contents).map_err(|err| err.to_string())
+      });
+    let config = match config {
+      Ok(config) => config,
+      Err(err) => {
+        errors.push(format!("Invalid J2534 config {}: {err}", path.display()));
+        continue;
+      }
+    };
+    let Some(library) = config["FUNCTION_LIB"].as_str() else {
+      errors.push(format!(
+        "J2534 config {} has no FUNCTION_LIB",
+        path.display()
+      ));
+      continue;
+    };
+    devices.push(J2534Device {
+      name: config["NAME"]
+        .as_str()
+        .map(str::to_string)
+        .unwrap_or_else(|| file_stem(&path)),
+      vendor: config["VENDOR"].as_str().map(str::to_string),
+      library: library.to_string(),
+      source: J2534DeviceSource::Config,
+      protocols: PROTOCOL_KEYS
+        .iter()
+        .filter(|protocol| {
+          config[**protocol].as_bool() == Some(true)
+            || config[**protocol].as_u64().is_some_and(|value| value != 0)
+        })
+        .map(|protocol| protocol.to_string())
+        .collect(),
+    });
+  }
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+  use super::*;
+
+  fn names(devices: &[J2534Device]) -> Vec<&str> {
+    devices.iter().map(|device| device.name.as_str()).collect()
+  }
+
+  #[test]
+  fn reads_linux_driver_configs() {
+    let dir = std::env::temp_dir().join(format!("vyntool-j2534-{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(&dir).unwrap();
+    let library = dir.join("libpassthru.so");
+    fs::write(&library, b"").unwrap();
+    let library = library.to_string_lossy().to_string();
+    let configs = [
+      (
+        "a_tactrix.json",
+        serde_json::json!({
+          "NAME": "Tactrix OpenPort 2.0",
+          "VENDOR": "Tactrix",
+          "FUNCTION_LIB": library,
+          "CAN": true,
+          "ISO15765": 1,
+          "J1850VPW": false,
+          "ISO9141": 0
+        })
+        .to_string(),
+      ),
+      (
+        "b_macchina.json",
+        serde_json::json!({ "FUNCTION_LIB": "/nonexistent/libm2.so", "ISO15765": true })
+          .to_string(),
+      ),
+      (
+        "c_nolib.json",
+        serde_json::json!({ "NAME": "No library" }).to_string(),
+      ),
+      ("d_broken.json", "{ \"NAME\": ".to_string()),
+      (
+        "e_duplicate.json",
+        serde_json::json!({ "NAME": "Same driver", "FUNCTION_LIB": library }).to_string(),
+      ),
+      ("notes.txt", "ignored".to_string()),
+    ];
+    for (name, contents) in &configs {
+      fs::write(dir.join(name), contents).unwrap();
+    }
+
+    let mut devices = Vec::new();
+    let mut errors = Vec::new();
+    config_devices(&dir, &mut devices, &mut errors);
+    assert_eq!(
+      names(&devices),
+      vec!["Tactrix OpenPort 2.0", "b_macchina", "Same driver"]
+    );
+    assert_eq!(devices[0].vendor.as_deref(), Some("Tactrix"));
+    assert_eq!(devices[0].protocols, vec!["CAN", "ISO15765"]);
+    assert_eq!(devices[1].vendor, None);
+    assert_eq!(devices[1].protocols, vec!["ISO15765"]);
+    assert_eq!(errors.len(), 2, "{errors:?}");
+    assert!(
+      errors[0].contains("c_nolib.json has no FUNCTION_LIB"),
+      "{errors:?}"
+    );
+    assert!(errors[1].starts_with("Invalid J2534 config"), "{errors:?}");
+    assert!(errors[1].contains("d_broken.json"), "{errors:?}");
+
+    errors.clear();
+    retain_installed(&mut devices, &mut errors);
+    assert_eq!(names(&devices), vec!["Tactrix OpenPort 2.0"]);
+    assert_eq!(
+      errors,
+      vec!["b_macchina: library /nonexistent/libm2.so not found"]
+    );
+    let _ = fs::remove_dir_all(&dir);
+  }
+
+  #[test]
+  fn missing_known_paths_are_not_errors() {
+    let mut devices = vec![J2534Device {
+      name: "vLinker FS".to_string(),
+      vendor: Some("vLinker".to_string()),
+      library: "/nonexistent/J2534.dll".to_string(),
+      source: J2534DeviceSource::KnownPath,
+      protocols: Vec::new(),
+    }];
+    let mut errors = Vec::new();
+    retain_installed(&mut devices, &mut errors);
+    assert!(devices.is_empty());
+    assert!(errors.is_empty(), "{errors:?}");
+  }
+}
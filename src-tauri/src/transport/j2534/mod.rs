@@ -0,0 +1,764 @@
+use super::{AdapterVersions, BusType, Filter, Frame, TimingConfig, Transport};
+use crate::protocol::addressing::EcuAddress;
+use libloading::Library;
+use std::ffi::{c_char, c_void};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+mod devices;
+mod status;
+
+pub use devices::{list_j2534_devices, J2534Device};
+use devices::J2534DeviceSource;
+use status::J2534Status;
+
+// The API promises at most 80 characters; the slack covers DLLs that write past that.
+const TEXT_BUFFER_LEN: usize = 256;
+
+const PROTOCOL_CAN: u32 = 0x00000005;
+const PROTOCOL_ISO15765: u32 = 0x00000006;
+const PASS_FILTER: u32 = 0x00000001;
+const FLOW_CONTROL_FILTER: u32 = 0x00000003;
+const CAN_29BIT_ID: u32 = 0x00000100;
+const CAN_ID_BOTH: u32 = 0x00000800;
+const ISO15765_FRAME_PAD: u32 = 0x00000040;
+const ISO15765_ADDR_TYPE: u32 = 0x00000080;
+// RxStatus bits for our own transmissions and for a First Frame the adapter has started reassembling.
+const TX_MSG_TYPE: u32 = 0x00000001;
+const START_OF_MESSAGE: u32 = 0x00000002;
+const TX_DONE: u32 = 0x00000008;
+// A payload write returns once the adapter has sent every frame, flow control waits included.
+const PAYLOAD_WRITE_TIMEOUT_MS: u32 = 1000;
+// N_Cr budget once the adapter reports a First Frame.
+const RECEPTION_TIMEOUT_MS: u64 = 1000;
+const DRAIN_LIMIT: usize = 256;
+const GET_CONFIG: u32 = 0x01;
+const SET_CONFIG: u32 = 0x02;
+const READ_VBATT: u32 = 0x03;
+const CLEAR_TX_BUFFER: u32 = 0x07;
+const CLEAR_RX_BUFFER: u32 = 0x08;
+const CLEAR_MSG_FILTERS: u32 = 0x0A;
+const DATA_RATE: u32 = 0x01;
+const LOOPBACK: u32 = 0x03;
+const ISO15765_BS: u32 = 0x1E;
+const ISO15765_STMIN: u32 = 0x1F;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct PassThruMsg {
+  protocol_id: u32,
+  rx_status: u32,
+  tx_flags: u32,
+  timestamp: u32,
+  data_size: u32,
+  extra_data_index: u32,
+  data: [u8; 4128],
+}
+
+#[repr(C)]
+struct SConfig {
+  parameter: u32,
+  value: u32,
+}
+
+#[repr(C)]
+struct SConfigList {
+  num_of_params: u32,
+  config_ptr: *mut SConfig,
+}
+
+impl Default for PassThruMsg {
+  fn default() -> Self {
+    Self {
+      protocol_id: 0,
+      rx_status: 0,
+      tx_flags: 0,
+      timestamp: 0,
+      data_size: 0,
+      extra_data_index: 0,
+      data: [0u8; 4128],
+    }
+  }
+}
+
+type PassThruOpen = unsafe extern "C" fn(*mut c_void, *mut u32) -> u32;
+type PassThruClose = unsafe extern "C" fn(u32) -> u32;
+type PassThruConnect = unsafe extern "C" fn(u32, u32, u32, u32, *mut u32) -> u32;
+type PassThruDisconnect = unsafe extern "C" fn(u32) -> u32;
+type PassThruReadMsgs = unsafe extern "C" fn(u32, *mut PassThruMsg, *mut u32, u32) -> u32;
+type PassThruWriteMsgs = unsafe extern "C" fn(u32, *mut PassThruMsg, *mut u32, u32) -> u32;
+type PassThruStartMsgFilter =
+  unsafe extern "C" fn(u32, u32, *mut PassThruMsg, *mut PassThruMsg, *mut PassThruMsg, *mut u32) -> u32;
+type PassThruStopMsgFilter = unsafe extern "C" fn(u32, u32) -> u32;
+type PassThruIoctl = unsafe extern "C" fn(u32, u32, *mut c_void, *mut c_void) -> u32;
+type PassThruReadVersion = unsafe extern "C" fn(u32, *mut c_char, *mut c_char, *mut c_char) -> u32;
+type PassThruGetLastError = unsafe extern "C" fn(*mut c_char) -> u32;
+
+pub struct J2534Library {
+  _lib: Library,
+  open: PassThruOpen,
+  close: PassThruClose,
+  connect: PassThruConnect,
+  disconnect: PassThruDisconnect,
+  read_msgs: PassThruReadMsgs,
+  write_msgs: PassThruWriteMsgs,
+  start_filter: Option<PassThruStartMsgFilter>,
+  stop_filter: Option<PassThruStopMsgFilter>,
+  ioctl: Option<PassThruIoctl>,
+  read_version: Option<PassThruReadVersion>,
+  get_last_error: Option<PassThruGetLastError>,
+}
+
+impl J2534Library {
+  unsafe fn new(path: &Path) -> Result<Self, String> {
+    let lib = Library::new(path).map_err(|err| format!("Failed to load J2534 DLL: {err}"))?;
+
+    let open: PassThruOpen = *lib
+      .get::<PassThruOpen>(b"PassThruOpen")
+      .map_err(|err| err.to_string())?;
+    let close: PassThruClose = *lib
+      .get::<PassThruClose>(b"PassThruClose")
+      .map_err(|err| err.to_string())?;
+    let connect: PassThruConnect = *lib
+      .get::<PassThruConnect>(b"PassThruConnect")
+      .map_err(|err| err.to_string())?;
+    let disconnect: PassThruDisconnect = *lib
+      .get::<PassThruDisconnect>(b"PassThruDisconnect")
+      .map_err(|err| err.to_string())?;
+    let read_msgs: PassThruReadMsgs = *lib
+      .get::<PassThruReadMsgs>(b"PassThruReadMsgs")
+      .map_err(|err| err.to_string())?;
+    let write_msgs: PassThruWriteMsgs = *lib
+      .get::<PassThruWriteMsgs>(b"PassThruWriteMsgs")
+      .map_err(|err| err.to_string())?;
+    let start_filter = lib
+      .get::<PassThruStartMsgFilter>(b"PassThruStartMsgFilter")
+      .ok()
+      .map(|symbol| *symbol);
+    let stop_filter = lib
+      .get::<PassThruStopMsgFilter>(b"PassThruStopMsgFilter")
+      .ok()
+      .map(|symbol| *symbol);
+    let ioctl = lib
+      .get::<PassThruIoctl>(b"PassThruIoctl")
+      .ok()
+      .map(|symbol| *symbol);
+    let read_version = lib
+      .get::<PassThruReadVersion>(b"PassThruReadVersion")
+      .ok()
+      .map(|symbol| *symbol);
+    let get_last_error = lib
+      .get::<PassThruGetLastError>(b"PassThruGetLastError")
+      .ok()
+      .map(|symbol| *symbol);
+
+    Ok(Self {
+      _lib: lib,
+      open,
+      close,
+      connect,
+      disconnect,
+      read_msgs,
+      write_msgs,
+      start_filter,
+      stop_filter,
+      ioctl,
+      read_version,
+      get_last_error,
+    })
+  }
+
+  // The named status plus the DLL's own description of the failure, when it keeps one.
+  fn check(&self, call: &str, status: u32) -> Result<(), String> {
+    let status = J2534Status::from_code(status);
+    if status == J2534Status::NoError {
+      return Ok(());
+    }
+    match self.last_error() {
+      Some(detail) => Err(format!("{call} failed: {status}: {detail}")),
+      None => Err(format!("{call} failed: {status}")),
+    }
+  }
+
+  fn last_error(&self) -> Option<String> {
+    let get_last_error = self.get_last_error?;
+    let mut buffer = [0 as c_char; TEXT_BUFFER_LEN];
+    let status = unsafe { get_last_error(buffer.as_mut_ptr()) };
+    if J2534Status::from_code(status) != J2534Status::NoError {
+      return None;
+    }
+    let text = c_text(&buffer);
+    (!text.is_empty()).then_some(text)
+  }
+
+  fn versions(&self, device_id: u32) -> Result<AdapterVersions, String> {
+    let read_version = self
+      .read_version
+      .ok_or_else(|| "PassThruReadVersion not available".to_string())?;
+    let mut firmware = [0 as c_char; TEXT_BUFFER_LEN];
+    let mut dll = [0 as c_char; TEXT_BUFFER_LEN];
+    let mut api = [0 as c_char; TEXT_BUFFER_LEN];
+    let status = unsafe {
+      read_version(device_id, firmware.as_mut_ptr(), dll.as_mut_ptr(), api.as_mut_ptr())
+    };
+    self.check("PassThruReadVersion", status)?;
+    Ok(AdapterVersions {
+      firmware: c_text(&firmware),
+      dll: c_text(&dll),
+      api: c_text(&api),
+    })
+  }
+
+  // Channel IDs for everything but READ_VBATT, which takes the device ID.
+  fn ioctl(&self, id: u32, ioctl_id: u32, input: *mut c_void, output: *mut c_void) -> Result<(), String> {
+    let ioctl = self.ioctl.ok_or_else(|| "PassThruIoctl not available".to_string())?;
+    let status = unsafe { ioctl(id, ioctl_id, input, output) };
+    self.check(&format!("PassThruIoctl 0x{ioctl_id:02X}"), status)
+  }
+
+  fn set_config(&self, channel_id: u32, params: &[(u32, u32)]) -> Result<(), String> {
+    let mut configs = params
+      .iter()
+      .map(|(parameter, value)| SConfig {
+        parameter: *parameter,
+        value: *value,
+      })
+      .collect::<Vec<_>>();
+    let mut list = SConfigList {
+      num_of_params: configs.len() as u32,
+      config_ptr: configs.as_mut_ptr(),
+    };
+    self.ioctl(
+      channel_id,
+      SET_CONFIG,
+      &mut list as *mut SConfigList as *mut c_void,
+      std::ptr::null_mut(),
+    )
+  }
+
+  fn get_config(&self, channel_id: u32, parameter: u32) -> Result<u32, String> {
+    let mut config = SConfig { parameter, value: 0 };
+    let mut list = SConfigList {
+      num_of_params: 1,
+      config_ptr: &mut config,
+    };
+    self.ioctl(
+      channel_id,
+      GET_CONFIG,
+      &mut list as *mut SConfigList as *mut c_void,
+      std::ptr::null_mut(),
+    )?;
+    Ok(config.value)
+  }
+
+  fn clear_buffers(&self, channel_id: u32) -> Result<(), String> {
+    self.ioctl(channel_id, CLEAR_TX_BUFFER, std::ptr::null_mut(), std::ptr::null_mut())?;
+    self.ioctl(channel_id, CLEAR_RX_BUFFER, std::ptr::null_mut(), std::ptr::null_mut())
+  }
+
+  fn read_vbatt(&self, device_id: u32) -> Result<f32, String> {
+    let mut millivolts = 0u32;
+    self.ioctl(
+      device_id,
+      READ_VBATT,
+      std::ptr::null_mut(),
+      &mut millivolts as *mut u32 as *mut c_void,
+    )?;
+    Ok(millivolts as f32 / 1000.0)
+  }
+}
+
+pub struct VLinkerFsJ2534Transport {
+  dll_path: Option<PathBuf>,
+  lib: Option<J2534Library>,
+  device_id: u32,
+  channel_id: u32,
+  baud: u32,
+  is_open: bool,
+  // Native ISO-TP: a second channel on ISO15765 next to the raw CAN one that probes and functional requests use.
+  iso15765: bool,
+  iso_channel_id: Option<u32>,
+  // Block size and STmin the adapter sends in its own flow control frames.
+  iso15765_flow: (u8, u8),
+  // Installed in first-use order so the oldest can make room when the adapter runs out of filters.
+  flow_filters: Vec<(EcuAddress, u32)>,
+}
+
+impl VLinkerFsJ2534Transport {
+  pub fn new(dll_path: Option<PathBuf>) -> Self {
+    Self {
+      dll_path,
+      lib: None,
+      device_id: 0,
+      channel_id: 0,
+      baud: 500_000,
+      is_open: false,
+      iso15765: false,
+      iso_channel_id: None,
+      iso15765_flow: (0, 0),
+      flow_filters: Vec::new(),
+    }
+  }
+
+  // Falls back to software ISO-TP on the CAN channel when the adapter cannot open ISO15765.
+  pub fn with_iso15765(mut self, enabled: bool) -> Self {
+    self.iso15765 = enabled;
+    self
+  }
+
+  pub fn with_iso15765_flow(mut self, block_size: u8, st_min: u8) -> Self {
+    self.iso15765_flow = (block_size, st_min);
+    self
+  }
+
+  pub fn probe() -> Result<PathBuf, String> {
+    find_j2534_dll()
+  }
+
+  fn load_library(&mut self) -> Result<(), String> {
+    if self.lib.is_some() {
+      return Ok(());
+    }
+    let dll_path = match self.dll_path.clone() {
+      Some(path) => path,
+      None => find_j2534_dll()?,
+    };
+    let lib = unsafe { J2534Library::new(&dll_path)? };
+    self.lib = Some(lib);
+    Ok(())
+  }
+
+  // Outside a session the device is opened just long enough for `read`.
+  fn with_device<R>(
+    &mut self,
+    read: impl FnOnce(&J2534Library, u32) -> Result<R, String>,
+  ) -> Result<R, String> {
+    self.load_library()?;
+    let lib = self.lib.as_ref().ok_or_else(|| "J2534 library not loaded".to_string())?;
+    if self.is_open {
+      return read(lib, self.device_id);
+    }
+    let mut device_id = 0u32;
+    let status = unsafe { (lib.open)(std::ptr::null_mut(), &mut device_id) };
+    lib.check("PassThruOpen", status)?;
+    let result = read(lib, device_id);
+    unsafe { (lib.close)(device_id) };
+    result
+  }
+
+  fn ensure_open(&self) -> Result<(), String> {
+    if !self.is_open {
+      return Err("Transport not open".to_string());
+    }
+    Ok(())
+  }
+
+  fn connect_iso15765(&self, lib: &J2534Library) -> Option<u32> {
+    if !self.iso15765 || lib.start_filter.is_none() {
+      return None;
+    }
+    let mut channel_id = 0u32;
+    let status =
+      unsafe { (lib.connect)(self.device_id, PROTOCOL_ISO15765, CAN_ID_BOTH, self.baud, &mut channel_id) };
+    if J2534Status::from_code(status) != J2534Status::NoError {
+      return None;
+    }
+    let (block_size, st_min) = self.iso15765_flow;
+    let _ = lib.set_config(
+      channel_id,
+      &[
+        (LOOPBACK, 0),
+        (ISO15765_BS, block_size as u32),
+        (ISO15765_STMIN, st_min as u32),
+      ],
+    );
+    let _ = lib.clear_buffers(channel_id);
+    Some(channel_id)
+  }
+
+  fn iso_channel(&self) -> Result<(&J2534Library, u32), String> {
+    self.ensure_open()?;
+    let lib = self.lib.as_ref().ok_or_else(|| "J2534 library not loaded".to_string())?;
+    let channel_id = self
+      .iso_channel_id
+      .ok_or_else(|| "ISO15765 channel not connected".to_string())?;
+    Ok((lib, channel_id))
+  }
+
+  // Each module needs its own flow control filter before the adapter will talk ISO-TP to it.
+  fn ensure_flow_filter(&mut self, address: EcuAddress) -> Result<(), String> {
+    if self.flow_filters.iter().any(|(installed, _)| *installed == address) {
+      return Ok(());
+    }
+    let (lib, channel_id) = self.iso_channel()?;
+    let start_filter = lib
+      .start_filter
+      .ok_or_else(|| "PassThruStartMsgFilter not available".to_string())?;
+    let stop_filter = lib.stop_filter;
+
+    let tx_flags = iso15765_flags(address);
+    let mut mask = iso15765_msg(tx_flags, u32::MAX, address.rx_prefix().map(|_| 0xFF), &[]);
+    let mut pattern = iso15765_msg(tx_flags, address.rx_id, address.rx_prefix(), &[]);
+    let mut flow = iso15765_msg(tx_flags, address.tx_id, address.tx_prefix(), &[]);
+    let mut filter_id = 0u32;
+    let mut status = unsafe {
+      start_filter(channel_id, FLOW_CONTROL_FILTER, &mut mask, &mut pattern, &mut flow, &mut filter_id)
+    };
+    let mut evicted = false;
+    if J2534Status::from_code(status) == J2534Status::ExceededLimit {
+      if let (Some(stop_filter), Some((_, oldest))) = (stop_filter, self.flow_filters.first()) {
+        unsafe {
+          let _ = stop_filter(channel_id, *oldest);
+          status = start_filter(
+            channel_id,
+            FLOW_CONTROL_FILTER,
+            &mut mask,
+            &mut pattern,
+            &mut flow,
+            &mut filter_id,
+          );
+        }
+        evicted = true;
+      }
+    }
+    let result = lib.check(
+      &format!("PassThruStartMsgFilter (flow control for {})", address.label()),
+      status,
+    );
+    if evicted {
+      self.flow_filters.remove(0);
+    }
+    result?;
+    self.flow_filters.push((address, filter_id));
+    Ok(())
+  }
+
+  // Raw CAN exchanges run ISO-TP in software, so the adapter must not answer their First Frames as well.
+  // `ensure_flow_filter` puts each filter back on the next payload to that module.
+  fn release_flow_filters(&mut self) {
+    if self.flow_filters.is_empty() {
+      return;
+    }
+    let (Some(lib), Some(channel_id)) = (self.lib.as_ref(), self.iso_channel_id) else {
+      return;
+    };
+    // The ISO15765 channel carries nothing but these filters.
+    let _ = lib.ioctl(channel_id, CLEAR_MSG_FILTERS, std::ptr::null_mut(), std::ptr::null_mut());
+    self.flow_filters.clear();
+  }
+
+  // Leftovers from earlier exchanges: both channels see the same traffic, each in its own buffer.
+  fn drain(&self, channel_id: u32) {
+    let Some(lib) = self.lib.as_ref() else {
+      return;
+    };
+    let cleared = lib.ioctl(channel_id, CLEAR_RX_BUFFER, std::ptr::null_mut(), std::ptr::null_mut());
+    if cleared.is_ok() {
+      return;
+    }
+    let mut msg = PassThruMsg::default();
+    for _ in 0..DRAIN_LIMIT {
+      let mut num = 1u32;
+      let status = unsafe { (lib.read_msgs)(channel_id, &mut msg, &mut num, 0) };
+      if J2534Status::from_code(status) != J2534Status::NoError || num == 0 {
+        break;
+      }
+    }
+  }
+}
+
+fn c_text(buffer: &[c_char]) -> String {
+  let bytes = buffer
+    .iter()
+    .take_while(|byte| **byte != 0)
+    .map(|byte| *byte as u8)
+    .collect::<Vec<_>>();
+  String::from_utf8_lossy(&bytes).trim().to_string()
+}
+
+// Reads report an empty queue as a timeout or, with a zero timeout, as an empty buffer.
+fn is_empty_read(status: u32, num: u32) -> bool {
+  num == 0
+    || matches!(
+      J2534Status::from_code(status),
+      J2534Status::Timeout | J2534Status::BufferEmpty
+    )
+}
+
+fn iso15765_flags(address: EcuAddress) -> u32 {
+  let mut flags = ISO15765_FRAME_PAD;
+  if address.is_29bit() {
+    flags |= CAN_29BIT_ID;
+  }
+  if address.tx_prefix().is_some() {
+    flags |= ISO15765_ADDR_TYPE;
+  }
+  flags
+}
+
+// CAN ID, then the extended address byte if any, then the payload.
+fn iso15765_msg(tx_flags: u32, id: u32, prefix: Option<u8>, payload: &[u8]) -> PassThruMsg {
+  let mut data = [0u8; 4128];
+  data[0..4].copy_from_slice(&id.to_be_bytes());
+  let mut len = 4;
+  if let Some(prefix) = prefix {
+    data[len] = prefix;
+    len += 1;
+  }
+  data[len..len + payload.len()].copy_from_slice(payload);
+  PassThruMsg {
+    protocol_id: PROTOCOL_ISO15765,
+    tx_flags,
+    data_size: (len + payload.len()) as u32,
+    data,
+    ..PassThruMsg::default()
+  }
+}
+
+impl Transport for VLinkerFsJ2534Transport {
+  fn open(&mut self) -> Result<(), String> {
+    if self.is_open {
+      return Ok(());
+    }
+    self.load_library()?;
+    let lib = self.lib.as_ref().ok_or_else(|| "J2534 library not loaded".to_string())?;
+
+    let mut device_id = 0u32;
+    let status = unsafe { (lib.open)(std::ptr::null_mut(), &mut device_id) };
+    lib.check("PassThruOpen", status)?;
+
+    let mut channel_id = 0u32;
+    let status = unsafe { (lib.connect)(device_id, PROTOCOL_CAN, CAN_ID_BOTH, self.baud, &mut channel_id) };
+    if let Err(err) = lib.check("PassThruConnect", status) {
+      unsafe { (lib.close)(device_id) };
+      return Err(err);
+    }
+
+    // Some adapters fall back to their default rate instead of refusing one they cannot do.
+    if let Ok(applied) = lib.get_config(channel_id, DATA_RATE) {
+      if applied != self.baud {
+        unsafe {
+          (lib.disconnect)(channel_id);
+          (lib.close)(device_id);
+        }
+        return Err(format!("Adapter connected at {applied} bit/s instead of {}", self.baud));
+      }
+    }
+
+    // Loopback is off by default, but some DLLs ship with it on and not every one accepts the setting.
+    let _ = lib.set_config(channel_id, &[(LOOPBACK, 0)]);
+    let _ = lib.clear_buffers(channel_id);
+
+    self.device_id = device_id;
+    self.channel_id = channel_id;
+    self.iso_channel_id = self.connect_iso15765(lib);
+    self.flow_filters.clear();
+    self.is_open = true;
+    Ok(())
+  }
+
+  fn close(&mut self) {
+    if let Some(lib) = &self.lib {
+      if self.is_open {
+        unsafe {
+          if let Some(iso_channel_id) = self.iso_channel_id {
+            let _ = (lib.disconnect)(iso_channel_id);
+          }
+          let _ = (lib.disconnect)(self.channel_id);
+          let _ = (lib.close)(self.device_id);
+        }
+      }
+    }
+    self.iso_channel_id = None;
+    self.flow_filters.clear();
+    self.is_open = false;
+  }
+
+  fn send(&mut self, frame: &Frame) -> Result<(), String> {
+    self.ensure_open()?;
+    self.release_flow_filters();
+    let lib = self.lib.as_ref().ok_or_else(|| "J2534 library not loaded".to_string())?;
+
+    let mut msg = PassThruMsg::default();
+    msg.protocol_id = PROTOCOL_CAN;
+    msg.tx_flags = if frame.is_extended { CAN_29BIT_ID } else { 0 };
+    msg.data_size = (4 + frame.data.len()) as u32;
+    msg.data[0..4].copy_from_slice(&frame.id.to_be_bytes());
+    msg.data[4..4 + frame.data.len()].copy_from_slice(&frame.data);
+
+    let mut num = 1u32;
+    let status = unsafe { (lib.write_msgs)(self.channel_id, &mut msg, &mut num, 100) };
+    lib.check("PassThruWriteMsgs", status)
+  }
+
+  fn recv(&mut self, timeout_ms: u64) -> Result<Option<Frame>, String> {
+    self.ensure_open()?;
+    let lib = self.lib.as_ref().ok_or_else(|| "J2534 library not loaded".to_string())?;
+
+    let mut msg = PassThruMsg::default();
+    let mut num = 1u32;
+    let status = unsafe { (lib.read_msgs)(self.channel_id, &mut msg, &mut num, timeout_ms as u32) };
+    if is_empty_read(status, num) {
+      return Ok(None);
+    }
+    lib.check("PassThruReadMsgs", status)?;
+    if msg.data_size < 4 || msg.rx_status & TX_MSG_TYPE != 0 {
+      return Ok(None);
+    }
+    let id = u32::from_be_bytes([msg.data[0], msg.data[1], msg.data[2], msg.data[3]]);
+    let payload = msg.data[4..msg.data_size as usize].to_vec();
+    Ok(Some(Frame {
+      id,
+      data: payload,
+      timestamp_ms: msg.timestamp as u128,
+      is_extended: (msg.rx_status & CAN_29BIT_ID) == CAN_29BIT_ID,
+    }))
+  }
+
+  fn set_filters(&mut self, filters: Vec<Filter>) -> Result<(), String> {
+    self.ensure_open()?;
+    let lib = self.lib.as_ref().ok_or_else(|| "J2534 library not loaded".to_string())?;
+
+    let Some(start_filter) = lib.start_filter else {
+      return Ok(());
+    };
+
+    for filter in filters {
+      // The 29-bit flag on mask and pattern selects which ID length the filter applies to.
+      let tx_flags = if filter.is_extended { CAN_29BIT_ID } else { 0 };
+      let mut mask = PassThruMsg::default();
+      mask.protocol_id = PROTOCOL_CAN;
+      mask.tx_flags = tx_flags;
+      mask.data_size = 4;
+      mask.data[0..4].copy_from_slice(&filter.mask.to_be_bytes());
+
+      let mut pattern = PassThruMsg::default();
+      pattern.protocol_id = PROTOCOL_CAN;
+      pattern.tx_flags = tx_flags;
+      pattern.data_size = 4;
+      pattern.data[0..4].copy_from_slice(&filter.id.to_be_bytes());
+
+      let mut flow = PassThruMsg::default();
+      let mut filter_id = 0u32;
+      let status = unsafe {
+        start_filter(self.channel_id, PASS_FILTER, &mut mask, &mut pattern, &mut flow, &mut filter_id)
+      };
+      lib.check("PassThruStartMsgFilter", status)?;
+    }
+
+    Ok(())
+  }
+
+  // An open channel is switched live and read back, since some adapters ignore rates they cannot do.
+  fn set_baud(&mut self, baud: u32) -> Result<(), String> {
+    if self.is_open {
+      let lib = self.lib.as_ref().ok_or_else(|| "J2534 library not loaded".to_string())?;
+      for channel_id in std::iter::once(self.channel_id).chain(self.iso_channel_id) {
+        lib.set_config(channel_id, &[(DATA_RATE, baud)])?;
+        let applied = lib.get_config(channel_id, DATA_RATE)?;
+        if applied != baud {
+          return Err(format!("Adapter kept {applied} bit/s instead of {baud}"));
+        }
+      }
+    }
+    self.baud = baud;
+    Ok(())
+  }
+
+  fn set_bus(&mut self, _bus: BusType) -> Result<(), String> {
+    Ok(())
+  }
+
+  // P2/P2* only bound the `recv_payload` wait the client passes in; J2534 04.04 has no ISO15765 parameter for
+  // them, and BS/STmin are fixed per session by `connect_iso15765`.
+  fn set_timing(&mut self, _timing: TimingConfig) -> Result<(), String> {
+    Ok(())
+  }
+
+  fn supports_payloads(&self) -> bool {
+    self.is_open && self.iso_channel_id.is_some()
+  }
+
+  fn send_payload(&mut self, address: EcuAddress, payload: &[u8]) -> Result<(), String> {
+    if payload.len() > 4095 {
+      return Err(format!("ISO-TP payload of {} bytes is too long", payload.len()));
+    }
+    self.ensure_flow_filter(address)?;
+    let (lib, channel_id) = self.iso_channel()?;
+    self.drain(channel_id);
+
+    let mut msg = iso15765_msg(iso15765_flags(address), address.tx_id, address.tx_prefix(), payload);
+    let mut num = 1u32;
+    let status = unsafe { (lib.write_msgs)(channel_id, &mut msg, &mut num, PAYLOAD_WRITE_TIMEOUT_MS) };
+    if J2534Status::from_code(status) != J2534Status::NoError {
+      // Whatever is left of a half-sent payload would go out ahead of the next request.
+      let _ = lib.ioctl(channel_id, CLEAR_TX_BUFFER, std::ptr::null_mut(), std::ptr::null_mut());
+      return lib.check("PassThruWriteMsgs (ISO15765)", status);
+    }
+    Ok(())
+  }
+
+  fn recv_payload(&mut self, address: EcuAddress, timeout_ms: u64) -> Result<Vec<u8>, String> {
+    let (lib, channel_id) = self.iso_channel()?;
+    let header_len = 4 + address.rx_prefix().map_or(0, |_| 1);
+    let mut deadline = Instant::now() + Duration::from_millis(timeout_ms);
+    let mut msg = PassThruMsg::default();
+
+    loop {
+      let now = Instant::now();
+      if now >= deadline {
+        return Err("ISO15765 timeout waiting for response".to_string());
+      }
+      let timeout = deadline.saturating_duration_since(now).as_millis().clamp(1, 250) as u32;
+      let mut num = 1u32;
+      let status = unsafe { (lib.read_msgs)(channel_id, &mut msg, &mut num, timeout) };
+      if is_empty_read(status, num) {
+        continue;
+      }
+      lib.check("PassThruReadMsgs (ISO15765)", status)?;
+      if msg.rx_status & (TX_MSG_TYPE | TX_DONE) != 0 || (msg.data_size as usize) < header_len {
+        continue;
+      }
+      let id = u32::from_be_bytes([msg.data[0], msg.data[1], msg.data[2], msg.data[3]]);
+      if id != address.rx_id || address.rx_prefix().is_some_and(|prefix| msg.data[4] != prefix) {
+        continue;
+      }
+      // The adapter is answering flow control and reassembling; the payload follows on completion.
+      if msg.rx_status & START_OF_MESSAGE != 0 {
+        deadline = deadline.max(Instant::now() + Duration::from_millis(RECEPTION_TIMEOUT_MS));
+        continue;
+      }
+      let payload = msg.data[header_len..msg.data_size as usize].to_vec();
+      self.drain(self.channel_id);
+      return Ok(payload);
+    }
+  }
+
+  fn battery_voltage(&mut self) -> Result<f32, String> {
+    self.with_device(|lib, device_id| lib.read_vbatt(device_id))
+  }
+
+  fn adapter_versions(&mut self) -> Result<AdapterVersions, String> {
+    self.with_device(|lib, device_id| lib.versions(device_id))
+  }
+}
+
+// Without a user choice `J2534_DLL` wins, then a vLinker driver, then whatever else is installed.
+fn find_j2534_dll() -> Result<PathBuf, String> {
+  let (devices, _) = list_j2534_devices();
+  devices
+    .iter()
+    .find(|device| {
+      device.source == J2534DeviceSource::Environment
+        || device.name.to_ascii_lowercase().contains("vlinker")
+        || device
+          .vendor
+          .as_deref()
+          .is_some_and(|vendor| vendor.to_ascii_lowercase().contains("vlinker"))
+    })
+    .or(devices.first())
+    .map(|device| PathBuf::from(&device.library))
+    .ok_or_else(|| {
+      "J2534 DLL not found. Install the adapter's PassThru driver or set J2534_DLL.".to_string()
+    })
+}
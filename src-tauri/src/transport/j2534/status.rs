@@ -0,0 +1,141 @@
+use std::fmt;
+
+// Return codes of every PassThru call (SAE J2534-1 v04.04).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum J2534Status {
+  NoError,
+  NotSupported,
+  InvalidChannelId,
+  InvalidProtocolId,
+  NullParameter,
+  InvalidIoctlValue,
+  InvalidFlags,
+  Failed,
+  DeviceNotConnected,
+  Timeout,
+  InvalidMsg,
+  InvalidTimeInterval,
+  ExceededLimit,
+  InvalidMsgId,
+  DeviceInUse,
+  InvalidIoctlId,
+  BufferEmpty,
+  BufferFull,
+  BufferOverflow,
+  PinInvalid,
+  ChannelInUse,
+  MsgProtocolId,
+  InvalidFilterId,
+  NoFlowControl,
+  NotUnique,
+  InvalidBaudrate,
+  InvalidDeviceId,
+  Other(u32),
+}
+
+impl J2534Status {
+  pub fn from_code(code: u32) -> Self {
+    match code {
+      0x00 => J2534Status::NoError,
+      0x01 => J2534Status::NotSupported,
+      0x02 => J2534Status::InvalidChannelId,
+      0x03 => J2534Status::InvalidProtocolId,
+      0x04 => J2534Status::NullParameter,
+      0x05 => J2534Status::InvalidIoctlValue,
+      0x06 => J2534Status::InvalidFlags,
+      0x07 => J2534Status::Failed,
+      0x08 => J2534Status::DeviceNotConnected,
+      0x09 => J2534Status::Timeout,
+      0x0A => J2534Status::InvalidMsg,
+      0x0B => J2534Status::InvalidTimeInterval,
+      0x0C => J2534Status::ExceededLimit,
+      0x0D => J2534Status::InvalidMsgId,
+      0x0E => J2534Status::DeviceInUse,
+      0x0F => J2534Status::InvalidIoctlId,
+      0x10 => J2534Status::BufferEmpty,
+      0x11 => J2534Status::BufferFull,
+      0x12 => J2534Status::BufferOverflow,
+      0x13 => J2534Status::PinInvalid,
+      0x14 => J2534Status::ChannelInUse,
+      0x15 => J2534Status::MsgProtocolId,
+      0x16 => J2534Status::InvalidFilterId,
+      0x17 => J2534Status::NoFlowControl,
+      0x18 => J2534Status::NotUnique,
+      0x19 => J2534Status::InvalidBaudrate,
+      0x1A => J2534Status::InvalidDeviceId,
+      other => J2534Status::Other(other),
+    }
+  }
+
+  pub fn code(&self) -> u32 {
+    match self {
+      J2534Status::NoError => 0x00,
+      J2534Status::NotSupported => 0x01,
+      J2534Status::InvalidChannelId => 0x02,
+      J2534Status::InvalidProtocolId => 0x03,
+      J2534Status::NullParameter => 0x04,
+      J2534Status::InvalidIoctlValue => 0x05,
+      J2534Status::InvalidFlags => 0x06,
+      J2534Status::Failed => 0x07,
+      J2534Status::DeviceNotConnected => 0x08,
+      J2534Status::Timeout => 0x09,
+      J2534Status::InvalidMsg => 0x0A,
+      J2534Status::InvalidTimeInterval => 0x0B,
+      J2534Status::ExceededLimit => 0x0C,
+      J2534Status::InvalidMsgId => 0x0D,
+      J2534Status::DeviceInUse => 0x0E,
+      J2534Status::InvalidIoctlId => 0x0F,
+      J2534Status::BufferEmpty => 0x10,
+      J2534Status::BufferFull => 0x11,
+      J2534Status::BufferOverflow => 0x12,
+      J2534Status::PinInvalid => 0x13,
+      J2534Status::ChannelInUse => 0x14,
+      J2534Status::MsgProtocolId => 0x15,
+      J2534Status::InvalidFilterId => 0x16,
+      J2534Status::NoFlowControl => 0x17,
+      J2534Status::NotUnique => 0x18,
+      J2534Status::InvalidBaudrate => 0x19,
+      J2534Status::InvalidDeviceId => 0x1A,
+      J2534Status::Other(code) => *code,
+    }
+  }
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      J2534Status::NoError => "STATUS_NOERROR",
+      J2534Status::NotSupported => "ERR_NOT_SUPPORTED",
+      J2534Status::InvalidChannelId => "ERR_INVALID_CHANNEL_ID",
+      J2534Status::InvalidProtocolId => "ERR_INVALID_PROTOCOL_ID",
+      J2534Status::NullParameter => "ERR_NULL_PARAMETER",
+      J2534Status::InvalidIoctlValue => "ERR_INVALID_IOCTL_VALUE",
+      J2534Status::InvalidFlags => "ERR_INVALID_FLAGS",
+      J2534Status::Failed => "ERR_FAILED",
+      J2534Status::DeviceNotConnected => "ERR_DEVICE_NOT_CONNECTED",
+      J2534Status::Timeout => "ERR_TIMEOUT",
+      J2534Status::InvalidMsg => "ERR_INVALID_MSG",
+      J2534Status::InvalidTimeInterval => "ERR_INVALID_TIME_INTERVAL",
+      J2534Status::ExceededLimit => "ERR_EXCEEDED_LIMIT",
+      J2534Status::InvalidMsgId => "ERR_INVALID_MSG_ID",
+      J2534Status::DeviceInUse => "ERR_DEVICE_IN_USE",
+      J2534Status::InvalidIoctlId => "ERR_INVALID_IOCTL_ID",
+      J2534Status::BufferEmpty => "ERR_BUFFER_EMPTY",
+      J2534Status::BufferFull => "ERR_BUFFER_FULL",
+      J2534Status::BufferOverflow => "ERR_BUFFER_OVERFLOW",
+      J2534Status::PinInvalid => "ERR_PIN_INVALID",
+      J2534Status::ChannelInUse => "ERR_CHANNEL_IN_USE",
+      J2534Status::MsgProtocolId => "ERR_MSG_PROTOCOL_ID",
+      J2534Status::InvalidFilterId => "ERR_INVALID_FILTER_ID",
+      J2534Status::NoFlowControl => "ERR_NO_FLOW_CONTROL",
+      J2534Status::NotUnique => "ERR_NOT_UNIQUE",
+      J2534Status::InvalidBaudrate => "ERR_INVALID_BAUDRATE",
+      J2534Status::InvalidDeviceId => "ERR_INVALID_DEVICE_ID",
+      J2534Status::Other(_) => "ERR_UNKNOWN",
+    }
+  }
+}
+
+impl fmt::Display for J2534Status {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{} (0x{:02X})", self.name(), self.code())
+  }
+}
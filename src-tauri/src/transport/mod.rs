@@ -1,12 +1,16 @@
 mod j2534;
 mod direct;
 mod sim;
+mod socketcan;
 mod types;
 
 pub use direct::VLinkerDirectTransport;
-pub use j2534::VLinkerFsJ2534Transport;
+pub use j2534::{list_j2534_devices, J2534Device, VLinkerFsJ2534Transport};
 pub use sim::SimTransport;
-pub use types::{BusType, Filter, Frame, TimingConfig};
+pub use socketcan::SocketCanTransport;
+pub use types::{AdapterVersions, BusType, Filter, Frame, TimingConfig};
+
+use crate::protocol::addressing::EcuAddress;
 
 pub trait Transport: Send {
   fn open(&mut self) -> Result<(), String>;
@@ -17,6 +21,29 @@ pub trait Transport: Send {
   fn set_baud(&mut self, baud: u32) -> Result<(), String>;
   fn set_bus(&mut self, bus: BusType) -> Result<(), String>;
   fn set_timing(&mut self, timing: TimingConfig) -> Result<(), String>;
+
+  // Adapters that run ISO-TP themselves exchange whole payloads; the rest leave segmentation to `IsoTpLink`.
+  fn supports_payloads(&self) -> bool {
+    false
+  }
+
+  fn send_payload(&mut self, address: EcuAddress, _payload: &[u8]) -> Result<(), String> {
+    Err(format!("Transport cannot send ISO-TP payloads to {}", address.label()))
+  }
+
+  fn recv_payload(&mut self, address: EcuAddress, _timeout_ms: u64) -> Result<Vec<u8>, String> {
+    Err(format!("Transport cannot receive ISO-TP payloads from {}", address.label()))
+  }
+
+  // Volts at the OBD battery pin, for adapters that can measure it.
+  fn battery_voltage(&mut self) -> Result<f32, String> {
+    Err("Transport cannot read battery voltage".to_string())
+  }
+
+  // Firmware, driver and API versions, for adapters that report them.
+  fn adapter_versions(&mut self) -> Result<AdapterVersions, String> {
+    Err("Transport does not report versions".to_string())
+  }
 }
 
 impl Transport for Box<dyn Transport> {
@@ -51,4 +78,24 @@ impl Transport for Box<dyn Transport> {
   fn set_timing(&mut self, timing: TimingConfig) -> Result<(), String> {
     self.as_mut().set_timing(timing)
   }
+
+  fn supports_payloads(&self) -> bool {
+    self.as_ref().supports_payloads()
+  }
+
+  fn send_payload(&mut self, address: EcuAddress, payload: &[u8]) -> Result<(), String> {
+    self.as_mut().send_payload(address, payload)
+  }
+
+  fn recv_payload(&mut self, address: EcuAddress, timeout_ms: u64) -> Result<Vec<u8>, String> {
+    self.as_mut().recv_payload(address, timeout_ms)
+  }
+
+  fn battery_voltage(&mut self) -> Result<f32, String> {
+    self.as_mut().battery_voltage()
+  }
+
+  fn adapter_versions(&mut self) -> Result<AdapterVersions, String> {
+    self.as_mut().adapter_versions()
+  }
 }
@@ -1,10 +1,14 @@
 use std::collections::VecDeque;
+use std::time::Duration;
+
+use crate::simulation::{SimulationSession, VirtualEcu};
 
 use super::{BusType, Filter, Frame, TimingConfig, Transport};
 
 pub struct SimTransport {
   open: bool,
   queue: VecDeque<Frame>,
+  ecus: Vec<VirtualEcu>,
 }
 
 impl SimTransport {
@@ -12,9 +16,20 @@ impl SimTransport {
     Self {
       open: false,
       queue: VecDeque::new(),
+      ecus: Vec::new(),
     }
   }
 
+  pub fn from_session(session: &SimulationSession) -> Self {
+    let mut transport = Self::new();
+    transport.ecus = session
+      .modules
+      .iter()
+      .map(|module| VirtualEcu::from_module(module, &session.vin))
+      .collect();
+    transport
+  }
+
   pub fn push_frame(&mut self, frame: Frame) {
     self.queue.push_back(frame);
   }
@@ -30,18 +45,30 @@ impl Transport for SimTransport {
     self.open = false;
   }
 
-  fn send(&mut self, _frame: &Frame) -> Result<(), String> {
+  fn send(&mut self, frame: &Frame) -> Result<(), String> {
     if !self.open {
       return Err("Sim transport not open".to_string());
     }
+    for ecu in self.ecus.iter_mut() {
+      let functional = ecu.functional_id() == Some(frame.id);
+      if functional || ecu.request_id == frame.id {
+        let responses = ecu.handle_frame(frame, functional);
+        self.queue.extend(responses);
+      }
+    }
     Ok(())
   }
 
-  fn recv(&mut self, _timeout_ms: u64) -> Result<Option<Frame>, String> {
+  fn recv(&mut self, timeout_ms: u64) -> Result<Option<Frame>, String> {
     if !self.open {
       return Err("Sim transport not open".to_string());
     }
-    Ok(self.queue.pop_front())
+    if let Some(frame) = self.queue.pop_front() {
+      return Ok(Some(frame));
+    }
+    // Virtual ECUs answer synchronously, so an empty queue behaves like a silent bus.
+    std::thread::sleep(Duration::from_millis(timeout_ms));
+    Ok(None)
   }
 
   fn set_filters(&mut self, _filters: Vec<Filter>) -> Result<(), String> {
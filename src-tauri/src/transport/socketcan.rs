@@ -0,0 +1,320 @@
+use std::path::Path;
+use std::process::Command;
+
+use super::{BusType, Filter, Frame, TimingConfig, Transport};
+
+pub const DEFAULT_INTERFACE: &str = "can0";
+
+pub struct SocketCanTransport {
+  interface: String,
+  baud: u32,
+  filters: Vec<Filter>,
+  fd: Option<i32>,
+}
+
+impl SocketCanTransport {
+  pub fn new(interface: Option<String>) -> Self {
+    let interface = interface
+      .or_else(|| std::env::var("SOCKETCAN_INTERFACE").ok())
+      .unwrap_or_else(|| DEFAULT_INTERFACE.to_string());
+    Self {
+      interface,
+      baud: 500_000,
+      filters: Vec::new(),
+      fd: None,
+    }
+  }
+
+  fn ensure_open(&self) -> Result<i32, String> {
+    self.fd.ok_or_else(|| "Transport not open".to_string())
+  }
+
+  fn is_virtual(&self) -> bool {
+    Path::new("/sys/devices/virtual/net").join(&self.interface).exists()
+  }
+
+  // Bit timing needs CAP_NET_ADMIN, so it is left to whoever brought the interface up; a mismatch is reported.
+  fn check_bitrate(&self) -> Result<(), String> {
+    // vcan interfaces have no bit timing.
+    if self.is_virtual() {
+      return Ok(());
+    }
+    match current_bitrate(&self.interface) {
+      Some(bitrate) if bitrate != self.baud => Err(format!(
+        "CAN interface {} runs at {bitrate} bit/s instead of {}; set it with `ip link set {} type can bitrate {}`",
+        self.interface, self.baud, self.interface, self.baud
+      )),
+      _ => Ok(()),
+    }
+  }
+}
+
+impl Transport for SocketCanTransport {
+  fn open(&mut self) -> Result<(), String> {
+    if self.fd.is_some() {
+      return Ok(());
+    }
+    let fd = sys::open(&self.interface)?;
+    if let Err(err) = self
+      .check_bitrate()
+      .and_then(|_| sys::apply_filters(fd, &self.filters))
+    {
+      sys::close(fd);
+      return Err(err);
+    }
+    self.fd = Some(fd);
+    Ok(())
+  }
+
+  fn close(&mut self) {
+    if let Some(fd) = self.fd.take() {
+      sys::close(fd);
+    }
+  }
+
+  fn send(&mut self, frame: &Frame) -> Result<(), String> {
+    let fd = self.ensure_open()?;
+    sys::write_frame(fd, frame)
+  }
+
+  fn recv(&mut self, timeout_ms: u64) -> Result<Option<Frame>, String> {
+    let fd = self.ensure_open()?;
+    sys::read_frame(fd, timeout_ms)
+  }
+
+  fn set_filters(&mut self, filters: Vec<Filter>) -> Result<(), String> {
+    if let Some(fd) = self.fd {
+      sys::apply_filters(fd, &filters)?;
+    }
+    self.filters = filters;
+    Ok(())
+  }
+
+  fn set_baud(&mut self, baud: u32) -> Result<(), String> {
+    self.baud = baud;
+    if self.fd.is_some() {
+      self.check_bitrate()?;
+    }
+    Ok(())
+  }
+
+  fn set_bus(&mut self, _bus: BusType) -> Result<(), String> {
+    Ok(())
+  }
+
+  fn set_timing(&mut self, _timing: TimingConfig) -> Result<(), String> {
+    Ok(())
+  }
+}
+
+impl Drop for SocketCanTransport {
+  fn drop(&mut self) {
+    self.close();
+  }
+}
+
+fn current_bitrate(interface: &str) -> Option<u32> {
+  let output = Command::new("ip")
+    .args(["-details", "link", "show", interface])
+    .output()
+    .ok()?;
+  let text = String::from_utf8_lossy(&output.stdout);
+  let mut words = text.split_whitespace();
+  while let Some(word) = words.next() {
+    if word == "bitrate" {
+      return words.next()?.parse().ok();
+    }
+  }
+  None
+}
+
+#[cfg(target_os = "linux")]
+mod sys {
+  use std::ffi::CString;
+  use std::mem::{size_of, zeroed};
+  use std::time::{SystemTime, UNIX_EPOCH};
+
+  use super::super::{Filter, Frame};
+
+  pub fn open(interface: &str) -> Result<i32, String> {
+    let name = CString::new(interface).map_err(|_| "Invalid CAN interface name".to_string())?;
+    let ifindex = unsafe { libc::if_nametoindex(name.as_ptr()) };
+    if ifindex == 0 {
+      return Err(format!("CAN interface {interface} not found"));
+    }
+
+    let fd = unsafe { libc::socket(libc::PF_CAN, libc::SOCK_RAW | libc::SOCK_CLOEXEC, libc::CAN_RAW) };
+    if fd < 0 {
+      return Err(format!("Failed to open CAN socket: {}", last_error()));
+    }
+
+    let mut addr: libc::sockaddr_can = unsafe { zeroed() };
+    addr.can_family = libc::AF_CAN as libc::sa_family_t;
+    addr.can_ifindex = ifindex as i32;
+    let status = unsafe {
+      libc::bind(
+        fd,
+        &addr as *const libc::sockaddr_can as *const libc::sockaddr,
+        size_of::<libc::sockaddr_can>() as libc::socklen_t,
+      )
+    };
+    if status < 0 {
+      let err = last_error();
+      close(fd);
+      return Err(format!("Failed to bind CAN socket to {interface}: {err}"));
+    }
+    Ok(fd)
+  }
+
+  pub fn close(fd: i32) {
+    unsafe {
+      libc::close(fd);
+    }
+  }
+
+  pub fn apply_filters(fd: i32, filters: &[Filter]) -> Result<(), String> {
+    // No filters means accept everything; an empty CAN_RAW_FILTER list would drop all traffic.
+    let mut raw = filters
+      .iter()
+      .map(|filter| {
+        let mut entry: libc::can_filter = unsafe { zeroed() };
+        if filter.is_extended {
+          entry.can_id = (filter.id & libc::CAN_EFF_MASK) | libc::CAN_EFF_FLAG;
+          entry.can_mask = (filter.mask & libc::CAN_EFF_MASK) | libc::CAN_EFF_FLAG | libc::CAN_RTR_FLAG;
+        } else {
+          entry.can_id = filter.id & libc::CAN_SFF_MASK;
+          entry.can_mask = (filter.mask & libc::CAN_SFF_MASK) | libc::CAN_EFF_FLAG | libc::CAN_RTR_FLAG;
+        }
+        entry
+      })
+      .collect::<Vec<_>>();
+    if raw.is_empty() {
+      raw.push(unsafe { zeroed() });
+    }
+
+    let status = unsafe {
+      libc::setsockopt(
+        fd,
+        libc::SOL_CAN_RAW,
+        libc::CAN_RAW_FILTER,
+        raw.as_ptr() as *const libc::c_void,
+        (raw.len() * size_of::<libc::can_filter>()) as libc::socklen_t,
+      )
+    };
+    if status < 0 {
+      return Err(format!("Failed to set CAN filters: {}", last_error()));
+    }
+    Ok(())
+  }
+
+  pub fn write_frame(fd: i32, frame: &Frame) -> Result<(), String> {
+    if frame.data.len() > libc::CAN_MAX_DLEN {
+      return Err(format!("CAN frame too long: {} bytes", frame.data.len()));
+    }
+    let mut raw: libc::can_frame = unsafe { zeroed() };
+    raw.can_id = if frame.is_extended {
+      (frame.id & libc::CAN_EFF_MASK) | libc::CAN_EFF_FLAG
+    } else {
+      frame.id & libc::CAN_SFF_MASK
+    };
+    raw.can_dlc = frame.data.len() as u8;
+    raw.data[..frame.data.len()].copy_from_slice(&frame.data);
+
+    let written = unsafe {
+      libc::write(
+        fd,
+        &raw as *const libc::can_frame as *const libc::c_void,
+        size_of::<libc::can_frame>(),
+      )
+    };
+    if written < 0 {
+      return Err(format!("CAN write failed: {}", last_error()));
+    }
+    Ok(())
+  }
+
+  pub fn read_frame(fd: i32, timeout_ms: u64) -> Result<Option<Frame>, String> {
+    let mut pfd = libc::pollfd {
+      fd,
+      events: libc::POLLIN,
+      revents: 0,
+    };
+    let ready = unsafe { libc::poll(&mut pfd, 1, timeout_ms.min(i32::MAX as u64) as i32) };
+    if ready < 0 {
+      let err = std::io::Error::last_os_error();
+      if err.kind() == std::io::ErrorKind::Interrupted {
+        return Ok(None);
+      }
+      return Err(format!("CAN poll failed: {err}"));
+    }
+    if ready == 0 {
+      return Ok(None);
+    }
+
+    let mut raw: libc::can_frame = unsafe { zeroed() };
+    let read = unsafe {
+      libc::read(
+        fd,
+        &mut raw as *mut libc::can_frame as *mut libc::c_void,
+        size_of::<libc::can_frame>(),
+      )
+    };
+    if read < 0 {
+      return Err(format!("CAN read failed: {}", last_error()));
+    }
+    if (read as usize) < size_of::<libc::can_frame>() {
+      return Ok(None);
+    }
+    if raw.can_id & (libc::CAN_RTR_FLAG | libc::CAN_ERR_FLAG) != 0 {
+      return Ok(None);
+    }
+
+    let is_extended = raw.can_id & libc::CAN_EFF_FLAG != 0;
+    let id = if is_extended {
+      raw.can_id & libc::CAN_EFF_MASK
+    } else {
+      raw.can_id & libc::CAN_SFF_MASK
+    };
+    let len = usize::min(raw.can_dlc as usize, libc::CAN_MAX_DLEN);
+    let timestamp_ms = SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map(|elapsed| elapsed.as_millis())
+      .unwrap_or(0);
+
+    Ok(Some(Frame {
+      id,
+      data: raw.data[..len].to_vec(),
+      timestamp_ms,
+      is_extended,
+    }))
+  }
+
+  fn last_error() -> std::io::Error {
+    std::io::Error::last_os_error()
+  }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod sys {
+  use super::super::{Filter, Frame};
+
+  const UNSUPPORTED: &str = "SocketCAN is only available on Linux";
+
+  pub fn open(_interface: &str) -> Result<i32, String> {
+    Err(UNSUPPORTED.to_string())
+  }
+
+  pub fn close(_fd: i32) {}
+
+  pub fn apply_filters(_fd: i32, _filters: &[Filter]) -> Result<(), String> {
+    Err(UNSUPPORTED.to_string())
+  }
+
+  pub fn write_frame(_fd: i32, _frame: &Frame) -> Result<(), String> {
+    Err(UNSUPPORTED.to_string())
+  }
+
+  pub fn read_frame(_fd: i32, _timeout_ms: u64) -> Result<Option<Frame>, String> {
+    Err(UNSUPPORTED.to_string())
+  }
+}
@@ -26,3 +26,11 @@ pub struct TimingConfig {
   pub p2_ms: u64,
   pub p2_star_ms: u64,
 }
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdapterVersions {
+  pub firmware: String,
+  pub dll: String,
+  pub api: String,
+}
@@ -0,0 +1,306 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::OnceLock;
+
+use chrono::{Datelike, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::app_state::VehicleInfo;
+
+const WMI_TABLE: &str = include_str!("../../data/vin/wmi.json");
+const VDS_PATTERNS: &[&str] = &[
+  include_str!("../../data/vin/ford.json"),
+  include_str!("../../data/vin/ram.json"),
+];
+
+// Model-year codes in position 10, starting at 1980; the list repeats every 30 years.
+const YEAR_CODES: &[u8] = b"ABCDEFGHJKLMNPRSTVWXY123456789";
+const CHECK_WEIGHTS: [u32; 17] = [8, 7, 6, 5, 4, 3, 2, 10, 0, 9, 8, 7, 6, 5, 4, 3, 2];
+// Vehicles this old cannot answer UDS over CAN, so a VIN read from one belongs to the later cycle.
+const CAN_DIAGNOSTICS_YEAR: u16 = 2008;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CheckDigit {
+  Valid,
+  Invalid,
+  // Outside North America and China position 9 need not be a check digit.
+  NotUsed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VinInfo {
+  pub vin: String,
+  pub wmi: String,
+  pub manufacturer: Option<String>,
+  pub make: Option<String>,
+  pub country: Option<String>,
+  pub year: Option<u16>,
+  pub check_digit: CheckDigit,
+  // VDS fields from the make's pattern file: model, drive, body, engine, plant.
+  pub fields: BTreeMap<String, String>,
+}
+
+impl VinInfo {
+  pub fn vehicle(&self) -> VehicleInfo {
+    let mut details = self
+      .fields
+      .iter()
+      .filter(|(name, _)| !matches!(name.as_str(), "model" | "trim"))
+      .map(|(name, value)| (name.clone(), value.clone()))
+      .collect::<BTreeMap<_, _>>();
+    if let Some(manufacturer) = &self.manufacturer {
+      details.insert("manufacturer".to_string(), manufacturer.clone());
+    }
+    if let Some(country) = &self.country {
+      details.insert("country".to_string(), country.clone());
+    }
+    VehicleInfo {
+      make: self.make.clone().unwrap_or_else(|| "Unknown".to_string()),
+      model: self
+        .fields
+        .get("model")
+        .cloned()
+        .unwrap_or_else(|| "Unknown".to_string()),
+      year: self
+        .year
+        .map_or_else(|| "Unknown".to_string(), |year| year.to_string()),
+      trim: self.fields.get("trim").cloned().unwrap_or_default(),
+      details,
+      check_digit_valid: match self.check_digit {
+        CheckDigit::Valid => Some(true),
+        CheckDigit::Invalid => Some(false),
+        CheckDigit::NotUsed => None,
+      },
+    }
+  }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WmiTable {
+  regions: Vec<Region>,
+  manufacturers: Vec<Manufacturer>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Region {
+  prefix: String,
+  country: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Manufacturer {
+  wmi: String,
+  manufacturer: String,
+  make: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PatternFile {
+  makes: Vec<String>,
+  patterns: Vec<VdsPattern>,
+}
+
+// `code` matched at the 1-based `position` sets every entry of `values`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VdsPattern {
+  position: usize,
+  code: String,
+  #[serde(default)]
+  wmis: Vec<String>,
+  #[serde(default)]
+  min_year: Option<u16>,
+  #[serde(default)]
+  max_year: Option<u16>,
+  values: BTreeMap<String, String>,
+}
+
+impl VdsPattern {
+  fn matches(&self, vin: &str, wmi: &str, year: Option<u16>) -> bool {
+    let start = self.position.saturating_sub(1);
+    vin.get(start..start + self.code.len()) == Some(self.code.as_str())
+      && (self.wmis.is_empty() || self.wmis.iter().any(|candidate| candidate == wmi))
+      && year.map_or(self.min_year.is_none() && self.max_year.is_none(), |year| {
+        self.min_year.is_none_or(|min| year >= min) && self.max_year.is_none_or(|max| year <= max)
+      })
+  }
+}
+
+#[derive(Debug, Default)]
+pub struct VinDecoder {
+  regions: Vec<Region>,
+  manufacturers: HashMap<String, Manufacturer>,
+  // Keyed by lowercase make.
+  patterns: HashMap<String, Vec<VdsPattern>>,
+}
+
+impl VinDecoder {
+  pub fn builtin() -> Self {
+    let mut decoder = Self::default();
+    if let Ok(table) = serde_json::from_str::<WmiTable>(WMI_TABLE) {
+      decoder.regions = table.regions;
+      decoder.manufacturers = table
+        .manufacturers
+        .into_iter()
+        .map(|manufacturer| (manufacturer.wmi.clone(), manufacturer))
+        .collect();
+    }
+    for source in VDS_PATTERNS {
+      if let Ok(file) = serde_json::from_str::<PatternFile>(source) {
+        for make in file.makes {
+          decoder
+            .patterns
+            .entry(make.to_ascii_lowercase())
+            .or_default()
+            .extend(file.patterns.iter().cloned());
+        }
+      }
+    }
+    decoder
+  }
+
+  // None unless the VIN is 17 characters without I, O or Q.
+  pub fn decode(&self, vin: &str) -> Option<VinInfo> {
+    let vin = vin.trim().to_ascii_uppercase();
+    if vin.len() != 17 || !vin.bytes().all(|byte| transliterate(byte).is_some()) {
+      return None;
+    }
+    let bytes = vin.as_bytes();
+    let wmi = vin[0..3].to_string();
+    let manufacturer = self.manufacturers.get(&wmi);
+    let year = model_year(bytes, Utc::now().year() as u16);
+
+    let check_digit = if check_digit(bytes) == Some(bytes[8]) {
+      CheckDigit::Valid
+    } else if matches!(bytes[0], b'1'..=b'5' | b'L') {
+      CheckDigit::Invalid
+    } else {
+      CheckDigit::NotUsed
+    };
+
+    let mut fields = BTreeMap::new();
+    let patterns = manufacturer
+      .and_then(|manufacturer| self.patterns.get(&manufacturer.make.to_ascii_lowercase()));
+    for pattern in patterns.into_iter().flatten() {
+      if pattern.matches(&vin, &wmi, year) {
+        fields.extend(pattern.values.clone());
+      }
+    }
+
+    Some(VinInfo {
+      country: self
+        .regions
+        .iter()
+        .filter(|region| vin.starts_with(&region.prefix))
+        .max_by_key(|region| region.prefix.len())
+        .map(|region| region.country.clone()),
+      manufacturer: manufacturer.map(|manufacturer| manufacturer.manufacturer.clone()),
+      make: manufacturer.map(|manufacturer| manufacturer.make.clone()),
+      vin,
+      wmi,
+      year,
+      check_digit,
+      fields,
+    })
+  }
+}
+
+pub fn decode_vin(vin: &str) -> Option<VinInfo> {
+  static DECODER: OnceLock<VinDecoder> = OnceLock::new();
+  DECODER.get_or_init(VinDecoder::builtin).decode(vin)
+}
+
+fn transliterate(byte: u8) -> Option<u32> {
+  Some(match byte {
+    b'0'..=b'9' => (byte - b'0') as u32,
+    b'A'..=b'H' => (byte - b'A') as u32 + 1,
+    b'J'..=b'N' => (byte - b'J') as u32 + 1,
+    b'P' => 7,
+    b'R' => 9,
+    b'S'..=b'Z' => (byte - b'S') as u32 + 2,
+    _ => return None,
+  })
+}
+
+fn check_digit(vin: &[u8]) -> Option<u8> {
+  let sum = vin
+    .iter()
+    .zip(CHECK_WEIGHTS)
+    .map(|(byte, weight)| transliterate(*byte).map(|value| value * weight))
+    .sum::<Option<u32>>()?;
+  Some(match sum % 11 {
+    10 => b'X',
+    digit => b'0' + digit as u8,
+  })
+}
+
+// North American light vehicles put a letter in position 7 from 2010 on and a digit before that.
+// Heavy trucks are exempt, so a digit only picks the 1980s-2000s cycle when that year is plausible.
+fn model_year(vin: &[u8], current_year: u16) -> Option<u16> {
+  let index = YEAR_CODES.iter().position(|code| *code == vin[9])? as u16;
+  let earlier = 1980 + index;
+  let later = earlier + 30;
+  if later > current_year + 1 {
+    return Some(earlier);
+  }
+  if matches!(vin[0], b'1'..=b'5') {
+    if vin[6].is_ascii_alphabetic() || earlier < CAN_DIAGNOSTICS_YEAR {
+      return Some(later);
+    }
+    return Some(earlier);
+  }
+  Some(later)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn computes_check_digit() {
+    assert_eq!(check_digit(b"1M8GDM9AXKP042788"), Some(b'X'));
+    assert_eq!(check_digit(b"1FT7W2BT6KED12345"), Some(b'6'));
+    assert_eq!(
+      decode_vin("1M8GDM9AXKP042788").unwrap().check_digit,
+      CheckDigit::Valid
+    );
+    assert_eq!(
+      decode_vin("1M8GDM9A1KP042788").unwrap().check_digit,
+      CheckDigit::Invalid
+    );
+  }
+
+  #[test]
+  fn picks_the_model_year_cycle() {
+    // A digit in position 7 would mean 1989, but that car could not answer over CAN.
+    assert_eq!(model_year(b"1M8GDM9AXKP042788", 2026), Some(2019));
+    assert_eq!(model_year(b"2T1BURHE0AC123456", 2026), Some(2010));
+    // 2030 is still in the future.
+    assert_eq!(model_year(b"WVWZZZ1JZYW000001", 2026), Some(2000));
+    assert_eq!(model_year(b"1M8GDM9AXUP042788", 2026), None);
+  }
+
+  #[test]
+  fn bundled_tables_parse() {
+    let table = serde_json::from_str::<WmiTable>(WMI_TABLE).unwrap();
+    assert!(!table.regions.is_empty() && !table.manufacturers.is_empty());
+    for source in VDS_PATTERNS {
+      let file = serde_json::from_str::<PatternFile>(source).unwrap();
+      assert!(!file.makes.is_empty() && !file.patterns.is_empty());
+    }
+    let info = decode_vin("1FT7W2BT6KED12345").unwrap();
+    assert_eq!(info.make.as_deref(), Some("Ford"));
+    assert!(!info.fields.is_empty());
+  }
+
+  #[test]
+  fn rejects_malformed_vins() {
+    assert!(decode_vin("1M8GDM9AXKP04278").is_none());
+    assert!(decode_vin("1M8GDM9AXKP04278O").is_none());
+  }
+}
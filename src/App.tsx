@@ -1,13 +1,23 @@
 import { AnimatePresence, motion } from "framer-motion";
 import { type CSSProperties, useEffect, useMemo, useState } from "react";
+import { save } from "@tauri-apps/api/dialog";
 import { invoke } from "@tauri-apps/api/tauri";
 
 import TopologyTree from "./components/TopologyTree";
 import ModuleWorkspace from "./components/ModuleWorkspace";
+import BaselinePanel from "./components/BaselinePanel";
 import DtcList from "./components/DtcList";
 import LogsDrawer from "./components/LogsDrawer";
 import { Badge, Button, Card, Pill, SectionTitle } from "./components/ui";
-import type { AdapterStatus, ModuleInfo, TransportMode } from "./state/types";
+import type {
+  AdapterStatus,
+  DiagnosticSession,
+  J2534DeviceList,
+  ModuleInfo,
+  ProfileList,
+  TopologyFormat,
+  TransportMode,
+} from "./state/types";
 import { useAppState } from "./state/useAppState";
 
 const fade = {
@@ -28,6 +38,9 @@ export default function App() {
   const [logsOpen, setLogsOpen] = useState(false);
   const [adapterStatus, setAdapterStatus] = useState<AdapterStatus | null>(null);
   const [connectMode, setConnectMode] = useState<TransportMode>("simulation");
+  const [nativeIsoTp, setNativeIsoTp] = useState(false);
+  const [profiles, setProfiles] = useState<ProfileList | null>(null);
+  const [devices, setDevices] = useState<J2534DeviceList | null>(null);
 
   const modules = snapshot.modules;
   const selectedModule = modules.find((module) => module.id === selectedModuleId) ?? null;
@@ -44,6 +57,18 @@ export default function App() {
       .catch(() => null);
   }, []);
 
+  useEffect(() => {
+    invoke<J2534DeviceList>("list_j2534_devices")
+      .then(setDevices)
+      .catch(() => null);
+  }, []);
+
+  useEffect(() => {
+    invoke<ProfileList>("list_profiles")
+      .then(setProfiles)
+      .catch(() => null);
+  }, []);
+
   useEffect(() => {
     if (snapshot.phase === "disconnected") {
       setConnectMode(snapshot.transport);
@@ -58,7 +83,26 @@ export default function App() {
 
   const handleConnect = () => {
     const simulationPath = connectMode === "simulation" ? "samples/f250_session.json" : null;
-    invoke("start_scan", { mode: connectMode, simulation_path: simulationPath });
+    invoke("start_scan", {
+      mode: connectMode,
+      simulation_path: simulationPath,
+      native_isotp: connectMode === "j2534" && nativeIsoTp,
+    });
+  };
+
+  const handleSelectDevice = (library: string | null) => {
+    invoke("select_j2534_device", { library })
+      .then(() => {
+        setDevices((current) => (current ? { ...current, selected: library } : current));
+        return invoke<AdapterStatus>("get_adapter_status").then(setAdapterStatus);
+      })
+      .catch(() => null);
+  };
+
+  const handleSelectProfile = (profileId: string | null) => {
+    invoke("select_profile", { profile_id: profileId })
+      .then(() => setProfiles((current) => (current ? { ...current, selected: profileId } : current)))
+      .catch(() => null);
   };
 
   const handleClearAll = () => {
@@ -69,6 +113,24 @@ export default function App() {
     invoke("clear_dtcs", { module_id: module.id });
   };
 
+  const handleSessionChange = (module: ModuleInfo, session: DiagnosticSession) => {
+    invoke("set_diagnostic_session", { module_id: module.id, session });
+  };
+
+  const handleUnlock = (module: ModuleInfo, level: number) => {
+    invoke("security_access", { module_id: module.id, level });
+  };
+
+  const handleExportTopology = async (format: TopologyFormat) => {
+    const destination = await save({
+      title: "Export Topology",
+      defaultPath: format === "dot" ? "vyntool-topology.dot" : "vyntool-topology.json",
+    });
+    if (destination) {
+      invoke("export_topology", { destination, format });
+    }
+  };
+
   return (
     <div className="app-shell">
       <header className="topbar">
@@ -81,7 +143,21 @@ export default function App() {
             {snapshot.adapterConnected ? "Adapter connected" : "Adapter offline"}
           </Badge>
           <Badge tone="info">{snapshot.transport === "simulation" ? "Simulation" : "Live"}</Badge>
-          {snapshot.vin && <Badge tone="neutral">VIN {snapshot.vin}</Badge>}
+          {snapshot.batteryVoltage != null && (
+            <Badge tone={snapshot.batteryVoltage < 12 ? "warning" : "neutral"}>
+              {snapshot.batteryVoltage.toFixed(1)} V
+            </Badge>
+          )}
+          {snapshot.vin && (
+            <Badge tone={snapshot.vehicle?.checkDigitValid === false ? "warning" : "neutral"}>
+              VIN {snapshot.vin}
+            </Badge>
+          )}
+          {snapshot.vehicle && (
+            <Badge tone="neutral">
+              {snapshot.vehicle.year} {snapshot.vehicle.make} {snapshot.vehicle.model}
+            </Badge>
+          )}
           <Button variant="ghost" onClick={() => setLogsOpen(true)}>
             Logs
           </Button>
@@ -114,7 +190,26 @@ export default function App() {
                       >
                         Live Adapter
                       </Pill>
+                      <Pill
+                        active={connectMode === "direct"}
+                        onClick={() => setConnectMode("direct")}
+                      >
+                        Serial
+                      </Pill>
+                      <Pill
+                        active={connectMode === "socketCan"}
+                        onClick={() => setConnectMode("socketCan")}
+                      >
+                        SocketCAN
+                      </Pill>
                     </div>
+                    {connectMode === "j2534" && (
+                      <div className="mode-toggle">
+                        <Pill active={nativeIsoTp} onClick={() => setNativeIsoTp(!nativeIsoTp)}>
+                          Adapter ISO-TP
+                        </Pill>
+                      </div>
+                    )}
                   </div>
                 </div>
 
@@ -128,11 +223,59 @@ export default function App() {
                       <div className="status-sub">
                         {adapterStatus?.message ?? "Checking J2534 driver"}
                       </div>
+                      {adapterStatus?.batteryVoltage != null && (
+                        <div className="status-sub">
+                          Battery {adapterStatus.batteryVoltage.toFixed(1)} V
+                        </div>
+                      )}
+                      {adapterStatus?.versions && (
+                        <div className="status-sub">
+                          Firmware {adapterStatus.versions.firmware} · DLL{" "}
+                          {adapterStatus.versions.dll} · API {adapterStatus.versions.api}
+                        </div>
+                      )}
+                      {adapterStatus?.deviceError && (
+                        <div className="status-sub">{adapterStatus.deviceError}</div>
+                      )}
                     </div>
                     <Badge tone={adapterStatus?.available ? "success" : "warning"}>
                       {adapterStatus?.available ? "J2534 OK" : "Install driver"}
                     </Badge>
                   </div>
+                  {devices && devices.devices.length > 1 && (
+                    <div className="profile-pills">
+                      <Pill active={!devices.selected} onClick={() => handleSelectDevice(null)}>
+                        Auto
+                      </Pill>
+                      {devices.devices.map((device) => (
+                        <Pill
+                          key={device.library}
+                          active={devices.selected === device.library}
+                          onClick={() => handleSelectDevice(device.library)}
+                        >
+                          {device.name}
+                        </Pill>
+                      ))}
+                    </div>
+                  )}
+                </Card>
+
+                <Card className="connect-card">
+                  <SectionTitle>Discovery profile</SectionTitle>
+                  <div className="profile-pills">
+                    <Pill active={!profiles?.selected} onClick={() => handleSelectProfile(null)}>
+                      Auto (VIN)
+                    </Pill>
+                    {profiles?.profiles.map((profile) => (
+                      <Pill
+                        key={profile.id}
+                        active={profiles.selected === profile.id}
+                        onClick={() => handleSelectProfile(profile.id)}
+                      >
+                        {profile.name}
+                      </Pill>
+                    ))}
+                  </div>
                 </Card>
 
                 <Card className="connect-card">
@@ -235,7 +378,17 @@ export default function App() {
 
               <div className="ready-grid">
                 <Card className="topology-card">
-                  <SectionTitle>Topology</SectionTitle>
+                  <div className="topology-header">
+                    <SectionTitle>Topology</SectionTitle>
+                    <div className="summary-actions">
+                      <Button variant="ghost" onClick={() => handleExportTopology("json")}>
+                        JSON
+                      </Button>
+                      <Button variant="ghost" onClick={() => handleExportTopology("dot")}>
+                        DOT
+                      </Button>
+                    </div>
+                  </div>
                   {snapshot.topology.buses.length === 0 ? (
                     <div className="muted">No topology data available yet.</div>
                   ) : (
@@ -254,6 +407,8 @@ export default function App() {
                       module={selectedModule}
                       dtcs={snapshot.dtcs[selectedModule.id] ?? []}
                       onClear={() => handleClearModule(selectedModule)}
+                      onSessionChange={(session) => handleSessionChange(selectedModule, session)}
+                      onUnlock={(level) => handleUnlock(selectedModule, level)}
                     />
                   ) : (
                     <Card>
@@ -275,6 +430,18 @@ export default function App() {
                     emptyMessage="No DTCs stored across all modules."
                   />
                 </Card>
+
+                <Card className="dtc-card">
+                  <SectionTitle>Expected build</SectionTitle>
+                  <BaselinePanel
+                    report={snapshot.baseline}
+                    defaultName={
+                      snapshot.vehicle
+                        ? `${snapshot.vehicle.year} ${snapshot.vehicle.make} ${snapshot.vehicle.model}`
+                        : snapshot.vin ?? "Known-good vehicle"
+                    }
+                  />
+                </Card>
               </div>
             </motion.div>
           )}
@@ -0,0 +1,75 @@
+import { invoke } from "@tauri-apps/api/tauri";
+import { useState } from "react";
+
+import type { BaselineEntry, BaselineReport, BaselineStatus } from "../state/types";
+import { Badge, Button } from "./ui";
+
+interface Props {
+  report?: BaselineReport | null;
+  defaultName: string;
+}
+
+const statusLabels: Record<BaselineStatus, string> = {
+  match: "As expected",
+  missing: "Missing",
+  extra: "Not in baseline",
+  unexpectedAddress: "Unexpected address",
+  unexpectedBus: "Unexpected bus",
+  identificationChanged: "Identification changed",
+};
+
+export default function BaselinePanel({ report, defaultName }: Props) {
+  const [saved, setSaved] = useState<string | null>(null);
+  const deviations = report?.entries.filter((entry) => entry.status !== "match") ?? [];
+
+  const saveBaseline = () => {
+    invoke("save_baseline", { name: defaultName })
+      .then(() => setSaved(`Saved as "${defaultName}"`))
+      .catch((error) => setSaved(String(error)));
+  };
+
+  return (
+    <div className="dtc-list">
+      <div className="dtc-header">
+        <h4>{report ? `Baseline: ${report.name}` : "No baseline for this vehicle"}</h4>
+        {report && (
+          <Badge tone={deviations.length ? "warning" : "success"}>
+            {deviations.length ? `${deviations.length} deviations` : "Matches"}
+          </Badge>
+        )}
+      </div>
+      {deviations.length > 0 && (
+        <div className="dtc-table">
+          {deviations.map((entry) => (
+            <div key={`${entry.name}-${entry.moduleId ?? ""}`} className="dtc-row">
+              <div>
+                <div className="dtc-code">{entry.name}</div>
+                <div className="dtc-desc">{statusLabels[entry.status]}</div>
+                <div className="dtc-detail">{describeEntry(entry)}</div>
+                {entry.identification.map((mismatch) => (
+                  <div key={mismatch.did} className="dtc-detail">
+                    {mismatch.did.toString(16).toUpperCase().padStart(4, "0")}: expected {mismatch.expected},
+                    found {mismatch.found ?? "nothing"}
+                  </div>
+                ))}
+              </div>
+            </div>
+          ))}
+        </div>
+      )}
+      <div className="summary-actions">
+        <Button variant="outline" onClick={saveBaseline}>
+          Save as baseline
+        </Button>
+        {saved && <span className="module-meta">{saved}</span>}
+      </div>
+    </div>
+  );
+}
+
+function describeEntry(entry: BaselineEntry) {
+  const expected = entry.expectedAddress && `${entry.expectedAddress} on ${entry.expectedBus}`;
+  const found = entry.foundAddress && `${entry.foundAddress} on ${entry.foundBus}`;
+  if (expected && found && expected !== found) return `Expected ${expected}, found ${found}`;
+  return expected ?? found ?? "";
+}
@@ -21,13 +21,31 @@ export default function DtcList({ title, dtcs, emptyMessage }: Props) {
       ) : (
         <div className="dtc-table">
           {dtcs.map((dtc) => (
-            <div key={dtc.code} className="dtc-row">
+            <div key={`${dtc.moduleId ?? ""}-${dtc.code}`} className="dtc-row">
               <div>
                 <div className="dtc-code">{dtc.code}</div>
                 <div className="dtc-desc">{dtc.description}</div>
                 {dtc.moduleId && <div className="dtc-module">{dtc.moduleId}</div>}
+                {dtc.snapshots?.map((snapshot) => (
+                  <div key={snapshot.recordNumber} className="dtc-detail">
+                    Snapshot {snapshot.recordNumber}:{" "}
+                    {snapshot.values.map((value) => `${value.name} ${value.value}`).join(" · ")}
+                    {snapshot.undecoded && ` · raw ${snapshot.undecoded}`}
+                  </div>
+                ))}
+                {dtc.extendedData?.length > 0 && (
+                  <div className="dtc-detail">
+                    {dtc.extendedData
+                      .map((record) => `${record.name} ${record.value || record.raw}`)
+                      .join(" · ")}
+                  </div>
+                )}
+              </div>
+              <div className="dtc-badges">
+                {dtc.statusFlags?.warningIndicatorRequested && <Badge tone="danger">MIL</Badge>}
+                {dtc.permanent && <Badge tone="info">Permanent</Badge>}
+                <Badge tone="warning">{dtc.status}</Badge>
               </div>
-              <Badge tone="warning">{dtc.status}</Badge>
             </div>
           ))}
         </div>
@@ -1,6 +1,6 @@
 import { useMemo, useState } from "react";
 
-import type { DtcInfo, ModuleInfo } from "../state/types";
+import type { DiagnosticSession, DtcInfo, ModuleInfo, VinCheck } from "../state/types";
 import DtcList from "./DtcList";
 import { Badge, Button } from "./ui";
 
@@ -8,20 +8,35 @@ interface Props {
   module: ModuleInfo;
   dtcs: DtcInfo[];
   onClear: () => void;
+  onSessionChange: (session: DiagnosticSession) => void;
+  onUnlock: (level: number) => void;
 }
 
 const tabs = ["Codes", "Live Data", "Actuations", "Info"] as const;
 
-export default function ModuleWorkspace({ module, dtcs, onClear }: Props) {
+export default function ModuleWorkspace({ module, dtcs, onClear, onSessionChange, onUnlock }: Props) {
   const [activeTab, setActiveTab] = useState<(typeof tabs)[number]>("Codes");
 
   const infoLines = useMemo(
     () => [
       { label: "TX ID", value: `0x${module.txId.toString(16).toUpperCase()}` },
       { label: "RX ID", value: `0x${module.rxId.toString(16).toUpperCase()}` },
+      { label: "Addressing", value: formatAddressing(module) },
       { label: "Bus", value: module.bus },
       { label: "Category", value: module.category },
       { label: "Status", value: module.dtcCount > 0 ? "Attention" : "OK" },
+      { label: "Session", value: module.session ?? "default" },
+      {
+        label: "Security",
+        value: module.securityLevel
+          ? `Unlocked (level 0x${module.securityLevel.toString(16).toUpperCase().padStart(2, "0")})`
+          : "Locked",
+      },
+      ...(module.identification ?? []).map((entry) => ({ label: entry.name, value: entry.value })),
+      ...(module.vinChecks ?? []).map((check) => ({
+        label: `VIN (${check.did.toString(16).toUpperCase().padStart(4, "0")})`,
+        value: formatVinCheck(check),
+      })),
     ],
     [module]
   );
@@ -37,6 +52,17 @@ export default function ModuleWorkspace({ module, dtcs, onClear }: Props) {
           <Badge tone={module.dtcCount > 0 ? "warning" : "success"}>
             {module.dtcCount > 0 ? `${module.dtcCount} DTCs` : "OK"}
           </Badge>
+          <Button
+            variant="ghost"
+            onClick={() => onSessionChange(module.session === "extended" ? "default" : "extended")}
+          >
+            {module.session === "extended" ? "Default Session" : "Extended Session"}
+          </Button>
+          {module.session === "extended" && !module.securityLevel && (
+            <Button variant="ghost" onClick={() => onUnlock(1)}>
+              Unlock
+            </Button>
+          )}
           <Button variant="outline" onClick={onClear}>
             Clear This Module
           </Button>
@@ -85,3 +111,30 @@ export default function ModuleWorkspace({ module, dtcs, onClear }: Props) {
     </div>
   );
 }
+
+function formatAddressing(module: ModuleInfo) {
+  const addressing = module.addressing ?? { mode: "normal" };
+  switch (addressing.mode) {
+    case "normalFixed":
+      return "29-bit normal fixed";
+    case "extended":
+      return `Extended (target 0x${addressing.target.toString(16).toUpperCase().padStart(2, "0")})`;
+    default:
+      return module.txId > 0x7ff ? "29-bit normal" : "11-bit normal";
+  }
+}
+
+function formatVinCheck(check: VinCheck) {
+  switch (check.status) {
+    case "match":
+      return `${check.vin} (matches)`;
+    case "mismatch":
+      return `${check.vin} (does not match vehicle)`;
+    case "blank":
+      return "Not programmed";
+    case "notSupported":
+      return "Not supported";
+    default:
+      return "No response";
+  }
+}
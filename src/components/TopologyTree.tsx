@@ -12,6 +12,7 @@ interface Props {
 
 export default function TopologyTree({ topology, modules, selectedId, onSelect }: Props) {
   const moduleMap = new Map(modules.map((module) => [module.id, module]));
+  const nodeMap = new Map((topology.nodes ?? []).map((node) => [node.id, node]));
 
   return (
     <div className="topology-tree">
@@ -20,7 +21,16 @@ export default function TopologyTree({ topology, modules, selectedId, onSelect }
           <div className="bus-header">
             <span className="bus-dot" />
             <span className="bus-title">{bus.name}</span>
+            {bus.baudRate && <span className="module-meta">{bus.baudRate / 1000} kbit/s</span>}
             <Badge tone="info">{bus.modules.length} nodes</Badge>
+            {bus.tester && <Badge tone="neutral">Tester</Badge>}
+            {(topology.links ?? [])
+              .filter((link) => link.to === bus.name)
+              .map((link) => (
+                <Badge key={link.gateway} tone="neutral">
+                  via {moduleMap.get(link.gateway)?.name ?? link.gateway}
+                </Badge>
+              ))}
           </div>
           <div className="bus-list">
             {bus.modules.map((moduleId) => {
@@ -35,12 +45,24 @@ export default function TopologyTree({ topology, modules, selectedId, onSelect }
                 >
                   <div className="module-node-main">
                     <span className="module-name">{module.name}</span>
-                    <span className="module-meta">{module.category}</span>
+                    <span className="module-meta">
+                      {module.category}
+                      {nodeMap.get(module.id)?.networkDtcs.length
+                        ? ` · ${nodeMap.get(module.id)?.networkDtcs.join(", ")}`
+                        : ""}
+                    </span>
                   </div>
                   <div className="module-node-status">
-                    <Badge tone={module.dtcCount > 0 ? "warning" : "success"}>
-                      {module.dtcCount > 0 ? `${module.dtcCount} DTCs` : "OK"}
-                    </Badge>
+                    {module.vinChecks?.some(
+                      (check) => check.status === "mismatch" || check.status === "blank"
+                    ) && <Badge tone="danger">VIN mismatch</Badge>}
+                    {module.status === "noResponse" ? (
+                      <Badge tone="danger">No response</Badge>
+                    ) : (
+                      <Badge tone={module.dtcCount > 0 ? "warning" : "success"}>
+                        {module.dtcCount > 0 ? `${module.dtcCount} DTCs` : "OK"}
+                      </Badge>
+                    )}
                   </div>
                 </button>
               );
@@ -7,10 +7,17 @@ export type AppPhase =
   | "ready"
   | "error";
 
-export type TransportMode = "simulation" | "j2534";
+export type TransportMode = "simulation" | "j2534" | "direct" | "socketCan";
 
 export type ModuleStatus = "ok" | "noResponse" | "error";
 
+export type DiagnosticSession = "default" | "programming" | "extended" | "safetySystem";
+
+export type AddressingMode =
+  | { mode: "normal" }
+  | { mode: "normalFixed" }
+  | { mode: "extended"; target: number; source: number };
+
 export interface ModuleInfo {
   id: string;
   name: string;
@@ -18,23 +25,165 @@ export interface ModuleInfo {
   category: string;
   txId: number;
   rxId: number;
+  addressing: AddressingMode;
   status: ModuleStatus;
   dtcCount: number;
+  identification: DidValue[];
+  session: DiagnosticSession;
+  securityLevel?: number | null;
+  vinChecks?: VinCheck[];
+}
+
+export type VinCheckStatus = "match" | "mismatch" | "blank" | "notSupported" | "noResponse";
+
+export interface VinCheck {
+  did: number;
+  vin?: string | null;
+  status: VinCheckStatus;
+}
+
+export interface DtcStatus {
+  testFailed: boolean;
+  testFailedThisOperationCycle: boolean;
+  pending: boolean;
+  confirmed: boolean;
+  testNotCompletedSinceLastClear: boolean;
+  testFailedSinceLastClear: boolean;
+  testNotCompletedThisOperationCycle: boolean;
+  warningIndicatorRequested: boolean;
+}
+
+export interface DidValue {
+  did: number;
+  name: string;
+  value: string;
+  raw: string;
+}
+
+export interface DtcSnapshot {
+  recordNumber: number;
+  values: DidValue[];
+  undecoded?: string | null;
+}
+
+export interface DtcExtendedData {
+  recordNumber: number;
+  name: string;
+  value: string;
+  raw: string;
 }
 
 export interface DtcInfo {
   code: string;
   description: string;
   status: string;
+  statusByte: number;
+  statusFlags: DtcStatus;
+  permanent: boolean;
+  snapshots: DtcSnapshot[];
+  extendedData: DtcExtendedData[];
 }
 
+export type BusAddressing = "normal11Bit" | "normal29Bit" | "normalFixed" | "extended";
+
 export interface BusInfo {
   name: string;
   modules: string[];
+  baudRate?: number | null;
+  addressing?: BusAddressing[];
+  tester?: boolean;
+}
+
+export interface TopologyNode {
+  id: string;
+  name: string;
+  bus: string;
+  category: string;
+  status: ModuleStatus;
+  dtcCount: number;
+  gateway: boolean;
+  networkDtcs: string[];
+}
+
+export interface GatewayLink {
+  gateway: string;
+  from: string;
+  to: string;
 }
 
 export interface TopologyGraph {
   buses: BusInfo[];
+  nodes?: TopologyNode[];
+  links?: GatewayLink[];
+}
+
+export type TopologyFormat = "json" | "dot";
+
+export interface VehicleInfo {
+  make: string;
+  model: string;
+  year: string;
+  trim?: string;
+  details?: Record<string, string>;
+  checkDigitValid?: boolean | null;
+}
+
+export type BaselineSource = "saved" | "profile";
+
+export type BaselineStatus =
+  | "match"
+  | "missing"
+  | "extra"
+  | "unexpectedAddress"
+  | "unexpectedBus"
+  | "identificationChanged";
+
+export interface IdentificationMismatch {
+  did: number;
+  expected: string;
+  found?: string | null;
+}
+
+export interface BaselineEntry {
+  name: string;
+  moduleId?: string | null;
+  status: BaselineStatus;
+  expectedBus?: string | null;
+  expectedAddress?: string | null;
+  foundBus?: string | null;
+  foundAddress?: string | null;
+  identification: IdentificationMismatch[];
+}
+
+export interface BaselineReport {
+  baselineId: string;
+  name: string;
+  source: BaselineSource;
+  entries: BaselineEntry[];
+}
+
+export interface BaselineModule {
+  name: string;
+  bus: string;
+  category: string;
+  txId: number;
+  rxId: number;
+  addressing?: AddressingMode;
+  identification?: Record<string, string>;
+}
+
+export interface Baseline {
+  id: string;
+  name: string;
+  vinPattern?: string | null;
+  created?: string | null;
+  modules: BaselineModule[];
+  source: BaselineSource;
+}
+
+export interface BaselineList {
+  baselines: Baseline[];
+  errors: string[];
 }
 
 export interface ProgressInfo {
@@ -60,7 +209,10 @@ export interface AppSnapshot {
   phase: AppPhase;
   transport: TransportMode;
   adapterConnected: boolean;
+  batteryVoltage?: number | null;
   vin?: string | null;
+  vehicle?: VehicleInfo | null;
+  baseline?: BaselineReport | null;
   modules: ModuleInfo[];
   dtcs: Record<string, DtcInfo[]>;
   topology: TopologyGraph;
@@ -71,8 +223,76 @@ export interface AppSnapshot {
   lastSession?: SessionSummary | null;
 }
 
+export interface ModuleCandidate {
+  txId: number;
+  rxId: number;
+  addressing?: AddressingMode;
+  name: string;
+  bus: string;
+  category: string;
+}
+
+export type InventorySource =
+  | { kind: "did"; did: number }
+  | { kind: "routine"; routineId: number; option?: number[] };
+
+export type InventoryFormat =
+  | { kind: "idList"; width: number; rxOffset: number }
+  | { kind: "bitmap"; modules: ModuleCandidate[] };
+
+export interface GatewaySpec {
+  txId: number;
+  rxId: number;
+  addressing?: AddressingMode;
+  source: InventorySource;
+  format: InventoryFormat;
+}
+
+export interface DiscoveryProfile {
+  id: string;
+  name: string;
+  wmis: string[];
+  minYear?: number | null;
+  maxYear?: number | null;
+  modules: ModuleCandidate[];
+  names: ModuleCandidate[];
+  gateway?: GatewaySpec | null;
+  source: "builtin" | "user";
+}
+
+export interface ProfileList {
+  profiles: DiscoveryProfile[];
+  selected: string | null;
+  errors: string[];
+}
+
 export interface AdapterStatus {
   available: boolean;
   message: string;
   dllPath?: string | null;
+  batteryVoltage?: number | null;
+  versions?: AdapterVersions | null;
+  deviceError?: string | null;
+}
+
+export type J2534DeviceSource = "environment" | "registry" | "config" | "knownPath";
+
+export interface J2534Device {
+  name: string;
+  vendor?: string | null;
+  library: string;
+  source: J2534DeviceSource;
+  protocols: string[];
+}
+
+export interface J2534DeviceList {
+  devices: J2534Device[];
+  selected?: string | null;
+  errors: string[];
+}
+
+export interface AdapterVersions {
+  firmware: string;
+  dll: string;
+  api: string;
 }
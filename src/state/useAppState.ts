@@ -9,9 +9,11 @@ const emptySnapshot: AppSnapshot = {
   transport: "simulation",
   adapterConnected: false,
   vin: null,
+  vehicle: null,
+  baseline: null,
   modules: [],
   dtcs: {},
-  topology: { buses: [] },
+  topology: { buses: [], nodes: [], links: [] },
   progress: null,
   lastError: null,
   sessionId: null,